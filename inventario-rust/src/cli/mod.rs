@@ -0,0 +1,678 @@
+// Modo de línea de comandos, para usar el inventario sin interfaz gráfica
+
+use std::collections::HashMap;
+use serde_json::json;
+
+use rustiventario::inventario::{InventarioError, InventarioManager};
+use rustiventario::inventario::compras::sugerir_reorden;
+use rustiventario::models::{Dinero, FormatoMoneda, Moneda, MotivoMovimiento, Producto};
+use rustiventario::storage::{CsvStorage, SqliteStorage};
+
+/// Texto de ayuda de la línea de comandos
+pub const USO: &str = "\
+uso: inventario-rust [--sqlite <ruta> | --archivo <ruta>]
+     inventario-rust <comando> [--sqlite <ruta> | --archivo <ruta>] [--json]
+
+Sin comando se abre la interfaz gráfica. Los comandos usan inventario.csv
+si no se indica otro almacenamiento. Los montos admiten decimales (19.99) y,
+sin --moneda, están en la moneda de la mayoría de los productos.
+
+comandos:
+  add <nombre> --precio <monto> --stock <n> [--moneda <código>] [--tags a,b] [--punto-reorden <n>] [--sku <sku>] [--barcode <código>]
+  list
+  update-stock <id> <stock> [--motivo venta|compra|ajuste|devolucion] [--nota <texto>]
+  transfer <id> <cantidad> [--from <ubicación>] [--to <ubicación>] [--nota <texto>]
+  locations [--add <nombre>]
+  set-code <id> [--sku <sku>] [--barcode <código>]   (un valor vacío quita el código)
+  remove <id>
+  search (--tag <consulta> | --name <texto> | --code <sku o código de barras>)
+  low-stock
+  reorder                                            (cuánto pedir de cada producto con bajo stock)
+  price-range <min> <max> [--moneda <código>] [--page <n>] [--per-page <n>]
+  top (--expensive <n> | --cheap <n>)
+  help";
+
+/// Archivo que usan los comandos si no se indica ninguno
+const ARCHIVO_POR_DEFECTO: &str = "inventario.csv";
+
+/// Productos por página en `price-range` si no se indica otra cantidad
+const POR_PAGINA: usize = 20;
+
+/// Dónde se guarda el inventario
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Origen {
+    /// Sin almacenamiento; la interfaz abre el último archivo usado y lo
+    /// guarda sola (ver `DiarioStorage`)
+    Memoria,
+    /// Archivo CSV o JSON, reescrito en cada cambio
+    Archivo(String),
+    /// Base SQLite
+    Sqlite(String),
+}
+
+impl Origen {
+    /// Crear el gestor cargando desde este origen
+    pub fn abrir(&self) -> Result<InventarioManager, InventarioError> {
+        match self {
+            Origen::Memoria => Ok(InventarioManager::new()),
+            Origen::Archivo(ruta) => InventarioManager::con_almacenamiento(Box::new(CsvStorage::new(ruta))),
+            Origen::Sqlite(ruta) => InventarioManager::con_almacenamiento(Box::new(SqliteStorage::abrir(ruta)?)),
+        }
+    }
+}
+
+/// Operación pedida desde la línea de comandos
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Comando {
+    /// El precio se interpreta al ejecutar, en `moneda` o en la moneda
+    /// principal del inventario
+    Agregar {
+        nombre: String,
+        precio: String,
+        moneda: Option<Moneda>,
+        stock: u32,
+        tags: Vec<String>,
+        punto_reorden: Option<u32>,
+        sku: Option<String>,
+        codigo_barras: Option<String>,
+    },
+    Listar,
+    ActualizarStock {
+        id: u32,
+        stock: u32,
+        motivo: MotivoMovimiento,
+        nota: Option<String>,
+    },
+    /// Cambiar los códigos de un producto; `None` conserva el actual y
+    /// un texto vacío lo quita
+    Codigos {
+        id: u32,
+        sku: Option<String>,
+        codigo_barras: Option<String>,
+    },
+    Eliminar(u32),
+    /// Mover unidades entre ubicaciones; `None` es el stock sin ubicación
+    Transferir {
+        id: u32,
+        cantidad: u32,
+        origen: Option<String>,
+        destino: Option<String>,
+        nota: Option<String>,
+    },
+    /// Listar las ubicaciones con su stock, dando de alta una antes si se indica
+    Ubicaciones { agregar: Option<String> },
+    /// Consulta de tags, por ejemplo `periféricos AND NOT móviles`
+    BuscarTag(String),
+    /// Búsqueda aproximada por nombre, ordenada por relevancia
+    BuscarNombre(String),
+    /// Búsqueda exacta por SKU o código de barras
+    BuscarCodigo(String),
+    BajoStock,
+    /// Sugerencias de reposición de los productos con bajo stock
+    Reponer,
+    /// Productos con precio entre `min` y `max`, ordenados por precio;
+    /// con `pagina` solo se muestra esa página (empezando en 1)
+    RangoPrecio {
+        min: String,
+        max: String,
+        moneda: Option<Moneda>,
+        pagina: Option<usize>,
+        por_pagina: usize,
+    },
+    /// Los `n` productos más caros (o más baratos)
+    Top { n: usize, caros: bool },
+    Ayuda,
+}
+
+/// Qué hacer al arrancar, según los argumentos
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Invocacion {
+    /// Abrir la interfaz gráfica
+    Interfaz(Origen),
+    /// Ejecutar un comando e imprimir el resultado, en JSON si `json`
+    Comando { origen: Origen, comando: Comando, json: bool },
+}
+
+/// Interpretar los argumentos del programa (sin el nombre del ejecutable)
+pub fn parsear(args: &[String]) -> Result<Invocacion, InventarioError> {
+    let mut origen = Origen::Memoria;
+    let mut json = false;
+    let mut posicionales = Vec::new();
+    let mut opciones = HashMap::new();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--json" => json = true,
+            "--help" | "-h" => posicionales.insert(0, "help".to_string()),
+            opcion if opcion.starts_with("--") => {
+                let valor = args.next()
+                    .ok_or_else(|| error_uso(format!("falta el valor de {}", opcion)))?
+                    .clone();
+                match opcion {
+                    "--archivo" => origen = Origen::Archivo(valor),
+                    "--sqlite" => origen = Origen::Sqlite(valor),
+                    _ => {
+                        opciones.insert(opcion.trim_start_matches("--").to_string(), valor);
+                    }
+                }
+            }
+            _ => posicionales.push(arg.clone()),
+        }
+    }
+
+    let Some(nombre_comando) = posicionales.first().cloned() else {
+        if json || !opciones.is_empty() {
+            return Err(error_uso("falta el comando".to_string()));
+        }
+        return Ok(Invocacion::Interfaz(origen));
+    };
+
+    let mut argumentos = Argumentos { posicionales: posicionales[1..].to_vec(), opciones };
+    let comando = match nombre_comando.as_str() {
+        "add" => Comando::Agregar {
+            nombre: argumentos.posicional(0, "nombre")?,
+            precio: argumentos.opcion("precio")
+                .ok_or_else(|| error_uso("falta --precio".to_string()))?,
+            moneda: argumentos.moneda()?,
+            stock: argumentos.numero("stock")?
+                .ok_or_else(|| error_uso("falta --stock".to_string()))?,
+            tags: argumentos.opcion("tags")
+                .map(|tags| tags.split(',')
+                    .map(|t| t.trim().to_string())
+                    .filter(|t| !t.is_empty())
+                    .collect())
+                .unwrap_or_default(),
+            punto_reorden: argumentos.numero("punto-reorden")?,
+            sku: argumentos.opcion("sku"),
+            codigo_barras: argumentos.opcion("barcode"),
+        },
+        "list" => Comando::Listar,
+        "update-stock" => Comando::ActualizarStock {
+            id: parsear_numero("id", &argumentos.posicional(0, "id")?)?,
+            stock: parsear_numero("stock", &argumentos.posicional(1, "stock")?)?,
+            motivo: match argumentos.opcion("motivo") {
+                Some(clave) => MotivoMovimiento::desde_clave(&clave)
+                    .ok_or_else(|| error_uso(format!("motivo desconocido: \"{}\"", clave)))?,
+                None => MotivoMovimiento::Ajuste,
+            },
+            nota: argumentos.opcion("nota"),
+        },
+        "set-code" => Comando::Codigos {
+            id: parsear_numero("id", &argumentos.posicional(0, "id")?)?,
+            sku: argumentos.opcion("sku"),
+            codigo_barras: argumentos.opcion("barcode"),
+        },
+        "remove" => Comando::Eliminar(parsear_numero("id", &argumentos.posicional(0, "id")?)?),
+        "transfer" => Comando::Transferir {
+            id: parsear_numero("id", &argumentos.posicional(0, "id")?)?,
+            cantidad: parsear_numero("cantidad", &argumentos.posicional(1, "cantidad")?)?,
+            origen: argumentos.opcion("from"),
+            destino: argumentos.opcion("to"),
+            nota: argumentos.opcion("nota"),
+        },
+        "locations" => Comando::Ubicaciones { agregar: argumentos.opcion("add") },
+        "search" => match (argumentos.opcion("tag"), argumentos.opcion("name"), argumentos.opcion("code")) {
+            (Some(tag), None, None) => Comando::BuscarTag(tag),
+            (None, Some(nombre), None) => Comando::BuscarNombre(nombre),
+            (None, None, Some(codigo)) => Comando::BuscarCodigo(codigo),
+            _ => return Err(error_uso("indicar --tag <tag>, --name <texto> o --code <código>".to_string())),
+        },
+        "low-stock" => Comando::BajoStock,
+        "reorder" => Comando::Reponer,
+        "price-range" => Comando::RangoPrecio {
+            min: argumentos.posicional(0, "min")?,
+            max: argumentos.posicional(1, "max")?,
+            moneda: argumentos.moneda()?,
+            pagina: match argumentos.numero("page")? {
+                Some(0) => return Err(error_uso("las páginas empiezan en 1".to_string())),
+                pagina => pagina.map(|p| p as usize),
+            },
+            por_pagina: argumentos.numero("per-page")?.map_or(POR_PAGINA, |n| n as usize),
+        },
+        "top" => match (argumentos.numero("expensive")?, argumentos.numero("cheap")?) {
+            (Some(n), None) => Comando::Top { n: n as usize, caros: true },
+            (None, Some(n)) => Comando::Top { n: n as usize, caros: false },
+            _ => return Err(error_uso("indicar --expensive <n> o --cheap <n>".to_string())),
+        },
+        "help" => Comando::Ayuda,
+        otro => return Err(error_uso(format!("comando desconocido: \"{}\"", otro))),
+    };
+    argumentos.comprobar_sin_sobrantes(&nombre_comando)?;
+
+    // Los comandos siempre trabajan sobre algún archivo
+    if origen == Origen::Memoria {
+        origen = Origen::Archivo(ARCHIVO_POR_DEFECTO.to_string());
+    }
+
+    Ok(Invocacion::Comando { origen, comando, json })
+}
+
+/// Ejecutar un comando sobre el inventario, devolviendo el texto a imprimir.
+/// Los cambios quedan guardados por el almacenamiento del gestor y los
+/// precios de las tablas se muestran con `formato`.
+pub fn ejecutar(
+    inventario: &mut InventarioManager,
+    comando: Comando,
+    json: bool,
+    formato: &FormatoMoneda,
+) -> Result<String, InventarioError> {
+    let salida = match comando {
+        Comando::Agregar { nombre, precio, moneda, stock, tags, punto_reorden, sku, codigo_barras } => {
+            let precio = parsear_monto("precio", &precio, moneda.unwrap_or_else(|| inventario.moneda_principal()))?;
+            // Los códigos se comprueban antes para no dejar el producto a medias
+            inventario.comprobar_codigos(None, sku.as_deref(), codigo_barras.as_deref())?;
+            let id = inventario.agregar_producto(nombre, precio, stock, tags, punto_reorden)?;
+            if sku.is_some() || codigo_barras.is_some() {
+                inventario.establecer_codigos(id, sku.as_deref(), codigo_barras.as_deref())?;
+            }
+            if json {
+                json!({ "id": id }).to_string()
+            } else {
+                format!("Producto agregado con ID {}", id)
+            }
+        }
+        Comando::Listar => {
+            let productos = inventario.obtener_todos_productos();
+            listado(inventario, productos, json, formato)
+        }
+        Comando::ActualizarStock { id, stock, motivo, nota } => {
+            inventario.actualizar_stock(id, stock, motivo, nota)?;
+            if json {
+                let producto = inventario.obtener_todos_productos().into_iter().find(|p| p.id == id);
+                serde_json::to_string_pretty(&producto).unwrap_or_default()
+            } else {
+                format!("Stock del producto ID {} ahora es {}", id, stock)
+            }
+        }
+        Comando::Codigos { id, sku, codigo_barras } => {
+            let producto = inventario.obtener_todos_productos().into_iter().find(|p| p.id == id)
+                .ok_or(InventarioError::NoEncontrado(id))?;
+            let sku = sku.or_else(|| producto.sku.clone());
+            let codigo_barras = codigo_barras.or_else(|| producto.codigo_barras.clone());
+            inventario.establecer_codigos(id, sku.as_deref(), codigo_barras.as_deref())?;
+            if json {
+                let producto = inventario.obtener_todos_productos().into_iter().find(|p| p.id == id);
+                serde_json::to_string_pretty(&producto).unwrap_or_default()
+            } else {
+                format!("Códigos del producto ID {} actualizados", id)
+            }
+        }
+        Comando::Eliminar(id) => {
+            inventario.eliminar_producto(id)?;
+            if json {
+                json!({ "eliminado": id }).to_string()
+            } else {
+                format!("Producto ID {} eliminado", id)
+            }
+        }
+        Comando::Transferir { id, cantidad, origen, destino, nota } => {
+            inventario.transferir(id, origen.as_deref(), destino.as_deref(), cantidad, nota)?;
+            if json {
+                let producto = inventario.obtener_todos_productos().into_iter().find(|p| p.id == id);
+                serde_json::to_string_pretty(&producto).unwrap_or_default()
+            } else {
+                format!(
+                    "{} unidades del producto ID {} transferidas de {} a {}",
+                    cantidad,
+                    id,
+                    origen.as_deref().unwrap_or("sin ubicación"),
+                    destino.as_deref().unwrap_or("sin ubicación")
+                )
+            }
+        }
+        Comando::Ubicaciones { agregar } => {
+            if let Some(nombre) = agregar {
+                inventario.agregar_ubicacion(&nombre)?;
+            }
+            let totales = inventario.totales_por_ubicacion();
+            let sin_ubicacion = inventario.stock_sin_ubicacion();
+            if json {
+                json!({ "ubicaciones": totales, "sin_ubicacion": sin_ubicacion }).to_string()
+            } else {
+                let ancho = totales.keys().map(|n| n.chars().count()).max().unwrap_or(0).max("Sin ubicación".chars().count());
+                totales.iter()
+                    .map(|(nombre, total)| (nombre.as_str(), *total))
+                    .chain([("Sin ubicación", sin_ubicacion)])
+                    .map(|(nombre, total)| format!("{:<ancho$}  {}", nombre, total, ancho = ancho))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            }
+        }
+        Comando::BuscarTag(tag) => {
+            let productos = inventario.buscar_por_tags(&tag)?;
+            listado(inventario, productos, json, formato)
+        }
+        Comando::BuscarNombre(consulta) => {
+            // Se respeta el orden por relevancia
+            let productos = inventario.buscar_por_nombre(&consulta);
+            if json {
+                serde_json::to_string_pretty(&productos).unwrap_or_default()
+            } else {
+                tabla(inventario, &productos, formato)
+            }
+        }
+        Comando::BuscarCodigo(codigo) => {
+            let productos: Vec<&Producto> = inventario.buscar_por_codigo(&codigo).into_iter().collect();
+            listado(inventario, productos, json, formato)
+        }
+        Comando::BajoStock => {
+            let productos = inventario.productos_con_bajo_stock();
+            listado(inventario, productos, json, formato)
+        }
+        Comando::Reponer => {
+            let sugerencias = sugerir_reorden(inventario);
+            if json {
+                let filas: Vec<_> = sugerencias.iter()
+                    .map(|s| json!({
+                        "producto_id": s.producto_id,
+                        "nombre": s.nombre,
+                        "proveedor_id": s.proveedor_id,
+                        "stock": s.stock,
+                        "punto_reorden": s.punto_reorden,
+                        "pendiente": s.pendiente,
+                        "cantidad": s.cantidad,
+                    }))
+                    .collect();
+                serde_json::to_string_pretty(&filas).unwrap_or_default()
+            } else if sugerencias.is_empty() {
+                "No hace falta reponer nada.".to_string()
+            } else {
+                sugerencias.iter()
+                    .map(|s| {
+                        let proveedor = s.proveedor_id
+                            .and_then(|id| inventario.proveedor(id))
+                            .map_or("sin proveedor", |p| p.nombre.as_str());
+                        let pedido = if s.pendiente > 0 { format!(", {} ya pedidas", s.pendiente) } else { String::new() };
+                        format!("{} × {} (ID {}, {}; stock {}{})", s.cantidad, s.nombre, s.producto_id, proveedor, s.stock, pedido)
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            }
+        }
+        Comando::RangoPrecio { min, max, moneda, pagina, por_pagina } => {
+            let moneda = moneda.unwrap_or_else(|| inventario.moneda_principal());
+            let min = parsear_monto("min", &min, moneda)?;
+            let max = parsear_monto("max", &max, moneda)?;
+            let productos = match pagina {
+                // Sin dónde quedó la página anterior, se avanza desde la primera
+                Some(pagina) => {
+                    let mut productos = Vec::new();
+                    let mut ultimo = None;
+                    for _ in 0..pagina {
+                        productos = inventario.pagina_en_rango(min, max, ultimo, por_pagina);
+                        ultimo = productos.last().map(|p| (p.precio, p.id));
+                        if ultimo.is_none() {
+                            break;
+                        }
+                    }
+                    productos
+                }
+                None => inventario.productos_en_rango(min, max),
+            };
+            if json {
+                serde_json::to_string_pretty(&productos).unwrap_or_default()
+            } else if let Some(pagina) = pagina {
+                let total = inventario.cantidad_en_rango(min, max);
+                let paginas = total.div_ceil(por_pagina.max(1)).max(1);
+                format!("{}\n\nPágina {} de {} ({} productos)", tabla(inventario, &productos, formato), pagina, paginas, total)
+            } else {
+                tabla(inventario, &productos, formato)
+            }
+        }
+        Comando::Top { n, caros } => {
+            // Ya vienen ordenados por precio, así que no se reordenan por ID
+            let productos = if caros {
+                inventario.productos_mas_caros(n)
+            } else {
+                inventario.productos_mas_baratos(n)
+            };
+            if json {
+                serde_json::to_string_pretty(&productos).unwrap_or_default()
+            } else {
+                tabla(inventario, &productos, formato)
+            }
+        }
+        Comando::Ayuda => USO.to_string(),
+    };
+
+    Ok(salida)
+}
+
+// Argumentos de un comando, que se van consumiendo al interpretarlo
+struct Argumentos {
+    posicionales: Vec<String>,
+    opciones: HashMap<String, String>,
+}
+
+impl Argumentos {
+    // Método privado para leer un argumento posicional obligatorio
+    fn posicional(&mut self, indice: usize, nombre: &str) -> Result<String, InventarioError> {
+        self.posicionales.get_mut(indice)
+            .map(std::mem::take)
+            .ok_or_else(|| error_uso(format!("falta <{}>", nombre)))
+    }
+
+    // Método privado para leer una opción `--nombre valor`
+    fn opcion(&mut self, nombre: &str) -> Option<String> {
+        self.opciones.remove(nombre)
+    }
+
+    // Método privado para leer una opción numérica
+    fn numero(&mut self, nombre: &str) -> Result<Option<u32>, InventarioError> {
+        self.opcion(nombre)
+            .map(|valor| parsear_numero(nombre, &valor))
+            .transpose()
+    }
+
+    // Método privado para leer la opción `--moneda`
+    fn moneda(&mut self) -> Result<Option<Moneda>, InventarioError> {
+        self.opcion("moneda")
+            .map(|codigo| Moneda::parsear(&codigo).map_err(error_uso))
+            .transpose()
+    }
+
+    // Método privado para rechazar argumentos que el comando no usa
+    fn comprobar_sin_sobrantes(&self, comando: &str) -> Result<(), InventarioError> {
+        let posicionales = self.posicionales.iter().filter(|p| !p.is_empty());
+        let opciones = self.opciones.keys().map(|o| format!("--{}", o));
+        let sobrantes: Vec<String> = posicionales.cloned().chain(opciones).collect();
+
+        if sobrantes.is_empty() {
+            Ok(())
+        } else {
+            Err(error_uso(format!("argumentos no válidos para {}: {}", comando, sobrantes.join(" "))))
+        }
+    }
+}
+
+// Productos ordenados por ID, como tabla o JSON
+fn listado(inventario: &InventarioManager, mut productos: Vec<&Producto>, json: bool, formato: &FormatoMoneda) -> String {
+    productos.sort_by_key(|p| p.id);
+
+    if json {
+        serde_json::to_string_pretty(&productos).unwrap_or_default()
+    } else {
+        tabla(inventario, &productos, formato)
+    }
+}
+
+// Tabla de texto con columnas alineadas; el bajo stock se marca con "!"
+fn tabla(inventario: &InventarioManager, productos: &[&Producto], formato: &FormatoMoneda) -> String {
+    if productos.is_empty() {
+        return "No hay productos.".to_string();
+    }
+
+    let mut filas = vec![["ID", "Nombre", "Precio", "Stock", "Reorden", "Etiquetas"].map(String::from)];
+    for producto in productos {
+        let mut tags: Vec<&str> = producto.tags.iter().map(String::as_str).collect();
+        tags.sort_unstable();
+        let marca = if inventario.tiene_bajo_stock(producto.id) { " !" } else { "" };
+
+        filas.push([
+            producto.id.to_string(),
+            producto.nombre_completo(),
+            producto.precio.formatear(formato),
+            format!("{}{}", producto.stock, marca),
+            inventario.punto_reorden_de(producto.id).unwrap_or_default().to_string(),
+            tags.join(", "),
+        ]);
+    }
+
+    let mut anchos = [0; 6];
+    for fila in &filas {
+        for (ancho, celda) in anchos.iter_mut().zip(fila) {
+            *ancho = (*ancho).max(celda.chars().count());
+        }
+    }
+
+    filas.iter()
+        .map(|fila| {
+            let celdas: Vec<String> = fila.iter()
+                .zip(anchos)
+                .map(|(celda, ancho)| format!("{:<ancho$}", celda, ancho = ancho))
+                .collect();
+            celdas.join("  ").trim_end().to_string()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn parsear_numero(nombre: &str, valor: &str) -> Result<u32, InventarioError> {
+    valor.trim().parse()
+        .map_err(|_| error_uso(format!("{} inválido: \"{}\"", nombre, valor)))
+}
+
+fn parsear_monto(nombre: &str, valor: &str, moneda: Moneda) -> Result<Dinero, InventarioError> {
+    Dinero::parsear(valor, moneda)
+        .map_err(|motivo| error_uso(format!("{}: {}", nombre, motivo)))
+}
+
+fn error_uso(mensaje: String) -> InventarioError {
+    InventarioError::Validacion(format!("{}\n\n{}", mensaje, USO))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(texto: &str) -> Vec<String> {
+        texto.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn sin_comando_abre_la_interfaz() {
+        assert_eq!(parsear(&args("")).unwrap(), Invocacion::Interfaz(Origen::Memoria));
+        assert_eq!(
+            parsear(&args("--sqlite inv.db")).unwrap(),
+            Invocacion::Interfaz(Origen::Sqlite("inv.db".to_string()))
+        );
+    }
+
+    #[test]
+    fn interpreta_comandos_y_opciones() {
+        assert_eq!(
+            parsear(&args("add Mouse --precio 25,50 --moneda eur --stock 3 --tags a,b --archivo x.json --json")).unwrap(),
+            Invocacion::Comando {
+                origen: Origen::Archivo("x.json".to_string()),
+                comando: Comando::Agregar {
+                    nombre: "Mouse".to_string(),
+                    precio: "25,50".to_string(),
+                    moneda: Some(Moneda::parsear("EUR").unwrap()),
+                    stock: 3,
+                    tags: vec!["a".to_string(), "b".to_string()],
+                    punto_reorden: None,
+                    sku: None,
+                    codigo_barras: None,
+                },
+                json: true,
+            }
+        );
+        assert_eq!(
+            parsear(&args("update-stock 4 10 --motivo compra")).unwrap(),
+            Invocacion::Comando {
+                origen: Origen::Archivo(ARCHIVO_POR_DEFECTO.to_string()),
+                comando: Comando::ActualizarStock { id: 4, stock: 10, motivo: MotivoMovimiento::Compra, nota: None },
+                json: false,
+            }
+        );
+
+        assert_eq!(
+            parsear(&args("transfer 2 5 --to Tienda")).unwrap(),
+            Invocacion::Comando {
+                origen: Origen::Archivo(ARCHIVO_POR_DEFECTO.to_string()),
+                comando: Comando::Transferir { id: 2, cantidad: 5, origen: None, destino: Some("Tienda".to_string()), nota: None },
+                json: false,
+            }
+        );
+
+        assert!(parsear(&args("add Mouse --stock 3")).is_err());
+        assert!(parsear(&args("add Mouse --precio 1 --stock 3 --moneda dólar")).is_err());
+        assert!(parsear(&args("remove 1 2")).is_err());
+        assert!(parsear(&args("top --expensive 3 --cheap 2")).is_err());
+        assert!(parsear(&args("list --tag a")).is_err());
+    }
+
+    #[test]
+    fn los_comandos_guardan_en_el_archivo() {
+        let ruta = std::env::temp_dir().join("rustiventario_cli.csv");
+        let ruta = ruta.to_str().unwrap();
+        let _ = std::fs::remove_file(ruta);
+        let origen = Origen::Archivo(ruta.to_string());
+
+        let formato = FormatoMoneda::desde_locale("es_AR.UTF-8");
+
+        let mut inventario = origen.abrir().unwrap();
+        ejecutar(&mut inventario, Comando::Agregar {
+            nombre: "Mouse".to_string(),
+            precio: "1234.5".to_string(),
+            moneda: None,
+            stock: 2,
+            tags: vec!["periféricos".to_string()],
+            punto_reorden: None,
+            sku: Some("MOU-1".to_string()),
+            codigo_barras: None,
+        }, false, &formato).unwrap();
+
+        // Otra ejecución ve el producto guardado
+        let mut inventario = origen.abrir().unwrap();
+        let salida = ejecutar(&mut inventario, Comando::BajoStock, false, &formato).unwrap();
+        assert_eq!(salida, "\
+ID  Nombre  Precio     Stock  Reorden  Etiquetas
+1   Mouse   $1.234,50  2 !    4        periféricos");
+
+        let salida = ejecutar(&mut inventario, Comando::Reponer, false, &formato).unwrap();
+        assert_eq!(salida, "6 × Mouse (ID 1, sin proveedor; stock 2)");
+
+        let salida = ejecutar(&mut inventario, Comando::Top { n: 5, caros: true }, true, &formato).unwrap();
+        let productos: Vec<Producto> = serde_json::from_str(&salida).unwrap();
+        assert_eq!(productos.len(), 1);
+
+        // Buscar por el SKU con el que se agregó, o por el código de barras asignado después
+        ejecutar(&mut inventario, Comando::Codigos { id: 1, sku: None, codigo_barras: Some("036000291452".to_string()) }, false, &formato).unwrap();
+        let salida = ejecutar(&mut inventario, Comando::BuscarCodigo("mou-1".to_string()), true, &formato).unwrap();
+        let productos: Vec<Producto> = serde_json::from_str(&salida).unwrap();
+        assert_eq!(productos[0].codigo_barras.as_deref(), Some("036000291452"));
+        assert!(ejecutar(&mut inventario, Comando::Codigos { id: 1, sku: None, codigo_barras: Some("123".to_string()) }, false, &formato).is_err());
+
+        // Las ubicaciones y transferencias también quedan guardadas
+        ejecutar(&mut inventario, Comando::Ubicaciones { agregar: Some("Tienda".to_string()) }, false, &formato).unwrap();
+        ejecutar(&mut inventario, Comando::Transferir {
+            id: 1,
+            cantidad: 2,
+            origen: None,
+            destino: Some("Tienda".to_string()),
+            nota: None,
+        }, false, &formato).unwrap();
+        let mut inventario = origen.abrir().unwrap();
+        let salida = ejecutar(&mut inventario, Comando::Ubicaciones { agregar: None }, false, &formato).unwrap();
+        assert_eq!(salida, "Tienda         2\nSin ubicación  0");
+
+        std::fs::remove_file(ruta).unwrap();
+        let _ = std::fs::remove_file(rustiventario::utils::file_io::ruta_movimientos(ruta));
+        let _ = std::fs::remove_file(rustiventario::utils::file_io::ruta_umbrales(ruta));
+        let _ = std::fs::remove_file(rustiventario::utils::file_io::ruta_ubicaciones(ruta));
+        let _ = std::fs::remove_file(rustiventario::utils::file_io::ruta_proveedores(ruta));
+        let _ = std::fs::remove_file(rustiventario::utils::file_io::ruta_ordenes(ruta));
+    }
+}
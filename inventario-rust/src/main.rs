@@ -0,0 +1,42 @@
+mod ui;
+mod cli;
+
+use cli::Invocacion;
+use rustiventario::models::FormatoMoneda;
+use ui::app::{InventarioApp, NOMBRE_APP};
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    
+    // Con un comando se trabaja sin interfaz gráfica
+    let resultado = cli::parsear(&args).and_then(|invocacion| match invocacion {
+        Invocacion::Interfaz(origen) => origen.abrir(),
+        Invocacion::Comando { origen, comando, json } => {
+            let mut manager = origen.abrir()?;
+            let salida = cli::ejecutar(&mut manager, comando, json, &FormatoMoneda::desde_entorno())?;
+            println!("{}", salida);
+            std::process::exit(0);
+        }
+    });
+    
+    let manager = match resultado {
+        Ok(manager) => manager,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    
+    let native_options = eframe::NativeOptions {
+        viewport: eframe::egui::ViewportBuilder::default()
+            .with_inner_size(egui::vec2(1024.0, 768.0))
+            .with_min_inner_size(egui::vec2(800.0, 600.0)),
+        ..Default::default()
+    };
+    
+    eframe::run_native(
+        NOMBRE_APP,
+        native_options,
+        Box::new(|cc| Box::new(InventarioApp::new(cc, manager))),
+    ).unwrap();
+}
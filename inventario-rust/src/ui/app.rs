@@ -0,0 +1,2409 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::mpsc::Receiver;
+use std::sync::{Arc, Mutex};
+use chrono::{Local, TimeZone};
+use egui::{Color32, RichText, ScrollArea, Ui};
+use egui_extras::{Column, TableBuilder};
+use rfd::FileDialog;
+
+use rustiventario::inventario::{ConsultaTags, EventoInventario, InventarioError, InventarioManager};
+use rustiventario::inventario::comparacion::{self, Campo, Comparacion};
+use rustiventario::inventario::compras::{crear_ordenes_sugeridas, sugerir_reorden};
+use rustiventario::inventario::importacion::{self, ClaveImportacion, OpcionesImportacion, PlanImportacion};
+use rustiventario::inventario::reportes::{self, MetodoValuacion};
+use rustiventario::inventario::ventas::{self, Carrito, TasaImpuesto, Venta};
+use rustiventario::models::{Dinero, EstadoOrden, FormatoMoneda, LineaOrden, Moneda, MotivoMovimiento, Producto};
+use rustiventario::storage::DiarioStorage;
+use rustiventario::utils::file_io::{exportar_recibo, exportar_reporte, ruta_diario, AccionIncidencia, ModoCarga, ReporteCarga};
+
+/// Título de la ventana; también nombra la carpeta donde eframe guarda su estado
+pub const NOMBRE_APP: &str = "Sistema de Gestión de Inventario";
+
+/// Clave con la que se recuerda el último archivo abierto
+const CLAVE_ULTIMO_ARCHIVO: &str = "ultimo_archivo";
+
+pub struct InventarioApp {
+    inventario: Arc<Mutex<InventarioManager>>,
+    // Cambios avisados por el inventario, atendidos al empezar cada cuadro
+    eventos: Receiver<EventoInventario>,
+    // Sube con cada aviso del inventario, para saber si algo cambió
+    generacion: u64,
+    filtro_nombre: String,
+    // IDs encontrados por nombre, con el texto y la generación en que se buscó
+    busqueda_nombre: Option<(String, u64, Vec<u32>)>,
+    filtro_tag: String,
+    filtro_precio_min: String,
+    filtro_precio_max: String,
+    // Ubicación cuyo stock se muestra en la tabla; `None` = todas
+    filtro_ubicacion: Option<String>,
+    mostrar_bajo_stock: bool,
+    mensaje: Option<String>,
+    carga_estricta: bool,
+    reporte_carga: Option<ReporteCarga>,
+    // Importación por combinar, con su vista previa
+    importacion: Option<Importacion>,
+    comparacion: Option<VistaComparacion>,
+    // Estados para el formulario de agregar producto
+    nuevo_producto: NuevoProducto,
+    modo_edicion: Option<u32>,
+    // Producto cuyo historial se muestra en el panel derecho
+    producto_seleccionado: Option<u32>,
+    // Productos con variantes desplegados en la tabla
+    variantes_abiertas: HashSet<u32>,
+    nueva_variante: Option<NuevaVariante>,
+    nuevo_movimiento: NuevoMovimiento,
+    nueva_transferencia: NuevaTransferencia,
+    // Tag al que se le va a asignar un punto de reorden
+    nuevo_umbral_tag: String,
+    nueva_ubicacion: String,
+    // Lo que escribe el lector de códigos de barras (o el usuario)
+    codigo_escaneado: String,
+    // Producto encontrado por código, al que hay que desplazar la tabla
+    desplazar_a: Option<u32>,
+    // Separadores y posición del símbolo según el locale del sistema
+    formato_moneda: FormatoMoneda,
+    // Ventana de reportes de valuación
+    reportes_abiertos: bool,
+    metodo_valuacion: MetodoValuacion,
+    // Ventana de proveedores y órdenes de compra
+    compras_abiertas: bool,
+    nuevo_proveedor: NuevoProveedor,
+    nueva_orden: NuevaOrden,
+    // Unidades recibidas escritas para cada (orden, producto)
+    recepcion: HashMap<(u32, u32), String>,
+    ubicacion_recepcion: Option<String>,
+    // Ventana de caja para registrar ventas
+    caja_abierta: bool,
+    carrito: Carrito,
+    busqueda_caja: String,
+    // Porcentaje de impuestos, como lo escribe el usuario
+    tasa_impuesto: String,
+    ubicacion_caja: Option<String>,
+    // Venta recién cobrada, cuyo recibo se muestra hasta empezar otra
+    ultima_venta: Option<Venta>,
+    // Sin --archivo ni --sqlite la interfaz guarda sola en el archivo
+    // abierto, con un diario de cambios (ver `DiarioStorage`)
+    autoguardado: bool,
+    archivo_actual: Option<String>,
+}
+
+struct NuevoProducto {
+    nombre: String,
+    precio: String,
+    // Código ISO; vacío = la moneda principal del inventario
+    moneda: String,
+    // Costo unitario, en la moneda del precio; vacío = sin costo
+    costo: String,
+    stock: String,
+    tags: String,
+    punto_reorden: String,
+    sku: String,
+    codigo_barras: String,
+}
+
+struct NuevoMovimiento {
+    cantidad: String,
+    motivo: MotivoMovimiento,
+    nota: String,
+    // Costo unitario de una compra; vacío = sin costo
+    costo: String,
+    // `None` = stock sin ubicación
+    ubicacion: Option<String>,
+}
+
+struct NuevaVariante {
+    padre_id: u32,
+    nombre: String,
+    // Como `talla=M, color=rojo`
+    atributos: String,
+    stock: String,
+    // Vacío = el precio del producto principal
+    precio: String,
+}
+
+struct Importacion {
+    ruta: String,
+    opciones: OpcionesImportacion,
+    // Plan con las opciones elegidas, o el error al leer el archivo
+    plan: Result<PlanImportacion, String>,
+}
+
+struct VistaComparacion {
+    antes: String,
+    despues: String,
+    resultado: Result<Comparacion, String>,
+}
+
+#[derive(Default)]
+struct NuevoProveedor {
+    nombre: String,
+    contacto: String,
+}
+
+#[derive(Default)]
+struct NuevaOrden {
+    proveedor_id: Option<u32>,
+    producto_id: String,
+    cantidad: String,
+    lineas: Vec<LineaOrden>,
+}
+
+#[derive(Default)]
+struct NuevaTransferencia {
+    cantidad: String,
+    origen: Option<String>,
+    destino: Option<String>,
+}
+
+impl Default for NuevoMovimiento {
+    fn default() -> Self {
+        Self {
+            cantidad: String::new(),
+            motivo: MotivoMovimiento::Venta,
+            nota: String::new(),
+            costo: String::new(),
+            ubicacion: None,
+        }
+    }
+}
+
+impl Default for NuevoProducto {
+    fn default() -> Self {
+        Self {
+            nombre: String::new(),
+            precio: String::new(),
+            moneda: String::new(),
+            costo: String::new(),
+            stock: String::new(),
+            tags: String::new(),
+            punto_reorden: String::new(),
+            sku: String::new(),
+            codigo_barras: String::new(),
+        }
+    }
+}
+
+impl InventarioApp {
+    pub fn new(cc: &eframe::CreationContext<'_>, inventario: InventarioManager) -> Self {
+        // Personalizar la fuente si lo deseamos
+        cc.egui_ctx.style_mut(|style| {
+            style.text_styles.get_mut(&egui::TextStyle::Body).unwrap().size = 16.0;
+        });
+        
+        // Sin almacenamiento, retomar el último archivo con lo que haya quedado en su diario
+        let autoguardado = inventario.descripcion_almacenamiento().is_none();
+        let (mut inventario, archivo_actual, mensaje) = if autoguardado {
+            let ultimo = cc.storage.and_then(|s| eframe::get_value::<Option<String>>(s, CLAVE_ULTIMO_ARCHIVO));
+            recuperar_sesion(ultimo.flatten())
+        } else {
+            (inventario, None, None)
+        };
+        let eventos = inventario.suscribir();
+        
+        Self {
+            inventario: Arc::new(Mutex::new(inventario)),
+            eventos,
+            generacion: 0,
+            filtro_nombre: String::new(),
+            busqueda_nombre: None,
+            filtro_tag: String::new(),
+            filtro_precio_min: String::new(),
+            filtro_precio_max: String::new(),
+            filtro_ubicacion: None,
+            mostrar_bajo_stock: false,
+            mensaje,
+            carga_estricta: false,
+            reporte_carga: None,
+            importacion: None,
+            comparacion: None,
+            nuevo_producto: NuevoProducto::default(),
+            modo_edicion: None,
+            variantes_abiertas: HashSet::new(),
+            nueva_variante: None,
+            producto_seleccionado: None,
+            nuevo_movimiento: NuevoMovimiento::default(),
+            nueva_transferencia: NuevaTransferencia::default(),
+            nuevo_umbral_tag: String::new(),
+            nueva_ubicacion: String::new(),
+            codigo_escaneado: String::new(),
+            desplazar_a: None,
+            formato_moneda: FormatoMoneda::desde_entorno(),
+            reportes_abiertos: false,
+            metodo_valuacion: MetodoValuacion::CostoActual,
+            compras_abiertas: false,
+            nuevo_proveedor: NuevoProveedor::default(),
+            nueva_orden: NuevaOrden::default(),
+            recepcion: HashMap::new(),
+            ubicacion_recepcion: None,
+            caja_abierta: false,
+            carrito: Carrito::default(),
+            busqueda_caja: String::new(),
+            tasa_impuesto: String::new(),
+            ubicacion_caja: None,
+            ultima_venta: None,
+            autoguardado,
+            archivo_actual,
+        }
+    }
+    
+    // Método privado para guardar en otro archivo y seguir guardando ahí
+    fn guardar_como(&mut self, ruta: &str) -> Result<(), InventarioError> {
+        let mut inventario = self.inventario.lock().unwrap();
+        if !self.autoguardado {
+            return inventario.guardar_inventario(ruta);
+        }
+        
+        inventario.sincronizar()?;
+        inventario.guardar_inventario(ruta)?;
+        let diario = DiarioStorage::nuevo(Some(ruta), &ruta_diario(ruta))?;
+        inventario.reemplazar_almacenamiento(Some(Box::new(diario)));
+        
+        // Lo que no tenía archivo ya quedó guardado en este
+        if self.archivo_actual.is_none() {
+            let _ = std::fs::remove_file(diario_sin_guardar());
+        }
+        self.archivo_actual = Some(ruta.to_string());
+        Ok(())
+    }
+    
+    // Método privado para abrir otro archivo, guardando antes el actual
+    fn abrir_archivo(&mut self, ruta: &str, modo: ModoCarga) -> Result<ReporteCarga, InventarioError> {
+        let mut inventario = self.inventario.lock().unwrap();
+        if !self.autoguardado {
+            return inventario.cargar_inventario(ruta, modo);
+        }
+        
+        // El diario se abre antes de tocar nada: si falla, sigue abierto el
+        // inventario anterior con su almacenamiento
+        inventario.sincronizar()?;
+        let diario = DiarioStorage::nuevo(Some(ruta), &ruta_diario(ruta))?;
+        
+        // Cargar sin almacenamiento, para no pisar el archivo anterior
+        let anterior = inventario.reemplazar_almacenamiento(None);
+        let reporte = match inventario.cargar_inventario(ruta, modo) {
+            Ok(reporte) => reporte,
+            Err(e) => {
+                inventario.reemplazar_almacenamiento(anterior);
+                return Err(e);
+            }
+        };
+        
+        inventario.reemplazar_almacenamiento(Some(Box::new(diario)));
+        self.archivo_actual = Some(ruta.to_string());
+        Ok(reporte)
+    }
+    
+    fn mostrar_sidebar(&mut self, ui: &mut Ui) {
+        ui.heading("Filtros");
+        
+        ui.horizontal(|ui| {
+            ui.label("Nombre:");
+            ui.text_edit_singleline(&mut self.filtro_nombre);
+        });
+        
+        ui.horizontal(|ui| {
+            ui.label("Etiqueta:");
+            ui.text_edit_singleline(&mut self.filtro_tag)
+                .on_hover_text("Una etiqueta, o una consulta como: (electrónica OR hogar) AND NOT móviles");
+        });
+        
+        if !self.filtro_tag.trim().is_empty() {
+            if let Err(e) = ConsultaTags::parsear(&self.filtro_tag) {
+                ui.label(RichText::new(e.to_string()).small().color(Color32::RED));
+            }
+        }
+        
+        let moneda = self.inventario.lock().unwrap().moneda_principal();
+        ui.horizontal(|ui| {
+            ui.label(format!("Precio ({}):", moneda));
+            ui.add(egui::TextEdit::singleline(&mut self.filtro_precio_min).hint_text("mín").desired_width(60.0));
+            ui.label("a");
+            ui.add(egui::TextEdit::singleline(&mut self.filtro_precio_max).hint_text("máx").desired_width(60.0));
+        });
+        
+        ui.checkbox(&mut self.mostrar_bajo_stock, "Solo productos con bajo stock");
+        
+        ui.separator();
+        
+        ui.heading("Acciones");
+        
+        if let Some(descripcion) = self.inventario.lock().unwrap().descripcion_almacenamiento() {
+            ui.label(RichText::new(format!("Guardado automático en {}", descripcion)).small());
+        }
+        
+        if ui.button("Reiniciar filtros").clicked() {
+            self.filtro_nombre.clear();
+            self.filtro_tag.clear();
+            self.filtro_precio_min.clear();
+            self.filtro_precio_max.clear();
+            self.filtro_ubicacion = None;
+            self.mostrar_bajo_stock = false;
+        }
+        
+        if ui.button("Guardar inventario").clicked() {
+            if let Some(path) = FileDialog::new()
+                .add_filter("CSV", &["csv"])
+                .add_filter("JSON", &["json"])
+                .set_file_name("inventario.csv")
+                .save_file() 
+            {
+                let path_str = path.to_string_lossy().to_string();
+                match self.guardar_como(&path_str) {
+                    Ok(_) => self.mensaje = Some(format!("Inventario guardado en {}", path_str)),
+                    Err(e) => self.mensaje = Some(format!("Error al guardar: {}", e)),
+                }
+            }
+        }
+        
+        if ui.button("Cargar inventario").clicked() {
+            if let Some(path) = FileDialog::new()
+                .add_filter("Inventario (CSV, JSON)", &["csv", "json"])
+                .add_filter("CSV", &["csv"])
+                .add_filter("JSON", &["json"])
+                .pick_file() 
+            {
+                let path_str = path.to_string_lossy().to_string();
+                match self.abrir_archivo(&path_str, self.modo_carga()) {
+                    Ok(reporte) => {
+                        self.mensaje = Some(format!("Inventario cargado desde {}", path_str));
+                        self.reporte_carga = Some(reporte);
+                    }
+                    Err(e) => self.mensaje = Some(format!("Error al cargar: {}", e)),
+                }
+            }
+        }
+        
+        if ui.button("Importar y combinar").on_hover_text("Agregar y actualizar productos desde un archivo sin reemplazar el inventario").clicked() {
+            if let Some(path) = FileDialog::new()
+                .add_filter("Inventario (CSV, JSON)", &["csv", "json"])
+                .pick_file()
+            {
+                let ruta = path.to_string_lossy().to_string();
+                let opciones = OpcionesImportacion::default();
+                let plan = self.planificar_importacion(&ruta, opciones);
+                self.importacion = Some(Importacion { ruta, opciones, plan });
+            }
+        }
+        
+        ui.horizontal(|ui| {
+            if ui.button("Comparar con archivo").on_hover_text("Ver qué cambió desde que se guardó un archivo").clicked() {
+                if let Some(path) = FileDialog::new()
+                    .add_filter("Inventario (CSV, JSON)", &["csv", "json"])
+                    .pick_file()
+                {
+                    let ruta = path.to_string_lossy().to_string();
+                    let resultado = self.inventario.lock().unwrap()
+                        .comparar_con_archivo(&ruta, self.modo_carga())
+                        .map_err(|e| e.to_string());
+                    self.comparacion = Some(VistaComparacion { antes: ruta, despues: "Inventario actual".to_string(), resultado });
+                }
+            }
+            
+            if ui.button("Comparar dos archivos").clicked() {
+                let dialogo = || FileDialog::new().add_filter("Inventario (CSV, JSON)", &["csv", "json"]);
+                if let Some(antes) = dialogo().set_title("Versión anterior").pick_file() {
+                    if let Some(despues) = dialogo().set_title("Versión nueva").pick_file() {
+                        let antes = antes.to_string_lossy().to_string();
+                        let despues = despues.to_string_lossy().to_string();
+                        let resultado = comparacion::comparar_archivos(&antes, &despues, self.modo_carga())
+                            .map_err(|e| e.to_string());
+                        self.comparacion = Some(VistaComparacion { antes, despues, resultado });
+                    }
+                }
+            }
+        });
+        
+        ui.checkbox(&mut self.carga_estricta, "Carga estricta (abortar en el primer error)");
+        
+        self.mostrar_puntos_reorden(ui);
+        self.mostrar_ubicaciones(ui);
+        
+        ui.separator();
+        
+        // Agregar algunos productos de ejemplo para demo
+        if ui.button("Cargar datos de ejemplo").clicked() {
+            let mut inventario = self.inventario.lock().unwrap();
+            
+            let moneda = inventario.moneda_principal();
+            let ejemplos: [(&str, u64, u32, [&str; 2]); 5] = [
+                ("Laptop Gamer", 1200, 10, ["electrónica", "computadoras"]),
+                ("Smartphone", 800, 15, ["electrónica", "móviles"]),
+                ("Teclado Mecánico", 120, 3, ["electrónica", "periféricos"]),
+                ("Mouse Inalámbrico", 50, 2, ["electrónica", "periféricos"]),
+                ("Monitor 4K", 350, 7, ["electrónica", "periféricos"]),
+            ];
+            
+            for (nombre, precio, stock, tags) in ejemplos {
+                if let Err(e) = inventario.agregar_producto(
+                    nombre.to_string(),
+                    Dinero::desde_unidades(precio, moneda),
+                    stock,
+                    tags.iter().map(|t| t.to_string()).collect(),
+                    None
+                ) {
+                    self.mensaje = Some(format!("Error: {}", e));
+                    return;
+                }
+            }
+            
+            self.mensaje = Some("Datos de ejemplo cargados".to_string());
+        }
+    }
+    
+    fn mostrar_puntos_reorden(&mut self, ui: &mut Ui) {
+        ui.separator();
+        ui.heading("Puntos de reorden");
+        
+        let mut inventario = self.inventario.lock().unwrap();
+        let configuracion = inventario.configuracion_stock().clone();
+        let mut resultado = Ok(());
+        
+        // Global, para productos sin punto propio ni en sus tags
+        ui.horizontal(|ui| {
+            ui.label("Global:");
+            let mut global = configuracion.punto_reorden_global;
+            if ui.add(egui::DragValue::new(&mut global)).changed() {
+                resultado = inventario.establecer_punto_reorden_global(global);
+            }
+        });
+        
+        // Por tag
+        for (tag, punto) in &configuracion.punto_reorden_por_tag {
+            ui.horizontal(|ui| {
+                ui.label(format!("{}:", tag));
+                let mut punto = *punto;
+                if ui.add(egui::DragValue::new(&mut punto)).changed() {
+                    resultado = inventario.establecer_punto_reorden_tag(tag, Some(punto));
+                }
+                if ui.small_button("×").on_hover_text("Quitar punto de reorden del tag").clicked() {
+                    resultado = inventario.establecer_punto_reorden_tag(tag, None);
+                }
+            });
+        }
+        
+        ui.horizontal(|ui| {
+            ui.label("Tag:");
+            ui.text_edit_singleline(&mut self.nuevo_umbral_tag);
+        });
+        let tag = self.nuevo_umbral_tag.trim().to_string();
+        if ui.add_enabled(!tag.is_empty(), egui::Button::new("Agregar punto por tag")).clicked() {
+            resultado = inventario.establecer_punto_reorden_tag(&tag, Some(configuracion.punto_reorden_global));
+            self.nuevo_umbral_tag.clear();
+        }
+        
+        if let Err(e) = resultado {
+            self.mensaje = Some(format!("Error: {}", e));
+        }
+    }
+    
+    fn mostrar_ubicaciones(&mut self, ui: &mut Ui) {
+        ui.separator();
+        ui.heading("Ubicaciones");
+        
+        let mut inventario = self.inventario.lock().unwrap();
+        let totales = inventario.totales_por_ubicacion();
+        let ubicaciones: Vec<String> = totales.keys().cloned().collect();
+        
+        // La ubicación elegida filtra la tabla y cambia su columna de stock
+        if self.filtro_ubicacion.as_ref().is_some_and(|u| !totales.contains_key(u)) {
+            self.filtro_ubicacion = None;
+        }
+        selector_ubicacion(ui, "filtro_ubicacion", "Ver", &mut self.filtro_ubicacion, &ubicaciones, "Todas");
+        
+        let mut resultado = Ok(());
+        for (nombre, total) in &totales {
+            ui.horizontal(|ui| {
+                ui.label(format!("{}: {}", nombre, total));
+                if *total == 0 && ui.small_button("×").on_hover_text("Quitar ubicación").clicked() {
+                    resultado = inventario.eliminar_ubicacion(nombre);
+                }
+            });
+        }
+        ui.label(format!("Sin ubicación: {}", inventario.stock_sin_ubicacion()));
+        
+        ui.horizontal(|ui| {
+            ui.label("Nueva:");
+            ui.text_edit_singleline(&mut self.nueva_ubicacion);
+        });
+        if ui.add_enabled(!self.nueva_ubicacion.trim().is_empty(), egui::Button::new("Agregar ubicación")).clicked() {
+            resultado = inventario.agregar_ubicacion(&self.nueva_ubicacion);
+            if resultado.is_ok() {
+                self.nueva_ubicacion.clear();
+            }
+        }
+        
+        if let Err(e) = resultado {
+            self.mensaje = Some(format!("Error: {}", e));
+        }
+    }
+    
+    fn mostrar_formulario_producto(&mut self, ui: &mut Ui) {
+        let titulo = if self.modo_edicion.is_some() {
+            "Editar Producto"
+        } else {
+            "Agregar Nuevo Producto"
+        };
+        
+        ui.heading(titulo);
+        
+        // Una variante usa el nombre y las etiquetas del producto principal
+        let es_variante = self.modo_edicion.is_some_and(|id| {
+            self.inventario.lock().unwrap().obtener_producto(id).is_some_and(|p| p.variante.is_some())
+        });
+        
+        ui.horizontal(|ui| {
+            ui.label("Nombre:");
+            ui.add_enabled(!es_variante, egui::TextEdit::singleline(&mut self.nuevo_producto.nombre));
+        });
+        
+        ui.horizontal(|ui| {
+            ui.label("Precio:");
+            ui.text_edit_singleline(&mut self.nuevo_producto.precio);
+            ui.label("Moneda:");
+            ui.add(
+                egui::TextEdit::singleline(&mut self.nuevo_producto.moneda)
+                    .hint_text(self.inventario.lock().unwrap().moneda_principal().codigo())
+                    .desired_width(50.0),
+            );
+            ui.label("Costo:");
+            ui.add(egui::TextEdit::singleline(&mut self.nuevo_producto.costo).hint_text("opcional").desired_width(80.0));
+        });
+        
+        ui.horizontal(|ui| {
+            ui.label("Stock:");
+            ui.text_edit_singleline(&mut self.nuevo_producto.stock);
+        });
+        
+        ui.horizontal(|ui| {
+            ui.label("Etiquetas (separadas por comas):");
+            ui.add_enabled(!es_variante, egui::TextEdit::singleline(&mut self.nuevo_producto.tags));
+        });
+        if es_variante {
+            ui.label(RichText::new("El nombre y las etiquetas se cambian en el producto principal.").small());
+        }
+        
+        ui.horizontal(|ui| {
+            ui.label("Punto de reorden (vacío = heredado):");
+            ui.text_edit_singleline(&mut self.nuevo_producto.punto_reorden);
+        });
+        
+        ui.horizontal(|ui| {
+            ui.label("SKU:");
+            ui.text_edit_singleline(&mut self.nuevo_producto.sku);
+            ui.label("Código de barras (EAN-13/UPC-A):");
+            ui.text_edit_singleline(&mut self.nuevo_producto.codigo_barras);
+        });
+        
+        ui.horizontal(|ui| {
+            if ui.button("Cancelar").clicked() {
+                self.nuevo_producto = NuevoProducto::default();
+                self.modo_edicion = None;
+            }
+            
+            let boton_texto = if self.modo_edicion.is_some() { "Actualizar" } else { "Agregar" };
+            
+            if ui.button(boton_texto).clicked() {
+                let stock = self.nuevo_producto.stock.parse::<u32>().unwrap_or(0);
+                let tags: Vec<String> = self.nuevo_producto.tags
+                    .split(',')
+                    .map(|s| s.trim().to_string())
+                    .filter(|s| !s.is_empty())
+                    .collect();
+                let punto_reorden = match self.nuevo_producto.punto_reorden.trim() {
+                    "" => None,
+                    texto => match texto.parse::<u32>() {
+                        Ok(punto) => Some(punto),
+                        Err(_) => {
+                            self.mensaje = Some("Error: el punto de reorden debe ser un número entero".to_string());
+                            return;
+                        }
+                    },
+                };
+                
+                let sku = Some(self.nuevo_producto.sku.trim()).filter(|s| !s.is_empty());
+                let codigo_barras = Some(self.nuevo_producto.codigo_barras.trim()).filter(|c| !c.is_empty());
+                
+                let mut inventario = self.inventario.lock().unwrap();
+                
+                // Un precio vacío es 0; uno mal escrito no se guarda
+                let moneda = match self.nuevo_producto.moneda.trim() {
+                    "" => Ok(inventario.moneda_principal()),
+                    codigo => Moneda::parsear(codigo),
+                };
+                let precio = moneda.and_then(|moneda| match self.nuevo_producto.precio.trim() {
+                    "" => Ok(Dinero::new(0, moneda)),
+                    texto => Dinero::parsear(texto, moneda),
+                });
+                let precio = match precio {
+                    Ok(precio) => precio,
+                    Err(e) => {
+                        self.mensaje = Some(format!("Error: {}", e));
+                        return;
+                    }
+                };
+                let costo = match self.nuevo_producto.costo.trim() {
+                    "" => None,
+                    texto => match Dinero::parsear(texto, precio.moneda) {
+                        Ok(costo) => Some(costo),
+                        Err(e) => {
+                            self.mensaje = Some(format!("Error: costo: {}", e));
+                            return;
+                        }
+                    },
+                };
+                
+                // Los códigos se comprueban antes, para no guardar el producto a medias
+                if let Err(e) = inventario.comprobar_codigos(self.modo_edicion, sku, codigo_barras) {
+                    self.mensaje = Some(format!("Error: {}", e));
+                    return;
+                }
+                
+                if let Some(id) = self.modo_edicion {
+                    // Actualizar todos los campos del producto
+                    let resultado = inventario.actualizar_producto(
+                        id,
+                        self.nuevo_producto.nombre.clone(),
+                        precio,
+                        stock,
+                        tags,
+                        punto_reorden
+                    ).and_then(|_| {
+                        // Solo se registra un cambio de códigos o de costo si hubo alguno
+                        let producto = inventario.obtener_todos_productos().into_iter().find(|p| p.id == id);
+                        let cambiaron = producto.is_some_and(|p| p.sku.as_deref() != sku || p.codigo_barras.as_deref() != codigo_barras);
+                        let cambio_costo = producto.is_some_and(|p| p.costo != costo);
+                        if cambiaron {
+                            inventario.establecer_codigos(id, sku, codigo_barras)?;
+                        }
+                        if cambio_costo {
+                            inventario.establecer_costo(id, costo)?;
+                        }
+                        Ok(())
+                    });
+                    
+                    if let Err(e) = resultado {
+                        self.mensaje = Some(format!("Error: {}", e));
+                    } else {
+                        self.mensaje = Some(format!("Producto ID {} actualizado", id));
+                    }
+                } else {
+                    // Agregar nuevo producto
+                    let resultado = inventario.agregar_producto(
+                        self.nuevo_producto.nombre.clone(),
+                        precio,
+                        stock,
+                        tags,
+                        punto_reorden
+                    ).and_then(|id| {
+                        if sku.is_some() || codigo_barras.is_some() {
+                            inventario.establecer_codigos(id, sku, codigo_barras)?;
+                        }
+                        if costo.is_some() {
+                            inventario.establecer_costo(id, costo)?;
+                        }
+                        Ok(id)
+                    });
+                    
+                    match resultado {
+                        Ok(id) => self.mensaje = Some(format!("Producto agregado con ID {}", id)),
+                        Err(e) => self.mensaje = Some(format!("Error: {}", e)),
+                    }
+                }
+                
+                // Limpiar formulario
+                self.nuevo_producto = NuevoProducto::default();
+                self.modo_edicion = None;
+            }
+        });
+    }
+    
+    // Campo para escanear: los lectores de códigos escriben el código y un
+    // Enter, como un teclado. Al encontrarlo se selecciona el producto.
+    fn mostrar_escaner(&mut self, ui: &mut Ui) {
+        ui.label("Escanear:");
+        let respuesta = ui.add(
+            egui::TextEdit::singleline(&mut self.codigo_escaneado)
+                .hint_text("SKU o código de barras")
+                .desired_width(180.0),
+        );
+        
+        if !(respuesta.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter))) {
+            return;
+        }
+        
+        let codigo = std::mem::take(&mut self.codigo_escaneado);
+        if codigo.trim().is_empty() {
+            return;
+        }
+        
+        let encontrado = self.inventario.lock().unwrap().buscar_por_codigo(&codigo).map(|p| (p.id, p.nombre.clone()));
+        match encontrado {
+            Some((id, nombre)) => {
+                // Quitar los filtros para que el producto quede a la vista
+                self.filtro_nombre.clear();
+                self.filtro_tag.clear();
+                self.filtro_precio_min.clear();
+                self.filtro_precio_max.clear();
+                self.filtro_ubicacion = None;
+                self.mostrar_bajo_stock = false;
+                
+                self.producto_seleccionado = Some(id);
+                self.nuevo_movimiento = NuevoMovimiento::default();
+                self.desplazar_a = Some(id);
+                self.mensaje = Some(format!("Código {}: {} (ID {})", codigo.trim(), nombre, id));
+            }
+            None => self.mensaje = Some(format!("Ningún producto tiene el código {}", codigo.trim())),
+        }
+        
+        // Dejar el campo listo para el siguiente escaneo
+        respuesta.request_focus();
+    }
+    
+    // Método privado para reaccionar a los cambios que avisó el inventario
+    fn atender_eventos(&mut self) {
+        let eventos: Vec<EventoInventario> = self.eventos.try_iter().collect();
+        if eventos.is_empty() {
+            return;
+        }
+        self.generacion += 1;
+        
+        let inventario = self.inventario.lock().unwrap();
+        let mut bajo_stock = Vec::new();
+        for evento in eventos {
+            match evento {
+                EventoInventario::ProductoEliminado { id } => {
+                    if self.producto_seleccionado == Some(id) {
+                        self.producto_seleccionado = None;
+                    }
+                    self.carrito.quitar(id);
+                }
+                EventoInventario::BajoStock { id, stock } => {
+                    if let Some(producto) = inventario.obtener_producto(id) {
+                        bajo_stock.push(format!("«{}» quedó con bajo stock ({} unidades)", producto.nombre_completo(), stock));
+                    }
+                }
+                _ => {}
+            }
+        }
+        
+        // Al cargar un archivo pueden ser muchos; basta con la cantidad
+        if bajo_stock.len() > 3 {
+            bajo_stock = vec![format!("{} productos quedaron con bajo stock", bajo_stock.len())];
+        }
+        if !bajo_stock.is_empty() {
+            let avisos: Vec<String> = self.mensaje.take().into_iter().chain(bajo_stock).collect();
+            self.mensaje = Some(avisos.join(". "));
+        }
+    }
+    
+    fn deshacer(&mut self) {
+        match self.inventario.lock().unwrap().deshacer() {
+            Ok(Some(descripcion)) => self.mensaje = Some(format!("Deshecho: {}", descripcion)),
+            Ok(None) => {}
+            Err(e) => self.mensaje = Some(format!("Error al deshacer: {}", e)),
+        }
+    }
+    
+    fn rehacer(&mut self) {
+        match self.inventario.lock().unwrap().rehacer() {
+            Ok(Some(descripcion)) => self.mensaje = Some(format!("Rehecho: {}", descripcion)),
+            Ok(None) => {}
+            Err(e) => self.mensaje = Some(format!("Error al rehacer: {}", e)),
+        }
+    }
+    
+    fn mostrar_historial(&mut self, ui: &mut Ui, id: u32) {
+        let mut inventario = self.inventario.lock().unwrap();
+        
+        let Some(producto) = inventario.obtener_todos_productos().into_iter().find(|p| p.id == id) else {
+            // El producto se eliminó
+            self.producto_seleccionado = None;
+            return;
+        };
+        
+        ui.horizontal(|ui| {
+            ui.heading("Historial");
+            if ui.button("×").clicked() {
+                self.producto_seleccionado = None;
+            }
+        });
+        ui.label(format!("{} (stock: {})", producto.nombre_completo(), producto.stock));
+        for (ubicacion, cantidad) in &producto.existencias {
+            ui.label(RichText::new(format!("{}: {}", ubicacion, cantidad)).small());
+        }
+        if !producto.existencias.is_empty() {
+            ui.label(RichText::new(format!("Sin ubicación: {}", producto.stock_en(None))).small());
+        }
+        
+        let ubicaciones: Vec<String> = inventario.ubicaciones().into_iter().map(String::from).collect();
+        let proveedor_actual = producto.proveedor_id;
+        
+        ui.separator();
+        
+        // Formulario para registrar un movimiento
+        ui.horizontal(|ui| {
+            ui.label("Cantidad (+/-):");
+            ui.text_edit_singleline(&mut self.nuevo_movimiento.cantidad);
+        });
+        
+        egui::ComboBox::from_label("Motivo")
+            .selected_text(self.nuevo_movimiento.motivo.to_string())
+            .show_ui(ui, |ui| {
+                // Las transferencias tienen su propio formulario
+                for motivo in MotivoMovimiento::TODOS {
+                    if motivo != MotivoMovimiento::Transferencia {
+                        ui.selectable_value(&mut self.nuevo_movimiento.motivo, motivo, motivo.to_string());
+                    }
+                }
+            });
+        
+        if !ubicaciones.is_empty() {
+            selector_ubicacion(
+                ui, "ubicacion_movimiento", "Ubicación", &mut self.nuevo_movimiento.ubicacion, &ubicaciones, "Sin ubicación",
+            );
+        }
+        
+        // Las compras con costo sirven para valuar el inventario
+        if self.nuevo_movimiento.motivo == MotivoMovimiento::Compra {
+            ui.horizontal(|ui| {
+                ui.label(format!("Costo unitario ({}):", producto.precio.moneda));
+                ui.add(egui::TextEdit::singleline(&mut self.nuevo_movimiento.costo).hint_text("opcional"));
+            });
+        }
+        
+        ui.horizontal(|ui| {
+            ui.label("Nota:");
+            ui.text_edit_singleline(&mut self.nuevo_movimiento.nota);
+        });
+        
+        if ui.button("Registrar movimiento").clicked() {
+            let costo = match self.nuevo_movimiento.costo.trim() {
+                "" => Ok(None),
+                _ if self.nuevo_movimiento.motivo != MotivoMovimiento::Compra => Ok(None),
+                texto => Dinero::parsear(texto, producto.precio.moneda).map(Some),
+            };
+            match (self.nuevo_movimiento.cantidad.trim().parse::<i64>(), costo) {
+                (_, Err(e)) => self.mensaje = Some(format!("Error: costo unitario: {}", e)),
+                (Ok(cantidad), Ok(costo)) => {
+                    // Las ventas restan aunque se escriban en positivo
+                    let delta = match self.nuevo_movimiento.motivo {
+                        MotivoMovimiento::Venta => -cantidad.abs(),
+                        _ => cantidad,
+                    };
+                    let nota = Some(self.nuevo_movimiento.nota.trim().to_string()).filter(|n| !n.is_empty());
+                    
+                    let ubicacion = self.nuevo_movimiento.ubicacion.clone();
+                    let motivo = self.nuevo_movimiento.motivo;
+                    let resultado = match (&ubicacion, costo) {
+                        (ubicacion, Some(costo)) => u32::try_from(delta)
+                            .map_err(|_| InventarioError::Validacion("la cantidad comprada debe ser positiva".to_string()))
+                            .and_then(|cantidad| inventario.registrar_compra(id, ubicacion.as_deref(), cantidad, costo, nota))
+                            .map(|cantidad| match ubicacion {
+                                Some(nombre) => format!("Stock del producto ID {} en {} ahora es {}", id, nombre, cantidad),
+                                None => format!("Stock del producto ID {} sin ubicación ahora es {}", id, cantidad),
+                            }),
+                        (Some(nombre), None) => inventario.registrar_movimiento_en(id, Some(nombre), delta, motivo, nota)
+                            .map(|cantidad| format!("Stock del producto ID {} en {} ahora es {}", id, nombre, cantidad)),
+                        (None, None) => inventario.registrar_movimiento(id, delta, motivo, nota)
+                            .map(|stock| format!("Stock del producto ID {} ahora es {}", id, stock)),
+                    };
+                    
+                    match resultado {
+                        Ok(mensaje) => {
+                            self.mensaje = Some(mensaje);
+                            self.nuevo_movimiento = NuevoMovimiento { ubicacion, ..NuevoMovimiento::default() };
+                        }
+                        Err(e) => self.mensaje = Some(format!("Error: {}", e)),
+                    }
+                }
+                (Err(_), _) => self.mensaje = Some("Error: la cantidad debe ser un número entero".to_string()),
+            }
+        }
+        
+        // Formulario para mover unidades entre ubicaciones
+        if !ubicaciones.is_empty() {
+            ui.separator();
+            ui.strong("Transferir");
+            
+            ui.horizontal(|ui| {
+                ui.label("Cantidad:");
+                ui.text_edit_singleline(&mut self.nueva_transferencia.cantidad);
+            });
+            selector_ubicacion(ui, "origen_transferencia", "Desde", &mut self.nueva_transferencia.origen, &ubicaciones, "Sin ubicación");
+            selector_ubicacion(ui, "destino_transferencia", "Hacia", &mut self.nueva_transferencia.destino, &ubicaciones, "Sin ubicación");
+            
+            if ui.button("Transferir").clicked() {
+                match self.nueva_transferencia.cantidad.trim().parse::<u32>() {
+                    Ok(cantidad) => {
+                        let origen = self.nueva_transferencia.origen.as_deref();
+                        let destino = self.nueva_transferencia.destino.as_deref();
+                        match inventario.transferir(id, origen, destino, cantidad, None) {
+                            Ok(()) => {
+                                self.mensaje = Some(format!(
+                                    "{} unidades transferidas de {} a {}",
+                                    cantidad,
+                                    origen.unwrap_or("sin ubicación"),
+                                    destino.unwrap_or("sin ubicación")
+                                ));
+                                self.nueva_transferencia.cantidad.clear();
+                            }
+                            Err(e) => self.mensaje = Some(format!("Error: {}", e)),
+                        }
+                    }
+                    Err(_) => self.mensaje = Some("Error: la cantidad debe ser un número positivo".to_string()),
+                }
+            }
+        }
+        
+        // Proveedor al que se le piden las reposiciones
+        let proveedores: Vec<(u32, String)> = inventario.proveedores().into_iter()
+            .map(|p| (p.id, p.nombre.clone()))
+            .collect();
+        if !proveedores.is_empty() {
+            ui.separator();
+            let mut proveedor = proveedor_actual;
+            let texto = proveedores.iter().find(|(p, _)| Some(*p) == proveedor)
+                .map(|(_, nombre)| nombre.as_str())
+                .unwrap_or("Sin proveedor");
+            egui::ComboBox::new("proveedor_producto", "Proveedor")
+                .selected_text(texto)
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut proveedor, None, "Sin proveedor");
+                    for (id, nombre) in &proveedores {
+                        ui.selectable_value(&mut proveedor, Some(*id), nombre);
+                    }
+                });
+            if proveedor != proveedor_actual {
+                if let Err(e) = inventario.establecer_proveedor(id, proveedor) {
+                    self.mensaje = Some(format!("Error: {}", e));
+                }
+            }
+        }
+        
+        ui.separator();
+        
+        let movimientos = inventario.movimientos_de(id);
+        if movimientos.is_empty() {
+            ui.label("Sin movimientos registrados.");
+            return;
+        }
+        
+        ScrollArea::vertical().show(ui, |ui| {
+            egui::Grid::new("historial_movimientos")
+                .striped(true)
+                .num_columns(5)
+                .show(ui, |ui| {
+                    ui.strong("Fecha");
+                    ui.strong("Motivo");
+                    ui.strong("Cambio");
+                    ui.strong("Ubicación");
+                    ui.strong("Nota");
+                    ui.end_row();
+                    
+                    // Los más recientes primero
+                    for movimiento in movimientos.iter().rev() {
+                        let fecha = Local.timestamp_opt(movimiento.fecha, 0)
+                            .single()
+                            .map(|f| f.format("%Y-%m-%d %H:%M").to_string())
+                            .unwrap_or_default();
+                        let color = if movimiento.delta < 0 { Color32::RED } else { Color32::from_rgb(0, 130, 0) };
+                        
+                        ui.label(fecha);
+                        ui.label(movimiento.motivo.to_string());
+                        ui.label(RichText::new(format!("{:+}", movimiento.delta)).color(color));
+                        ui.label(movimiento.ubicacion.as_deref().unwrap_or("—"));
+                        ui.label(movimiento.nota.as_deref().unwrap_or(""));
+                        ui.end_row();
+                    }
+                });
+        });
+    }
+    
+    fn mostrar_reporte_carga(&mut self, ctx: &egui::Context) {
+        let Some(reporte) = &self.reporte_carga else {
+            return;
+        };
+        
+        let mut abierto = true;
+        let mut cerrar = false;
+        
+        egui::Window::new("Reporte de carga")
+            .open(&mut abierto)
+            .collapsible(false)
+            .default_width(500.0)
+            .show(ctx, |ui| {
+                ui.label(format!("Productos cargados: {}", reporte.productos_cargados));
+                
+                if reporte.incidencias.is_empty() {
+                    ui.label("Todas las líneas se cargaron sin problemas.");
+                } else {
+                    ui.label(format!("Líneas con problemas: {}", reporte.incidencias.len()));
+                    ui.separator();
+                    
+                    ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+                        for incidencia in &reporte.incidencias {
+                            let (accion, color) = match incidencia.accion {
+                                AccionIncidencia::Rechazada => ("Rechazada", Color32::RED),
+                                AccionIncidencia::Corregida => ("Corregida", Color32::from_rgb(200, 120, 0)),
+                            };
+                            
+                            ui.horizontal_wrapped(|ui| {
+                                ui.label(RichText::new(accion).color(color));
+                                ui.label(format!(
+                                    "Línea {}, columna {}: {}",
+                                    incidencia.linea, incidencia.columna, incidencia.motivo
+                                ));
+                            });
+                        }
+                    });
+                }
+                
+                ui.separator();
+                cerrar = ui.button("Cerrar").clicked();
+            });
+        
+        if !abierto || cerrar {
+            self.reporte_carga = None;
+        }
+    }
+    
+    // Método privado para armar la vista previa de una importación
+    fn planificar_importacion(&self, ruta: &str, opciones: OpcionesImportacion) -> Result<PlanImportacion, String> {
+        let inventario = self.inventario.lock().unwrap();
+        importacion::planificar(&inventario, ruta, self.modo_carga(), opciones).map_err(|e| e.to_string())
+    }
+    
+    // Ventana para agregar una variante a un producto
+    fn mostrar_nueva_variante(&mut self, ctx: &egui::Context) {
+        let Some(variante) = &mut self.nueva_variante else {
+            return;
+        };
+        
+        let mut abierto = true;
+        let mut agregar = false;
+        let mut cerrar = false;
+        
+        egui::Window::new(format!("Nueva variante de «{}»", variante.nombre))
+            .open(&mut abierto)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Atributos:");
+                    ui.add(egui::TextEdit::singleline(&mut variante.atributos).hint_text("talla=M, color=rojo"));
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Stock:");
+                    ui.text_edit_singleline(&mut variante.stock);
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Precio:");
+                    ui.add(egui::TextEdit::singleline(&mut variante.precio).hint_text("el del producto"));
+                });
+                
+                ui.horizontal(|ui| {
+                    agregar = ui.button("Agregar").clicked();
+                    cerrar = ui.button("Cancelar").clicked();
+                });
+            });
+        
+        if agregar {
+            let mut atributos = BTreeMap::new();
+            for par in variante.atributos.split(',').filter(|par| !par.trim().is_empty()) {
+                match par.split_once('=') {
+                    Some((atributo, valor)) => {
+                        atributos.insert(atributo.trim().to_string(), valor.trim().to_string());
+                    }
+                    None => {
+                        self.mensaje = Some(format!("Error: falta el valor del atributo \"{}\" (atributo=valor)", par.trim()));
+                        return;
+                    }
+                }
+            }
+            let stock = match variante.stock.trim() {
+                "" => 0,
+                texto => match texto.parse::<u32>() {
+                    Ok(stock) => stock,
+                    Err(_) => {
+                        self.mensaje = Some("Error: el stock debe ser un número entero positivo".to_string());
+                        return;
+                    }
+                },
+            };
+            
+            let mut inventario = self.inventario.lock().unwrap();
+            let moneda = inventario.obtener_producto(variante.padre_id)
+                .map(|p| p.precio.moneda)
+                .unwrap_or_else(|| inventario.moneda_principal());
+            let precio = match variante.precio.trim() {
+                "" => None,
+                texto => match Dinero::parsear(texto, moneda) {
+                    Ok(precio) => Some(precio),
+                    Err(e) => {
+                        self.mensaje = Some(format!("Error: precio: {}", e));
+                        return;
+                    }
+                },
+            };
+            
+            match inventario.agregar_variante(variante.padre_id, atributos, stock, precio) {
+                Ok(id) => {
+                    self.mensaje = Some(format!("Variante agregada con ID {}", id));
+                    self.variantes_abiertas.insert(variante.padre_id);
+                    cerrar = true;
+                }
+                Err(e) => self.mensaje = Some(format!("Error: {}", e)),
+            }
+        }
+        
+        if !abierto || cerrar {
+            self.nueva_variante = None;
+        }
+    }
+    
+    // Método privado para leer archivos según la opción de carga estricta
+    fn modo_carga(&self) -> ModoCarga {
+        if self.carga_estricta { ModoCarga::Estricto } else { ModoCarga::Tolerante }
+    }
+    
+    // Ventana con las diferencias entre dos versiones del inventario,
+    // una al lado de la otra
+    fn mostrar_comparacion(&mut self, ctx: &egui::Context) {
+        let Some(vista) = &self.comparacion else {
+            return;
+        };
+        
+        let formato = self.formato_moneda;
+        let mut abierto = true;
+        let mut cerrar = false;
+        
+        egui::Window::new("Comparar inventarios")
+            .open(&mut abierto)
+            .collapsible(false)
+            .default_width(650.0)
+            .show(ctx, |ui| {
+                ui.label(format!("Antes: {}", vista.antes));
+                ui.label(format!("Después: {}", vista.despues));
+                ui.separator();
+                
+                let comparacion = match &vista.resultado {
+                    Ok(comparacion) => comparacion,
+                    Err(e) => {
+                        ui.label(RichText::new(format!("No se pudo comparar: {}", e)).color(Color32::RED));
+                        cerrar = ui.button("Cerrar").clicked();
+                        return;
+                    }
+                };
+                
+                if comparacion.es_igual() {
+                    ui.label(format!("Sin diferencias ({} productos iguales).", comparacion.sin_cambios));
+                } else {
+                    ui.label(format!(
+                        "{} agregados, {} eliminados, {} modificados, {} sin cambios",
+                        comparacion.agregados.len(), comparacion.eliminados.len(),
+                        comparacion.modificados.len(), comparacion.sin_cambios
+                    ));
+                }
+                
+                ScrollArea::vertical().max_height(400.0).show(ui, |ui| {
+                    egui::Grid::new("comparacion").striped(true).num_columns(4).show(ui, |ui| {
+                        for titulo in ["Producto", "Campo", "Antes", "Después"] {
+                            ui.strong(titulo);
+                        }
+                        ui.end_row();
+                        
+                        let verde = Color32::from_rgb(0, 150, 0);
+                        for producto in &comparacion.agregados {
+                            ui.label(RichText::new(format!("ID {} «{}»", producto.id, producto.nombre)).color(verde));
+                            ui.label("Agregado");
+                            ui.label("—");
+                            ui.label(resumen_producto(producto, &formato));
+                            ui.end_row();
+                        }
+                        for producto in &comparacion.eliminados {
+                            ui.label(RichText::new(format!("ID {} «{}»", producto.id, producto.nombre)).color(Color32::RED));
+                            ui.label("Eliminado");
+                            ui.label(resumen_producto(producto, &formato));
+                            ui.label("—");
+                            ui.end_row();
+                        }
+                        for modificado in &comparacion.modificados {
+                            let nombre = format!("ID {} «{}»", modificado.antes.id, modificado.antes.nombre);
+                            for (i, campo) in modificado.campos().into_iter().enumerate() {
+                                ui.label(if i == 0 { nombre.as_str() } else { "" });
+                                ui.label(campo.to_string());
+                                ui.label(valor_campo(&modificado.antes, campo, &formato));
+                                ui.label(valor_campo(&modificado.despues, campo, &formato));
+                                ui.end_row();
+                            }
+                        }
+                    });
+                });
+                
+                ui.separator();
+                cerrar = ui.button("Cerrar").clicked();
+            });
+        
+        if !abierto || cerrar {
+            self.comparacion = None;
+        }
+    }
+    
+    // Ventana con lo que haría una importación, para confirmarla
+    fn mostrar_importacion(&mut self, ctx: &egui::Context) {
+        let Some(importacion) = &mut self.importacion else {
+            return;
+        };
+        
+        let formato = self.formato_moneda;
+        let mut abierto = true;
+        let mut replanificar = false;
+        let mut aplicar = false;
+        let mut cerrar = false;
+        let mut ver_reporte = None;
+        
+        egui::Window::new("Importar y combinar")
+            .open(&mut abierto)
+            .collapsible(false)
+            .default_width(550.0)
+            .show(ctx, |ui| {
+                ui.label(format!("Archivo: {}", importacion.ruta));
+                ui.horizontal(|ui| {
+                    let clave = importacion.opciones.clave;
+                    egui::ComboBox::from_label("Combinar por")
+                        .selected_text(clave.to_string())
+                        .show_ui(ui, |ui| {
+                            for opcion in ClaveImportacion::TODAS {
+                                ui.selectable_value(&mut importacion.opciones.clave, opcion, opcion.to_string());
+                            }
+                        });
+                    replanificar |= importacion.opciones.clave != clave;
+                    replanificar |= ui.checkbox(
+                        &mut importacion.opciones.eliminar_faltantes,
+                        "Eliminar los que no están en el archivo",
+                    ).changed();
+                });
+                ui.separator();
+                
+                let plan = match &importacion.plan {
+                    Ok(plan) => plan,
+                    Err(e) => {
+                        ui.label(RichText::new(format!("No se pudo leer el archivo: {}", e)).color(Color32::RED));
+                        cerrar = ui.button("Cerrar").clicked();
+                        return;
+                    }
+                };
+                
+                ui.label(format!(
+                    "{} nuevos, {} con cambios, {} a eliminar, {} sin cambios",
+                    plan.agregar.len(), plan.actualizar.len(), plan.eliminar.len(), plan.sin_cambios
+                ));
+                if !plan.reporte.incidencias.is_empty()
+                    && ui.link(format!("{} líneas del archivo con problemas", plan.reporte.incidencias.len())).clicked()
+                {
+                    ver_reporte = Some(plan.reporte.clone());
+                }
+                
+                ScrollArea::vertical().max_height(350.0).show(ui, |ui| {
+                    if !plan.agregar.is_empty() {
+                        egui::CollapsingHeader::new(format!("Nuevos ({})", plan.agregar.len())).show(ui, |ui| {
+                            for producto in &plan.agregar {
+                                ui.label(format!(
+                                    "«{}» a {}, stock {}", producto.nombre, producto.precio.formatear(&formato), producto.stock
+                                ));
+                            }
+                        });
+                    }
+                    if !plan.actualizar.is_empty() {
+                        egui::CollapsingHeader::new(format!("Con cambios ({})", plan.actualizar.len())).default_open(true).show(ui, |ui| {
+                            for cambio in &plan.actualizar {
+                                ui.label(format!(
+                                    "ID {} «{}»: {}",
+                                    cambio.anterior.id, cambio.anterior.nombre,
+                                    describir_cambios(&cambio.anterior, &cambio.nuevo, &formato)
+                                ));
+                            }
+                        });
+                    }
+                    if !plan.eliminar.is_empty() {
+                        egui::CollapsingHeader::new(format!("A eliminar ({})", plan.eliminar.len())).show(ui, |ui| {
+                            for producto in &plan.eliminar {
+                                ui.label(format!("ID {} «{}» (stock {})", producto.id, producto.nombre, producto.stock));
+                            }
+                        });
+                    }
+                    if !plan.conflictos.is_empty() {
+                        let titulo = RichText::new(format!("Conflictos, no se importan ({})", plan.conflictos.len())).color(Color32::RED);
+                        egui::CollapsingHeader::new(titulo).default_open(true).show(ui, |ui| {
+                            for conflicto in &plan.conflictos {
+                                ui.label(format!("«{}»: {}", conflicto.producto.nombre, conflicto.motivo));
+                            }
+                        });
+                    }
+                });
+                
+                ui.separator();
+                ui.horizontal(|ui| {
+                    aplicar = ui.add_enabled(!plan.esta_vacio(), egui::Button::new("Aplicar")).clicked();
+                    cerrar = ui.button("Cancelar").clicked();
+                });
+            });
+        
+        if ver_reporte.is_some() {
+            self.reporte_carga = ver_reporte;
+        }
+        
+        if aplicar {
+            let Some(Importacion { plan: Ok(plan), .. }) = &self.importacion else {
+                return;
+            };
+            let resultado = self.inventario.lock().unwrap().importar(plan);
+            match resultado {
+                Ok(nuevos) => {
+                    self.mensaje = Some(format!(
+                        "Importación aplicada: {} nuevos, {} actualizados, {} eliminados",
+                        nuevos.len(), plan.actualizar.len(), plan.eliminar.len()
+                    ));
+                    cerrar = true;
+                }
+                Err(e) => {
+                    // Mostrar la vista previa con el inventario de ahora
+                    self.mensaje = Some(format!("Error al importar: {}", e));
+                    replanificar = true;
+                }
+            }
+        }
+        
+        if !abierto || cerrar {
+            self.importacion = None;
+        } else if replanificar {
+            if let Some(Importacion { ruta, opciones, .. }) = &self.importacion {
+                let plan = self.planificar_importacion(ruta, *opciones);
+                if let Some(importacion) = &mut self.importacion {
+                    importacion.plan = plan;
+                }
+            }
+        }
+    }
+    
+    // Ventana con el valor del inventario a costo y a precio de venta, y
+    // los márgenes por etiqueta y por producto
+    fn mostrar_reportes(&mut self, ctx: &egui::Context) {
+        if !self.reportes_abiertos {
+            return;
+        }
+        
+        let reporte = {
+            let inventario = self.inventario.lock().unwrap();
+            reportes::valuar(&inventario, inventario.moneda_principal(), self.metodo_valuacion)
+        };
+        let formato = self.formato_moneda;
+        let monto = |dinero: Option<Dinero>| dinero.map(|d| d.formatear(&formato)).unwrap_or_else(|| "—".to_string());
+        
+        let mut abierto = true;
+        egui::Window::new("Reportes")
+            .open(&mut abierto)
+            .default_width(650.0)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    egui::ComboBox::from_label("Método de valuación")
+                        .selected_text(self.metodo_valuacion.to_string())
+                        .show_ui(ui, |ui| {
+                            for metodo in MetodoValuacion::TODOS {
+                                ui.selectable_value(&mut self.metodo_valuacion, metodo, metodo.to_string());
+                            }
+                        });
+                    
+                    if ui.button("Exportar CSV").clicked() {
+                        if let Some(path) = FileDialog::new()
+                            .add_filter("CSV", &["csv"])
+                            .set_file_name("reporte.csv")
+                            .save_file()
+                        {
+                            match exportar_reporte(path.to_str().unwrap_or_default(), &reporte) {
+                                Ok(()) => self.mensaje = Some("Reporte exportado".to_string()),
+                                Err(e) => self.mensaje = Some(format!("Error al exportar: {}", e)),
+                            }
+                        }
+                    }
+                });
+                
+                let total = &reporte.total;
+                ui.label(format!("Valor a costo: {}", monto(Some(total.valor_costo))));
+                ui.label(format!("Valor a precio de venta: {}", monto(Some(total.valor_venta))));
+                ui.label(format!("Margen bruto: {}", texto_margen(total.margen())));
+                if total.unidades_sin_costo > 0 {
+                    ui.label(RichText::new(format!(
+                        "{} unidades sin costo conocido no entran en el valor a costo ni en el margen",
+                        total.unidades_sin_costo
+                    )).small().color(Color32::from_rgb(200, 120, 0)));
+                }
+                if reporte.omitidos > 0 {
+                    ui.label(RichText::new(format!(
+                        "{} productos con precio en otra moneda que {} no se incluyen",
+                        reporte.omitidos, reporte.moneda
+                    )).small());
+                }
+                
+                ScrollArea::vertical().max_height(400.0).show(ui, |ui| {
+                    egui::CollapsingHeader::new("Por etiqueta").default_open(true).show(ui, |ui| {
+                        egui::Grid::new("reporte_tags").striped(true).num_columns(5).show(ui, |ui| {
+                            for titulo in ["Etiqueta", "Productos", "Valor a costo", "Valor de venta", "Margen"] {
+                                ui.strong(titulo);
+                            }
+                            ui.end_row();
+                            
+                            for (tag, totales) in &reporte.tags {
+                                ui.label(tag);
+                                ui.label(totales.productos.to_string());
+                                ui.label(monto(Some(totales.valor_costo)));
+                                ui.label(monto(Some(totales.valor_venta)));
+                                ui.label(texto_margen(totales.margen()));
+                                ui.end_row();
+                            }
+                        });
+                    });
+                    
+                    egui::CollapsingHeader::new("Por producto").show(ui, |ui| {
+                        egui::Grid::new("reporte_productos").striped(true).num_columns(7).show(ui, |ui| {
+                            for titulo in ["ID", "Nombre", "Stock", "Costo unitario", "Valor a costo", "Valor de venta", "Margen"] {
+                                ui.strong(titulo);
+                            }
+                            ui.end_row();
+                            
+                            for producto in &reporte.productos {
+                                ui.label(producto.id.to_string());
+                                ui.label(&producto.nombre);
+                                ui.label(producto.stock.to_string());
+                                ui.label(monto(producto.costo_unitario));
+                                ui.label(monto(producto.valor_costo));
+                                ui.label(monto(Some(producto.valor_venta)));
+                                ui.label(texto_margen(producto.margen()));
+                                ui.end_row();
+                            }
+                        });
+                    });
+                });
+            });
+        
+        if !abierto {
+            self.reportes_abiertos = false;
+        }
+    }
+    
+    // Ventana de proveedores, órdenes de compra y sugerencias de reposición
+    fn mostrar_compras(&mut self, ctx: &egui::Context) {
+        if !self.compras_abiertas {
+            return;
+        }
+        
+        let mut inventario = self.inventario.lock().unwrap();
+        let nombres: HashMap<u32, String> = inventario.obtener_todos_productos().into_iter()
+            .map(|p| (p.id, p.nombre.clone()))
+            .collect();
+        let proveedores: Vec<(u32, String)> = inventario.proveedores().into_iter()
+            .map(|p| (p.id, p.nombre.clone()))
+            .collect();
+        let nombre_proveedor = |id: Option<u32>| match id {
+            Some(id) => proveedores.iter().find(|(p, _)| *p == id)
+                .map(|(_, nombre)| nombre.clone())
+                .unwrap_or_else(|| format!("#{}", id)),
+            None => "Sin proveedor".to_string(),
+        };
+        let nombre_producto = |id: u32| nombres.get(&id).cloned().unwrap_or_else(|| format!("ID {} (eliminado)", id));
+        let ubicaciones: Vec<String> = inventario.ubicaciones().into_iter().map(String::from).collect();
+        let formato = self.formato_moneda;
+        let mut resultado: Result<Option<String>, InventarioError> = Ok(None);
+        
+        let mut abierto = true;
+        egui::Window::new("Compras")
+            .open(&mut abierto)
+            .default_width(600.0)
+            .show(ctx, |ui| {
+                ScrollArea::vertical().max_height(500.0).show(ui, |ui| {
+                    egui::CollapsingHeader::new("Proveedores").default_open(true).show(ui, |ui| {
+                        let lista: Vec<_> = inventario.proveedores().into_iter().cloned().collect();
+                        for proveedor in lista {
+                            ui.horizontal(|ui| {
+                                ui.label(format!("#{} {}", proveedor.id, proveedor.nombre));
+                                if let Some(contacto) = &proveedor.contacto {
+                                    ui.label(RichText::new(contacto).small());
+                                }
+                                if ui.small_button("×").on_hover_text("Quitar proveedor").clicked() {
+                                    resultado = inventario.eliminar_proveedor(proveedor.id)
+                                        .map(|()| Some(format!("Proveedor «{}» eliminado", proveedor.nombre)));
+                                }
+                            });
+                        }
+                        
+                        ui.horizontal(|ui| {
+                            ui.label("Nombre:");
+                            ui.text_edit_singleline(&mut self.nuevo_proveedor.nombre);
+                        });
+                        ui.horizontal(|ui| {
+                            ui.label("Contacto:");
+                            ui.add(egui::TextEdit::singleline(&mut self.nuevo_proveedor.contacto).hint_text("opcional"));
+                        });
+                        let habilitado = !self.nuevo_proveedor.nombre.trim().is_empty();
+                        if ui.add_enabled(habilitado, egui::Button::new("Agregar proveedor")).clicked() {
+                            resultado = inventario.agregar_proveedor(&self.nuevo_proveedor.nombre, Some(&self.nuevo_proveedor.contacto))
+                                .map(|id| Some(format!("Proveedor agregado con ID {}", id)));
+                            if resultado.is_ok() {
+                                self.nuevo_proveedor = NuevoProveedor::default();
+                            }
+                        }
+                    });
+                    
+                    egui::CollapsingHeader::new("Sugerencias de reposición").default_open(true).show(ui, |ui| {
+                        let sugerencias = sugerir_reorden(&inventario);
+                        if sugerencias.is_empty() {
+                            ui.label("No hace falta reponer nada.");
+                            return;
+                        }
+                        
+                        egui::Grid::new("sugerencias_reorden").striped(true).num_columns(5).show(ui, |ui| {
+                            for titulo in ["Producto", "Proveedor", "Stock", "Pedido", "Sugerido"] {
+                                ui.strong(titulo);
+                            }
+                            ui.end_row();
+                            
+                            for sugerencia in &sugerencias {
+                                ui.label(&sugerencia.nombre);
+                                ui.label(nombre_proveedor(sugerencia.proveedor_id));
+                                ui.label(format!("{} / {}", sugerencia.stock, sugerencia.punto_reorden));
+                                ui.label(sugerencia.pendiente.to_string());
+                                ui.label(sugerencia.cantidad.to_string());
+                                ui.end_row();
+                            }
+                        });
+                        
+                        if ui.button("Crear órdenes en borrador")
+                            .on_hover_text("Una por proveedor; los productos sin proveedor quedan afuera")
+                            .clicked()
+                        {
+                            resultado = crear_ordenes_sugeridas(&mut inventario)
+                                .map(|creadas| Some(format!("Órdenes creadas: {}", creadas.len())));
+                        }
+                    });
+                    
+                    egui::CollapsingHeader::new("Nueva orden").show(ui, |ui| {
+                        egui::ComboBox::new("proveedor_nueva_orden", "Proveedor")
+                            .selected_text(nombre_proveedor(self.nueva_orden.proveedor_id))
+                            .show_ui(ui, |ui| {
+                                for (id, nombre) in &proveedores {
+                                    ui.selectable_value(&mut self.nueva_orden.proveedor_id, Some(*id), nombre);
+                                }
+                            });
+                        
+                        for linea in &self.nueva_orden.lineas {
+                            ui.label(format!("{} × {}", linea.cantidad, nombre_producto(linea.producto_id)));
+                        }
+                        
+                        ui.horizontal(|ui| {
+                            ui.label("Producto ID:");
+                            ui.add(egui::TextEdit::singleline(&mut self.nueva_orden.producto_id).desired_width(50.0));
+                            ui.label("Cantidad:");
+                            ui.add(egui::TextEdit::singleline(&mut self.nueva_orden.cantidad).desired_width(50.0));
+                            if ui.button("Agregar línea").clicked() {
+                                let producto_id = self.nueva_orden.producto_id.trim().parse::<u32>().ok();
+                                let cantidad = self.nueva_orden.cantidad.trim().parse::<u32>().ok();
+                                match (producto_id.and_then(|id| inventario.obtener_producto(id)), cantidad) {
+                                    (Some(producto), Some(cantidad)) if cantidad > 0 => {
+                                        // Se pide al último costo conocido
+                                        self.nueva_orden.lineas.push(LineaOrden::new(producto.id, cantidad, producto.costo));
+                                        self.nueva_orden.producto_id.clear();
+                                        self.nueva_orden.cantidad.clear();
+                                    }
+                                    (None, _) => self.mensaje = Some("Error: no existe ese producto".to_string()),
+                                    _ => self.mensaje = Some("Error: la cantidad debe ser un número positivo".to_string()),
+                                }
+                            }
+                        });
+                        
+                        let habilitado = self.nueva_orden.proveedor_id.is_some() && !self.nueva_orden.lineas.is_empty();
+                        if ui.add_enabled(habilitado, egui::Button::new("Crear orden")).clicked() {
+                            if let Some(proveedor_id) = self.nueva_orden.proveedor_id {
+                                let lineas = std::mem::take(&mut self.nueva_orden.lineas);
+                                resultado = inventario.crear_orden(proveedor_id, lineas, None)
+                                    .map(|id| Some(format!("Orden #{} creada en borrador", id)));
+                            }
+                        }
+                    });
+                    
+                    egui::CollapsingHeader::new("Órdenes").default_open(true).show(ui, |ui| {
+                        if !ubicaciones.is_empty() {
+                            selector_ubicacion(
+                                ui, "ubicacion_recepcion", "Recibir en", &mut self.ubicacion_recepcion, &ubicaciones, "Sin ubicación",
+                            );
+                        }
+                        
+                        // Las más recientes primero
+                        let ordenes: Vec<_> = inventario.ordenes().into_iter().rev().cloned().collect();
+                        for orden in ordenes {
+                            let fecha = Local.timestamp_opt(orden.fecha, 0)
+                                .single()
+                                .map(|f| f.format("%Y-%m-%d").to_string())
+                                .unwrap_or_default();
+                            let titulo = format!(
+                                "#{} · {} · {} · {}", orden.id, nombre_proveedor(Some(orden.proveedor_id)), fecha, orden.estado
+                            );
+                            
+                            egui::CollapsingHeader::new(titulo).id_source(("orden", orden.id)).show(ui, |ui| {
+                                if let Some(nota) = &orden.nota {
+                                    ui.label(RichText::new(nota).small());
+                                }
+                                
+                                egui::Grid::new(("lineas_orden", orden.id)).striped(true).num_columns(5).show(ui, |ui| {
+                                    for titulo in ["Producto", "Pedido", "Recibido", "Costo", "Recibir"] {
+                                        ui.strong(titulo);
+                                    }
+                                    ui.end_row();
+                                    
+                                    for linea in &orden.lineas {
+                                        ui.label(nombre_producto(linea.producto_id));
+                                        ui.label(linea.cantidad.to_string());
+                                        ui.label(linea.recibida.to_string());
+                                        ui.label(linea.costo_unitario.map(|c| c.formatear(&formato)).unwrap_or_else(|| "—".to_string()));
+                                        if orden.estado.abierta() && linea.pendiente() > 0 {
+                                            let texto = self.recepcion.entry((orden.id, linea.producto_id)).or_default();
+                                            ui.add(egui::TextEdit::singleline(texto).hint_text(linea.pendiente().to_string()).desired_width(50.0));
+                                        } else {
+                                            ui.label("");
+                                        }
+                                        ui.end_row();
+                                    }
+                                });
+                                
+                                ui.horizontal(|ui| match orden.estado {
+                                    EstadoOrden::Borrador => {
+                                        if ui.button("Enviar").clicked() {
+                                            resultado = inventario.enviar_orden(orden.id)
+                                                .map(|()| Some(format!("Orden #{} enviada", orden.id)));
+                                        }
+                                        if ui.button("Eliminar").clicked() {
+                                            resultado = inventario.eliminar_orden(orden.id)
+                                                .map(|()| Some(format!("Orden #{} eliminada", orden.id)));
+                                        }
+                                    }
+                                    EstadoOrden::Enviada | EstadoOrden::RecibidaParcial => {
+                                        let recibir = ui.button("Recibir").on_hover_text("Las cantidades escritas").clicked();
+                                        let todo = ui.button("Recibir todo lo pendiente").clicked();
+                                        if recibir || todo {
+                                            let mut cantidades = Vec::new();
+                                            for linea in orden.lineas.iter().filter(|l| l.pendiente() > 0) {
+                                                let texto = self.recepcion.get(&(orden.id, linea.producto_id))
+                                                    .map(|t| t.trim())
+                                                    .unwrap_or_default();
+                                                match texto.parse::<u32>() {
+                                                    _ if todo => cantidades.push((linea.producto_id, linea.pendiente())),
+                                                    Ok(cantidad) => cantidades.push((linea.producto_id, cantidad)),
+                                                    Err(_) if texto.is_empty() => {}
+                                                    Err(_) => {
+                                                        resultado = Err(InventarioError::Validacion(format!("cantidad inválida: \"{}\"", texto)));
+                                                        return;
+                                                    }
+                                                }
+                                            }
+                                            resultado = inventario.recibir_orden(orden.id, &cantidades, self.ubicacion_recepcion.as_deref())
+                                                .map(|()| Some(format!("Mercadería de la orden #{} recibida", orden.id)));
+                                            if resultado.is_ok() {
+                                                self.recepcion.retain(|(id, _), _| *id != orden.id);
+                                            }
+                                        }
+                                    }
+                                    EstadoOrden::Recibida => {}
+                                });
+                            });
+                        }
+                    });
+                });
+            });
+        
+        match resultado {
+            Ok(Some(mensaje)) => self.mensaje = Some(mensaje),
+            Ok(None) => {}
+            Err(e) => self.mensaje = Some(format!("Error: {}", e)),
+        }
+        if !abierto {
+            self.compras_abiertas = false;
+        }
+    }
+    
+    // Ventana de caja: carrito, impuestos y cobro con recibo
+    fn mostrar_caja(&mut self, ctx: &egui::Context) {
+        if !self.caja_abierta {
+            return;
+        }
+        
+        let mut inventario = self.inventario.lock().unwrap();
+        let ubicaciones: Vec<String> = inventario.ubicaciones().into_iter().map(String::from).collect();
+        if self.ubicacion_caja.as_ref().is_some_and(|u| !ubicaciones.contains(u)) {
+            self.ubicacion_caja = None;
+        }
+        // El carrito puede tener productos que se eliminaron mientras tanto
+        self.carrito.lineas.retain(|(id, _)| inventario.obtener_producto(*id).is_some());
+        let formato = self.formato_moneda;
+        
+        let mut abierto = true;
+        egui::Window::new("Caja")
+            .open(&mut abierto)
+            .default_width(450.0)
+            .show(ctx, |ui| {
+                // Tras cobrar se muestra el recibo hasta empezar otra venta
+                if let Some(venta) = self.ultima_venta.clone() {
+                    let recibo = venta.recibo(&formato);
+                    ui.label(RichText::new(&recibo).monospace());
+                    ui.horizontal(|ui| {
+                        if ui.button("Guardar recibo").clicked() {
+                            if let Some(path) = FileDialog::new()
+                                .add_filter("Texto", &["txt"])
+                                .set_file_name("recibo.txt")
+                                .save_file()
+                            {
+                                match exportar_recibo(path.to_str().unwrap_or_default(), &venta, &formato) {
+                                    Ok(()) => self.mensaje = Some("Recibo guardado".to_string()),
+                                    Err(e) => self.mensaje = Some(format!("Error al guardar el recibo: {}", e)),
+                                }
+                            }
+                        }
+                        if ui.button("Nueva venta").clicked() {
+                            self.ultima_venta = None;
+                        }
+                    });
+                    return;
+                }
+                
+                // Un lector de códigos escribe el código y Enter: se agrega directo
+                let respuesta = ui.horizontal(|ui| {
+                    ui.label("Producto:");
+                    ui.add(egui::TextEdit::singleline(&mut self.busqueda_caja).hint_text("nombre, SKU o código de barras"))
+                }).inner;
+                let texto = self.busqueda_caja.trim().to_string();
+                if respuesta.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) && !texto.is_empty() {
+                    match inventario.buscar_por_codigo(&texto) {
+                        Some(producto) => {
+                            self.carrito.agregar(producto.id, 1);
+                            self.busqueda_caja.clear();
+                        }
+                        None => self.mensaje = Some(format!("No hay ningún producto con el código \"{}\"", texto)),
+                    }
+                    respuesta.request_focus();
+                }
+                if !texto.is_empty() {
+                    for producto in ventas::buscar(&inventario, &texto).into_iter().take(8) {
+                        ui.horizontal(|ui| {
+                            if ui.small_button("+").clicked() {
+                                self.carrito.agregar(producto.id, 1);
+                                self.busqueda_caja.clear();
+                            }
+                            ui.label(format!("{} · {} · stock {}", producto.nombre_completo(), producto.precio.formatear(&formato), producto.stock));
+                        });
+                    }
+                }
+                
+                ui.separator();
+                
+                if self.carrito.esta_vacio() {
+                    ui.label("El carrito está vacío.");
+                    return;
+                }
+                
+                let mut quitar = None;
+                egui::Grid::new("carrito").striped(true).num_columns(5).show(ui, |ui| {
+                    for titulo in ["Producto", "Cantidad", "Precio", "Importe", ""] {
+                        ui.strong(titulo);
+                    }
+                    ui.end_row();
+                    
+                    for (id, cantidad) in &mut self.carrito.lineas {
+                        let Some(producto) = inventario.obtener_producto(*id) else {
+                            continue;
+                        };
+                        ui.label(producto.nombre_completo());
+                        ui.add(egui::DragValue::new(cantidad).clamp_range(1..=u32::MAX));
+                        ui.label(producto.precio.formatear(&formato));
+                        let importe = Dinero::new(producto.precio.centavos.saturating_mul(u64::from(*cantidad)), producto.precio.moneda);
+                        ui.label(importe.formatear(&formato));
+                        if ui.small_button("×").on_hover_text("Quitar del carrito").clicked() {
+                            quitar = Some(*id);
+                        }
+                        ui.end_row();
+                    }
+                });
+                if let Some(id) = quitar {
+                    self.carrito.quitar(id);
+                }
+                
+                ui.horizontal(|ui| {
+                    ui.label("Impuestos (%):");
+                    ui.add(egui::TextEdit::singleline(&mut self.tasa_impuesto).hint_text("0").desired_width(50.0));
+                });
+                if !ubicaciones.is_empty() {
+                    selector_ubicacion(ui, "ubicacion_caja", "Sale de", &mut self.ubicacion_caja, &ubicaciones, "Sin ubicación");
+                }
+                
+                let venta = TasaImpuesto::parsear(&self.tasa_impuesto)
+                    .map_err(InventarioError::Validacion)
+                    .and_then(|tasa| Venta::calcular(&inventario, &self.carrito, tasa));
+                match &venta {
+                    Ok(venta) => {
+                        ui.label(format!("Subtotal: {}", venta.subtotal.formatear(&formato)));
+                        ui.label(format!("Impuestos ({}): {}", venta.tasa, venta.impuesto.formatear(&formato)));
+                        ui.strong(format!("Total: {}", venta.total.formatear(&formato)));
+                    }
+                    Err(e) => {
+                        ui.label(RichText::new(e.to_string()).color(Color32::RED));
+                    }
+                }
+                
+                ui.horizontal(|ui| {
+                    if ui.add_enabled(venta.is_ok(), egui::Button::new("Cobrar")).clicked() {
+                        let tasa = venta.as_ref().map(|v| v.tasa).unwrap_or_default();
+                        match ventas::cobrar(&mut inventario, &self.carrito, tasa, self.ubicacion_caja.as_deref()) {
+                            Ok(venta) => {
+                                self.mensaje = Some(format!("Venta registrada por {}", venta.total.formatear(&formato)));
+                                self.ultima_venta = Some(venta);
+                                self.carrito.vaciar();
+                            }
+                            Err(e) => self.mensaje = Some(format!("Error: {}", e)),
+                        }
+                    }
+                    if ui.button("Vaciar carrito").clicked() {
+                        self.carrito.vaciar();
+                    }
+                });
+            });
+        
+        if !abierto {
+            self.caja_abierta = false;
+        }
+    }
+    
+    fn mostrar_tabla_productos(&mut self, ui: &mut Ui) {
+        // En lugar de mantener el lock durante todo el renderizado,
+        // primero obtenemos todos los datos necesarios y luego liberamos el mutex
+        
+        // Estructura para almacenar los datos de producto que necesitamos para la UI
+        #[derive(Clone)]
+        struct ProductoUI {
+            id: u32,
+            nombre: String,
+            precio: Dinero,
+            costo: Option<Dinero>,
+            stock: u32,
+            // Unidades en la ubicación elegida en el filtro, si hay una
+            stock_ubicacion: Option<u32>,
+            tags: Vec<String>,
+            punto_reorden: Option<u32>,
+            sku: Option<String>,
+            codigo_barras: Option<String>,
+            // Punto de reorden efectivo, propio o heredado
+            umbral: u32,
+            bajo_stock: bool,
+            // Atributos, si es una variante
+            variante: Option<(u32, String)>,
+            variantes: usize,
+            // Stock propio más el de las variantes
+            stock_agregado: u32,
+        }
+        
+        // Recopilamos todos los datos mientras tenemos el mutex bloqueado
+        let productos_ui: Vec<ProductoUI> = {
+            let inventario = self.inventario.lock().unwrap();
+            
+            // Un producto con variantes suma el stock de todas ellas
+            let stock_en = |p: &Producto, ubicacion: Option<&str>| {
+                p.stock_en(ubicacion) + inventario.variantes_de(p.id).iter().map(|v| v.stock_en(ubicacion)).sum::<u32>()
+            };
+            
+            // Con un nombre se usa el índice de búsqueda (ordenado por relevancia)
+            // y con un rango de precios el índice ordenado por precio,
+            // en la moneda principal
+            let moneda = inventario.moneda_principal();
+            let min = Dinero::parsear(&self.filtro_precio_min, moneda).ok();
+            let max = Dinero::parsear(&self.filtro_precio_max, moneda).ok();
+            let rango_precio = min.unwrap_or(Dinero::new(0, moneda))..=max.unwrap_or(Dinero::new(u64::MAX, moneda));
+            let productos = if !self.filtro_nombre.trim().is_empty() {
+                // Se vuelve a buscar solo si cambió el texto o el inventario
+                let vigente = matches!(&self.busqueda_nombre, Some((texto, generacion, _))
+                    if *texto == self.filtro_nombre && *generacion == self.generacion);
+                if !vigente {
+                    let ids = inventario.buscar_por_nombre(&self.filtro_nombre).iter().map(|p| p.id).collect();
+                    self.busqueda_nombre = Some((self.filtro_nombre.clone(), self.generacion, ids));
+                }
+                let ids = self.busqueda_nombre.as_ref().map_or(&[][..], |(_, _, ids)| ids.as_slice());
+                ids.iter().filter_map(|id| inventario.obtener_producto(*id)).collect()
+            } else if min.is_some() || max.is_some() {
+                inventario.productos_en_rango(*rango_precio.start(), *rango_precio.end())
+            } else {
+                inventario.obtener_todos_productos()
+            };
+            
+            // Una etiqueta suelta se busca por coincidencia parcial; una consulta
+            // con AND/OR/NOT se resuelve con operaciones sobre los conjuntos de tags
+            let ids_por_tags: Option<HashSet<u32>> = match ConsultaTags::parsear(&self.filtro_tag) {
+                Ok(ConsultaTags::Tag(_)) | Err(_) => None,
+                Ok(consulta) => Some(inventario.buscar_por_consulta(&consulta).iter().map(|p| p.id).collect()),
+            };
+            
+            productos
+                .into_iter()
+                .filter(|p| {
+                    // Sin rango se muestran los precios en todas las monedas
+                    let coincide_precio = (min.is_none() && max.is_none()) || rango_precio.contains(&p.precio);
+                    
+                    let coincide_tag = match &ids_por_tags {
+                        Some(ids) => ids.contains(&p.id),
+                        None => self.filtro_tag.trim().is_empty() ||
+                            p.tags.iter().any(|t| t.to_lowercase().contains(&self.filtro_tag.trim().to_lowercase())),
+                    };
+                    
+                    let coincide_stock = !self.mostrar_bajo_stock || inventario.tiene_bajo_stock(p.id);
+                    
+                    let coincide_ubicacion = match self.filtro_ubicacion.as_deref() {
+                        Some(ubicacion) => stock_en(p, Some(ubicacion)) > 0,
+                        None => true,
+                    };
+                    
+                    coincide_precio && coincide_tag && coincide_stock && coincide_ubicacion
+                })
+                .map(|p| ProductoUI {
+                    id: p.id,
+                    nombre: p.nombre.clone(),
+                    precio: p.precio,
+                    costo: p.costo,
+                    stock: p.stock,
+                    stock_ubicacion: self.filtro_ubicacion.as_deref().map(|u| stock_en(p, Some(u))),
+                    tags: p.tags.iter().cloned().collect(),
+                    punto_reorden: p.punto_reorden,
+                    sku: p.sku.clone(),
+                    codigo_barras: p.codigo_barras.clone(),
+                    umbral: inventario.punto_reorden_de(p.id).unwrap_or_default(),
+                    bajo_stock: inventario.tiene_bajo_stock(p.id),
+                    variante: p.variante.as_ref().map(|v| (v.padre_id, v.descripcion())),
+                    variantes: inventario.variantes_de(p.id).len(),
+                    stock_agregado: inventario.stock_agregado(p.id),
+                })
+                .collect()
+        }; // El mutex se libera aquí
+        
+        // Las variantes van debajo de su producto, si está en la lista y
+        // desplegado; si no, como un producto más
+        let visibles: HashSet<u32> = productos_ui.iter().map(|p| p.id).collect();
+        let mut variantes: HashMap<u32, Vec<&ProductoUI>> = HashMap::new();
+        for producto in &productos_ui {
+            if let Some((padre, _)) = producto.variante.as_ref().filter(|(padre, _)| visibles.contains(padre)) {
+                variantes.entry(*padre).or_default().push(producto);
+            }
+        }
+        let mut filas: Vec<&ProductoUI> = Vec::new();
+        for producto in &productos_ui {
+            if producto.variante.as_ref().is_some_and(|(padre, _)| visibles.contains(padre)) {
+                continue;
+            }
+            filas.push(producto);
+            if self.variantes_abiertas.contains(&producto.id) {
+                let mut hijas = variantes.remove(&producto.id).unwrap_or_default();
+                hijas.sort_by_key(|v| v.id);
+                filas.extend(hijas);
+            }
+        }
+        
+        ui.heading(format!("Productos ({})", productos_ui.len()));
+        
+        if productos_ui.is_empty() {
+            ui.label("No hay productos que coincidan con los filtros aplicados.");
+            return;
+        }
+        
+        // Fila del producto encontrado por código, si hay que llegar a ella
+        let fila_destino = self.desplazar_a.take()
+            .and_then(|id| filas.iter().position(|p| p.id == id));
+        
+        // Crear tabla
+        ScrollArea::vertical().show(ui, |ui| {
+            let mut tabla = TableBuilder::new(ui);
+            if let Some(fila) = fila_destino {
+                tabla = tabla.scroll_to_row(fila, Some(egui::Align::Center));
+            }
+            
+            tabla
+                .striped(true)
+                .resizable(true)
+                .cell_layout(egui::Layout::left_to_right(egui::Align::Center))
+                .column(Column::auto().at_least(50.0)) // ID
+                .column(Column::remainder().at_least(200.0)) // Nombre
+                .column(Column::auto().at_least(100.0)) // Precio
+                .column(Column::auto().at_least(100.0)) // Stock
+                .column(Column::remainder().at_least(200.0)) // Etiquetas
+                .column(Column::auto()) // Acciones
+                .header(20.0, |mut header| {
+                    header.col(|ui| { ui.strong("ID"); });
+                    header.col(|ui| { ui.strong("Nombre"); });
+                    header.col(|ui| { ui.strong("Precio"); });
+                    header.col(|ui| {
+                        match &self.filtro_ubicacion {
+                            Some(ubicacion) => ui.strong(format!("Stock en {}", ubicacion)),
+                            None => ui.strong("Stock"),
+                        };
+                    });
+                    header.col(|ui| { ui.strong("Etiquetas"); });
+                    header.col(|ui| { ui.strong("Acciones"); });
+                })
+                .body(|mut body| {
+                    for producto in filas {
+                        body.row(30.0, |mut row| {
+                            row.col(|ui| { ui.label(producto.id.to_string()); });
+                            row.col(|ui| {
+                                // Los productos con variantes se despliegan con la flecha
+                                let abierto = self.variantes_abiertas.contains(&producto.id);
+                                if producto.variantes > 0 && ui.small_button(if abierto { "⏷" } else { "⏵" }).clicked() {
+                                    if abierto {
+                                        self.variantes_abiertas.remove(&producto.id);
+                                    } else {
+                                        self.variantes_abiertas.insert(producto.id);
+                                    }
+                                }
+                                let nombre = match &producto.variante {
+                                    Some((padre, atributos)) if visibles.contains(padre) => format!("    ↳ {}", atributos),
+                                    Some((_, atributos)) => format!("{} ({})", producto.nombre, atributos),
+                                    None if producto.variantes > 0 => {
+                                        format!("{} ({} variantes)", producto.nombre, producto.variantes)
+                                    }
+                                    None => producto.nombre.clone(),
+                                };
+                                
+                                // Al seleccionar la fila se muestra su historial
+                                let seleccionado = self.producto_seleccionado == Some(producto.id);
+                                let mut etiqueta = ui.selectable_label(seleccionado, nombre);
+                                let codigos: Vec<String> = [("SKU", &producto.sku), ("Código de barras", &producto.codigo_barras)]
+                                    .into_iter()
+                                    .filter_map(|(nombre, codigo)| codigo.as_ref().map(|c| format!("{}: {}", nombre, c)))
+                                    .collect();
+                                if !codigos.is_empty() {
+                                    etiqueta = etiqueta.on_hover_text(codigos.join("\n"));
+                                }
+                                if etiqueta.clicked() {
+                                    self.producto_seleccionado = if seleccionado { None } else { Some(producto.id) };
+                                    self.nuevo_movimiento = NuevoMovimiento::default();
+                                }
+                            });
+                            row.col(|ui| {
+                                let precio = ui.label(producto.precio.formatear(&self.formato_moneda));
+                                if let Some(costo) = producto.costo {
+                                    precio.on_hover_text(format!("Costo: {}", costo.formatear(&self.formato_moneda)));
+                                }
+                            });
+                            
+                            // Color rojo para bajo stock
+                            row.col(|ui| { 
+                                let stock = producto.stock_ubicacion.unwrap_or(producto.stock_agregado).to_string();
+                                let text = if producto.bajo_stock {
+                                    RichText::new(stock).color(Color32::RED)
+                                } else {
+                                    RichText::new(stock)
+                                };
+                                let detalle = if producto.variantes > 0 {
+                                    format!("Total: {}, sumando sus variantes", producto.stock_agregado)
+                                } else {
+                                    format!("Total: {} · Punto de reorden: {}", producto.stock, producto.umbral)
+                                };
+                                ui.label(text).on_hover_text(detalle);
+                            });
+                            
+                            // Etiquetas
+                            row.col(|ui| { 
+                                ui.horizontal_wrapped(|ui| {
+                                    for tag in &producto.tags {
+                                        ui.label(
+                                            RichText::new(tag)
+                                                .background_color(Color32::from_rgb(230, 230, 250))
+                                        );
+                                        ui.add_space(2.0);
+                                    }
+                                });
+                            });
+                            
+                            // Acciones
+                            row.col(|ui| { 
+                                let id = producto.id;
+                                ui.horizontal(|ui| {
+                                    if ui.button("Editar").clicked() {
+                                        // Preparar para edición
+                                        self.modo_edicion = Some(id);
+                                        self.nuevo_producto.nombre = producto.nombre.clone();
+                                        self.nuevo_producto.precio = producto.precio.monto();
+                                        self.nuevo_producto.moneda = producto.precio.moneda.to_string();
+                                        self.nuevo_producto.costo = producto.costo.map(|c| c.monto()).unwrap_or_default();
+                                        self.nuevo_producto.stock = producto.stock.to_string();
+                                        self.nuevo_producto.tags = producto.tags.join(", ");
+                                        self.nuevo_producto.punto_reorden = producto.punto_reorden
+                                            .map(|p| p.to_string())
+                                            .unwrap_or_default();
+                                        self.nuevo_producto.sku = producto.sku.clone().unwrap_or_default();
+                                        self.nuevo_producto.codigo_barras = producto.codigo_barras.clone().unwrap_or_default();
+                                    }
+                                    
+                                    if producto.variante.is_none() && ui.button("Variante").clicked() {
+                                        self.nueva_variante = Some(NuevaVariante {
+                                            padre_id: id,
+                                            nombre: producto.nombre.clone(),
+                                            atributos: String::new(),
+                                            stock: String::new(),
+                                            precio: String::new(),
+                                        });
+                                    }
+                                    
+                                    if ui.button("Eliminar").clicked() {
+                                        match self.inventario.lock().unwrap().eliminar_producto(id) {
+                                            Ok(_) => self.mensaje = Some(format!("Producto ID {} eliminado", id)),
+                                            Err(e) => self.mensaje = Some(format!("Error: {}", e)),
+                                        }
+                                    }
+                                });
+                            });
+                        });
+                    }
+                });
+        });
+    }
+}
+
+/// Selector de ubicación; `None` se muestra con `texto_ninguna`
+fn selector_ubicacion(
+    ui: &mut Ui,
+    id: &str,
+    etiqueta: &str,
+    seleccion: &mut Option<String>,
+    ubicaciones: &[String],
+    texto_ninguna: &str,
+) {
+    egui::ComboBox::new(id, etiqueta)
+        .selected_text(seleccion.as_deref().unwrap_or(texto_ninguna))
+        .show_ui(ui, |ui| {
+            ui.selectable_value(seleccion, None, texto_ninguna);
+            for ubicacion in ubicaciones {
+                ui.selectable_value(seleccion, Some(ubicacion.clone()), ubicacion);
+            }
+        });
+}
+
+/// Diario del inventario que todavía no se guardó en ningún archivo
+fn diario_sin_guardar() -> String {
+    eframe::storage_dir(NOMBRE_APP)
+        .unwrap_or_else(std::env::temp_dir)
+        .join("sin_guardar.diario.jsonl")
+        .to_string_lossy()
+        .to_string()
+}
+
+/// Abrir `ultimo` (o el inventario sin guardar) aplicando su diario.
+/// Devuelve el gestor, el archivo abierto y un aviso para mostrar.
+fn recuperar_sesion(ultimo: Option<String>) -> (InventarioManager, Option<String>, Option<String>) {
+    let mut avisos = Vec::new();
+    let ultimo = ultimo.filter(|ruta| std::path::Path::new(ruta).exists());
+    let candidatos = if ultimo.is_some() { vec![ultimo, None] } else { vec![None] };
+    
+    for archivo in candidatos {
+        let ruta = archivo.as_deref().map(ruta_diario).unwrap_or_else(diario_sin_guardar);
+        let abierto = DiarioStorage::abrir(archivo.as_deref(), &ruta).and_then(|diario| {
+            let pendientes = diario.pendientes();
+            InventarioManager::con_almacenamiento(Box::new(diario)).map(|inventario| (inventario, pendientes))
+        });
+        
+        match abierto {
+            Ok((inventario, pendientes)) => {
+                if pendientes > 0 {
+                    avisos.push(format!("Se recuperaron {} cambios que no se habían guardado", pendientes));
+                }
+                let aviso = (!avisos.is_empty()).then(|| avisos.join(". "));
+                return (inventario, archivo, aviso);
+            }
+            Err(e) => avisos.push(format!(
+                "No se pudo abrir {}: {}",
+                archivo.as_deref().unwrap_or("el inventario sin guardar"), e
+            )),
+        }
+    }
+    
+    (InventarioManager::new(), None, Some(avisos.join(". ")))
+}
+
+/// Precio y stock de un producto, para listarlo en una línea
+fn resumen_producto(producto: &Producto, formato: &FormatoMoneda) -> String {
+    format!("{}, stock {}", producto.precio.formatear(formato), producto.stock)
+}
+
+/// Valor de un campo de un producto tal como se muestra al compararlo
+fn valor_campo(producto: &Producto, campo: Campo, formato: &FormatoMoneda) -> String {
+    let opcional = |valor: Option<String>| valor.unwrap_or_else(|| "—".to_string());
+    match campo {
+        Campo::Nombre => producto.nombre.clone(),
+        Campo::Precio => producto.precio.formatear(formato),
+        Campo::Stock => producto.stock.to_string(),
+        Campo::Tags => {
+            let mut tags: Vec<&str> = producto.tags.iter().map(String::as_str).collect();
+            tags.sort_unstable();
+            tags.join(", ")
+        }
+        Campo::PuntoReorden => opcional(producto.punto_reorden.map(|p| p.to_string())),
+        Campo::Existencias => producto.existencias.iter()
+            .map(|(ubicacion, cantidad)| format!("{}: {}", ubicacion, cantidad))
+            .collect::<Vec<_>>()
+            .join(", "),
+        Campo::Sku => opcional(producto.sku.clone()),
+        Campo::CodigoBarras => opcional(producto.codigo_barras.clone()),
+        Campo::Costo => opcional(producto.costo.map(|c| c.formatear(formato))),
+        Campo::Proveedor => opcional(producto.proveedor_id.map(|id| format!("ID {}", id))),
+        Campo::Variante => opcional(producto.variante.as_ref()
+            .map(|v| format!("de ID {}: {}", v.padre_id, v.descripcion()))),
+    }
+}
+
+/// Lista corta de los campos que cambian de `anterior` a `nuevo`
+fn describir_cambios(anterior: &Producto, nuevo: &Producto, formato: &FormatoMoneda) -> String {
+    Campo::TODOS.into_iter()
+        .filter(|campo| !campo.igual(anterior, nuevo))
+        .map(|campo| format!(
+            "{} {} → {}",
+            campo.to_string().to_lowercase(), valor_campo(anterior, campo, formato), valor_campo(nuevo, campo, formato)
+        ))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+// Margen en porcentaje, o una raya si no se conoce el costo
+fn texto_margen(margen: Option<f64>) -> String {
+    margen.map(|m| format!("{:.1} %", m)).unwrap_or_else(|| "—".to_string())
+}
+
+impl eframe::App for InventarioApp {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        self.atender_eventos();
+        
+        // Atajos de deshacer/rehacer, salvo mientras se edita un campo de texto
+        // (que tiene su propio deshacer)
+        if ctx.memory(|m| m.focus().is_none()) {
+            let rehacer = egui::KeyboardShortcut::new(egui::Modifiers::COMMAND | egui::Modifiers::SHIFT, egui::Key::Z);
+            let deshacer = egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::Z);
+            
+            // Primero el atajo con Shift, que también contiene Ctrl+Z
+            if ctx.input_mut(|i| i.consume_shortcut(&rehacer)) {
+                self.rehacer();
+            } else if ctx.input_mut(|i| i.consume_shortcut(&deshacer)) {
+                self.deshacer();
+            }
+        }
+        
+        egui::CentralPanel::default().show(ctx, |ui| {
+            ui.heading(RichText::new("Sistema de Gestión de Inventario").size(24.0));
+            
+            // Mensaje de estado
+            let mostrar_cierre = if let Some(msg) = &self.mensaje {
+                ui.horizontal(|ui| {
+                    ui.label(RichText::new(msg).color(Color32::from_rgb(0, 100, 0)));
+                    ui.button("×").clicked()
+                }).inner
+            } else {
+                false
+            };
+
+            if mostrar_cierre {
+                self.mensaje = None;
+            }
+            
+            egui::TopBottomPanel::top("top_panel").show_inside(ui, |ui| {
+                ui.horizontal(|ui| {
+                    if ui.button("Agregar Producto").clicked() {
+                        self.nuevo_producto = NuevoProducto::default();
+                        self.modo_edicion = None;
+                    }
+                    
+                    ui.separator();
+                    
+                    let (deshacer, rehacer) = {
+                        let inventario = self.inventario.lock().unwrap();
+                        (
+                            inventario.descripcion_deshacer().map(str::to_string),
+                            inventario.descripcion_rehacer().map(str::to_string),
+                        )
+                    };
+                    
+                    let boton = ui.add_enabled(deshacer.is_some(), egui::Button::new("⟲ Deshacer"));
+                    if boton.on_hover_text(deshacer.unwrap_or_default() + " (Ctrl+Z)").clicked() {
+                        self.deshacer();
+                    }
+                    
+                    let boton = ui.add_enabled(rehacer.is_some(), egui::Button::new("⟳ Rehacer"));
+                    if boton.on_hover_text(rehacer.unwrap_or_default() + " (Ctrl+Shift+Z)").clicked() {
+                        self.rehacer();
+                    }
+                    
+                    ui.separator();
+                    if ui.selectable_label(self.reportes_abiertos, "Reportes").clicked() {
+                        self.reportes_abiertos = !self.reportes_abiertos;
+                    }
+                    if ui.selectable_label(self.compras_abiertas, "Compras").clicked() {
+                        self.compras_abiertas = !self.compras_abiertas;
+                    }
+                    if ui.selectable_label(self.caja_abierta, "Caja").clicked() {
+                        self.caja_abierta = !self.caja_abierta;
+                    }
+                    
+                    ui.separator();
+                    self.mostrar_escaner(ui);
+                });
+            });
+            
+            egui::SidePanel::left("side_panel")
+                .resizable(true)
+                .default_width(200.0)
+                .width_range(150.0..=300.0)
+                .show_inside(ui, |ui| {
+                    self.mostrar_sidebar(ui);
+                });
+            
+            if let Some(id) = self.producto_seleccionado {
+                egui::SidePanel::right("history_panel")
+                    .resizable(true)
+                    .default_width(320.0)
+                    .width_range(250.0..=500.0)
+                    .show_inside(ui, |ui| {
+                        self.mostrar_historial(ui, id);
+                    });
+            }
+            
+            egui::TopBottomPanel::bottom("form_panel")
+                .resizable(true)
+                .default_height(150.0)
+                .height_range(100.0..=200.0)
+                .show_inside(ui, |ui| {
+                    self.mostrar_formulario_producto(ui);
+                });
+            
+            // Contenido principal
+            egui::CentralPanel::default().show_inside(ui, |ui| {
+                self.mostrar_tabla_productos(ui);
+            });
+        });
+        
+        self.mostrar_importacion(ctx);
+        self.mostrar_comparacion(ctx);
+        self.mostrar_nueva_variante(ctx);
+        self.mostrar_reporte_carga(ctx);
+        self.mostrar_reportes(ctx);
+        self.mostrar_compras(ctx);
+        self.mostrar_caja(ctx);
+    }
+    
+    // eframe lo llama cada `auto_save_interval` (30 s) y al cerrar la ventana
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        if !self.autoguardado {
+            return;
+        }
+        
+        eframe::set_value(storage, CLAVE_ULTIMO_ARCHIVO, &self.archivo_actual);
+        if let Err(e) = self.inventario.lock().unwrap().sincronizar() {
+            self.mensaje = Some(format!("Error al guardar automáticamente: {}", e));
+        }
+    }
+}
@@ -0,0 +1,55 @@
+// Mediciones del índice de precios con 100.000 productos:
+// `cargo bench -p rustiventario --bench indice_de_precios`
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use rustiventario::{Dinero, InventarioManager, Moneda, MotivoMovimiento};
+
+const CANTIDAD: u32 = 100_000;
+
+fn usd(unidades: u64) -> Dinero {
+    Dinero::desde_unidades(unidades, Moneda::POR_DEFECTO)
+}
+
+/// Inventario con precios repartidos sin orden entre 0 y 9.999
+fn inventario_grande() -> InventarioManager {
+    let mut inventario = InventarioManager::new();
+    for i in 0..CANTIDAD {
+        let precio = u64::from(i.wrapping_mul(7_919) % 10_000);
+        inventario.agregar_producto(format!("Producto {}", i), usd(precio), 10, Vec::new(), None).unwrap();
+    }
+    inventario
+}
+
+fn indice_de_precios(c: &mut Criterion) {
+    let mut grupo = c.benchmark_group("100k productos");
+    grupo.sample_size(10);
+    grupo.bench_function("agregar", |b| b.iter(inventario_grande));
+
+    let mut inventario = inventario_grande();
+    let mut id = 0;
+    grupo.bench_function("cambio de stock", |b| b.iter(|| {
+        id = id % CANTIDAD + 1;
+        inventario.actualizar_stock(id, id % 50, MotivoMovimiento::Compra, None).unwrap();
+    }));
+    grupo.bench_function("cambio de precio", |b| b.iter(|| {
+        id = id % CANTIDAD + 1;
+        let precio = u64::from(id) * 3 % 10_000;
+        inventario.actualizar_producto(id, format!("Producto {}", id), usd(precio), 20, Vec::new(), None).unwrap();
+    }));
+
+    grupo.bench_function("10 más caros", |b| b.iter(|| inventario.productos_mas_caros(black_box(10)).len()));
+    grupo.bench_function("rango de precios", |b| {
+        b.iter(|| inventario.productos_en_rango(black_box(usd(1_000)), black_box(usd(1_100))).len())
+    });
+
+    // Una página de la mitad del rango, retomando desde la anterior
+    let anterior = inventario.pagina_en_rango(usd(0), usd(u64::MAX), None, CANTIDAD as usize / 2);
+    let ultimo = anterior.last().map(|p| (p.precio, p.id));
+    grupo.bench_function("página a mitad del rango", |b| {
+        b.iter(|| inventario.pagina_en_rango(usd(0), usd(u64::MAX), black_box(ultimo), 50).len())
+    });
+    grupo.finish();
+}
+
+criterion_group!(benches, indice_de_precios);
+criterion_main!(benches);
@@ -0,0 +1,244 @@
+// Índice de búsqueda por nombre: palabras normalizadas (sin mayúsculas ni
+// acentos) asociadas a los productos que las contienen
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::ops::Bound;
+
+/// Cuántas palabras parecidas (con errores de tipeo o que solo contienen
+/// la buscada) se aceptan como mucho por cada palabra de la consulta
+const MAXIMO_APROXIMADAS: usize = 20;
+
+/// Índice invertido de las palabras de los nombres de productos
+#[derive(Debug, Default)]
+pub struct IndiceNombres {
+    // Ordenadas, para encontrar las que empiezan igual sin recorrerlas todas
+    ids_por_palabra: BTreeMap<String, HashSet<u32>>,
+    palabras_por_id: HashMap<u32, Vec<String>>,
+}
+
+impl IndiceNombres {
+    /// Indexar (o reindexar) el nombre de un producto
+    pub fn insertar(&mut self, id: u32, nombre: &str) {
+        self.quitar(id);
+
+        let palabras = tokenizar(nombre);
+        for palabra in &palabras {
+            self.ids_por_palabra.entry(palabra.clone()).or_default().insert(id);
+        }
+        self.palabras_por_id.insert(id, palabras);
+    }
+
+    /// Quitar un producto del índice
+    pub fn quitar(&mut self, id: u32) {
+        for palabra in self.palabras_por_id.remove(&id).unwrap_or_default() {
+            if let Some(ids) = self.ids_por_palabra.get_mut(&palabra) {
+                ids.remove(&id);
+                if ids.is_empty() {
+                    self.ids_por_palabra.remove(&palabra);
+                }
+            }
+        }
+    }
+
+    /// Vaciar el índice
+    pub fn limpiar(&mut self) {
+        self.ids_por_palabra.clear();
+        self.palabras_por_id.clear();
+    }
+
+    /// IDs de los productos cuyo nombre contiene todas las palabras de la
+    /// consulta (o algo parecido), con su puntaje, del más relevante al menos
+    pub fn buscar(&self, consulta: &str) -> Vec<(u32, u32)> {
+        let palabras = tokenizar(consulta);
+        if palabras.is_empty() {
+            return Vec::new();
+        }
+
+        let mut puntajes: HashMap<u32, u32> = HashMap::new();
+        for (i, buscada) in palabras.iter().enumerate() {
+            let mejores = self.coincidencias(buscada);
+
+            // Cada palabra de la consulta tiene que coincidir con algo
+            if i == 0 {
+                puntajes = mejores;
+            } else {
+                puntajes.retain(|id, total| match mejores.get(id) {
+                    Some(puntaje) => {
+                        *total += puntaje;
+                        true
+                    }
+                    None => false,
+                });
+            }
+        }
+
+        let mut resultado: Vec<(u32, u32)> = puntajes.into_iter().collect();
+        resultado.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        resultado
+    }
+
+    // Método privado para buscar la mejor coincidencia de una palabra en
+    // cada producto. Las iguales y las que empiezan igual salen del índice
+    // ordenado; solo si no hay ninguna se recorren las demás buscando
+    // parecidas, hasta `MAXIMO_APROXIMADAS`.
+    fn coincidencias(&self, buscada: &str) -> HashMap<u32, u32> {
+        let mut mejores = HashMap::new();
+        let con_el_comienzo = self.ids_por_palabra
+            .range::<str, _>((Bound::Included(buscada), Bound::Unbounded))
+            .take_while(|(palabra, _)| palabra.starts_with(buscada));
+        for (palabra, ids) in con_el_comienzo {
+            anotar(&mut mejores, ids, if palabra == buscada { 4 } else { 3 });
+        }
+        if !mejores.is_empty() {
+            return mejores;
+        }
+
+        let parecidas = self.ids_por_palabra.iter()
+            .filter_map(|(palabra, ids)| parecida(buscada, palabra).map(|puntaje| (ids, puntaje)))
+            .take(MAXIMO_APROXIMADAS);
+        for (ids, puntaje) in parecidas {
+            anotar(&mut mejores, ids, puntaje);
+        }
+        mejores
+    }
+}
+
+// Quedarse con el mejor puntaje de cada producto
+fn anotar(mejores: &mut HashMap<u32, u32>, ids: &HashSet<u32>, puntaje: u32) {
+    for id in ids {
+        let mejor = mejores.entry(*id).or_default();
+        *mejor = (*mejor).max(puntaje);
+    }
+}
+
+/// Pasar a minúsculas y quitar acentos, para comparar sin importar cómo se escribió
+fn normalizar(texto: &str) -> String {
+    texto.chars()
+        .flat_map(char::to_lowercase)
+        .map(|c| match c {
+            'á' | 'à' | 'â' | 'ä' | 'ã' | 'å' => 'a',
+            'é' | 'è' | 'ê' | 'ë' => 'e',
+            'í' | 'ì' | 'î' | 'ï' => 'i',
+            'ó' | 'ò' | 'ô' | 'ö' | 'õ' => 'o',
+            'ú' | 'ù' | 'û' | 'ü' => 'u',
+            'ñ' => 'n',
+            'ç' => 'c',
+            otro => otro,
+        })
+        .collect()
+}
+
+/// Separar un texto en palabras normalizadas
+fn tokenizar(texto: &str) -> Vec<String> {
+    normalizar(texto)
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|palabra| !palabra.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+// Puntaje de una palabra del índice que no es igual a la buscada ni
+// empieza igual (esas puntúan 4 y 3): la contiene 2, con errores de tipeo 1
+fn parecida(buscada: &str, palabra: &str) -> Option<u32> {
+    if palabra.contains(buscada) {
+        return Some(2);
+    }
+
+    // Se toleran más errores cuanto más larga es la palabra buscada
+    let largo = buscada.chars().count();
+    let tolerancia = match largo {
+        0..=3 => return None,
+        4..=7 => 1,
+        _ => 2,
+    };
+
+    // Una palabra mucho más corta no puede estar a tan pocos errores
+    if palabra.chars().count() + tolerancia < largo {
+        return None;
+    }
+
+    // También contra el comienzo de la palabra, por si todavía se está escribiendo
+    let comienzo: String = palabra.chars().take(largo).collect();
+    let cerca = distancia_edicion(buscada, palabra, tolerancia) <= tolerancia
+        || distancia_edicion(buscada, &comienzo, tolerancia) <= tolerancia;
+    cerca.then_some(1)
+}
+
+// Distancia de Levenshtein entre dos palabras, contando caracteres. Deja
+// de calcular apenas se pasa de `tope`, y entonces devuelve `tope + 1`.
+fn distancia_edicion(a: &str, b: &str, tope: usize) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut anterior: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut actual = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let sustitucion = anterior[j] + usize::from(ca != *cb);
+            actual[j + 1] = sustitucion.min(anterior[j + 1] + 1).min(actual[j] + 1);
+        }
+        // Las filas siguientes nunca bajan del mínimo de esta
+        if actual.iter().min().is_some_and(|minimo| *minimo > tope) {
+            return tope + 1;
+        }
+        anterior = actual;
+    }
+
+    anterior[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn indice(nombres: &[&str]) -> IndiceNombres {
+        let mut indice = IndiceNombres::default();
+        for (i, nombre) in nombres.iter().enumerate() {
+            indice.insertar(i as u32 + 1, nombre);
+        }
+        indice
+    }
+
+    fn ids(resultado: Vec<(u32, u32)>) -> Vec<u32> {
+        resultado.into_iter().map(|(id, _)| id).collect()
+    }
+
+    #[test]
+    fn ignora_mayusculas_y_acentos() {
+        let indice = indice(&["Cámara Electrónica", "Teclado", "CAÑÓN"]);
+
+        assert_eq!(ids(indice.buscar("electronica")), [1]);
+        assert_eq!(ids(indice.buscar("ELECTRÓNICA camara")), [1]);
+        assert_eq!(ids(indice.buscar("canon")), [3]);
+        assert!(indice.buscar("  ").is_empty());
+    }
+
+    #[test]
+    fn ordena_por_relevancia_y_tolera_errores() {
+        let indice = indice(&["Monitores curvos", "Monitor 4K", "Soporte de monitor", "Mouse"]);
+
+        // Exactas primero, luego las que empiezan igual
+        assert_eq!(ids(indice.buscar("monitor")), [2, 3, 1]);
+        // Todas las palabras tienen que coincidir
+        assert_eq!(ids(indice.buscar("monitor 4k")), [2]);
+        // Con errores de tipeo
+        assert_eq!(ids(indice.buscar("moniter")), [1, 2, 3]);
+        assert_eq!(ids(indice.buscar("soprte")), [3]);
+        // Las palabras cortas no se corrigen
+        assert!(indice.buscar("mx").is_empty());
+        // Por el comienzo o por una parte de la palabra
+        assert_eq!(ids(indice.buscar("mous")), [4]);
+        assert_eq!(ids(indice.buscar("ouse")), [4]);
+    }
+
+    #[test]
+    fn reindexar_reemplaza_las_palabras_anteriores() {
+        let mut indice = indice(&["Laptop Gamer"]);
+        indice.insertar(1, "Notebook");
+
+        assert!(indice.buscar("gamer").is_empty());
+        assert_eq!(ids(indice.buscar("notebook")), [1]);
+
+        indice.quitar(1);
+        assert!(indice.buscar("notebook").is_empty());
+    }
+}
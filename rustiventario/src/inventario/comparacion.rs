@@ -0,0 +1,172 @@
+// Comparación de dos versiones del inventario (dos archivos, o el
+// inventario actual y un archivo guardado), producto por producto
+
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::inventario::InventarioError;
+use crate::models::Producto;
+use crate::utils::file_io::{cargar_datos, ModoCarga};
+
+/// Dato de un producto que puede cambiar entre dos versiones
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Campo {
+    Nombre,
+    Precio,
+    Stock,
+    Tags,
+    PuntoReorden,
+    Existencias,
+    Sku,
+    CodigoBarras,
+    Costo,
+    Proveedor,
+    Variante,
+}
+
+impl Campo {
+    pub const TODOS: [Campo; 11] = [
+        Campo::Nombre,
+        Campo::Precio,
+        Campo::Stock,
+        Campo::Tags,
+        Campo::PuntoReorden,
+        Campo::Existencias,
+        Campo::Sku,
+        Campo::CodigoBarras,
+        Campo::Costo,
+        Campo::Proveedor,
+        Campo::Variante,
+    ];
+
+    /// Si el campo tiene el mismo valor en los dos productos
+    pub fn igual(&self, a: &Producto, b: &Producto) -> bool {
+        match self {
+            Campo::Nombre => a.nombre == b.nombre,
+            Campo::Precio => a.precio == b.precio,
+            Campo::Stock => a.stock == b.stock,
+            Campo::Tags => a.tags == b.tags,
+            Campo::PuntoReorden => a.punto_reorden == b.punto_reorden,
+            Campo::Existencias => a.existencias == b.existencias,
+            Campo::Sku => a.sku == b.sku,
+            Campo::CodigoBarras => a.codigo_barras == b.codigo_barras,
+            Campo::Costo => a.costo == b.costo,
+            Campo::Proveedor => a.proveedor_id == b.proveedor_id,
+            Campo::Variante => a.variante == b.variante,
+        }
+    }
+}
+
+impl fmt::Display for Campo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let texto = match self {
+            Campo::Nombre => "Nombre",
+            Campo::Precio => "Precio",
+            Campo::Stock => "Stock",
+            Campo::Tags => "Etiquetas",
+            Campo::PuntoReorden => "Punto de reorden",
+            Campo::Existencias => "Existencias",
+            Campo::Sku => "SKU",
+            Campo::CodigoBarras => "Código de barras",
+            Campo::Costo => "Costo",
+            Campo::Proveedor => "Proveedor",
+            Campo::Variante => "Variante",
+        };
+        write!(f, "{}", texto)
+    }
+}
+
+/// Un producto que está en las dos versiones con algún dato distinto
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProductoModificado {
+    pub antes: Producto,
+    pub despues: Producto,
+}
+
+impl ProductoModificado {
+    /// Los campos que cambiaron, en el orden de `Campo::TODOS`
+    pub fn campos(&self) -> Vec<Campo> {
+        Campo::TODOS.into_iter()
+            .filter(|campo| !campo.igual(&self.antes, &self.despues))
+            .collect()
+    }
+}
+
+/// Diferencias entre dos versiones del inventario; los productos se
+/// reconocen por su ID y cada lista va ordenada por ID
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Comparacion {
+    pub agregados: Vec<Producto>,
+    pub eliminados: Vec<Producto>,
+    pub modificados: Vec<ProductoModificado>,
+    /// Productos iguales en las dos versiones
+    pub sin_cambios: usize,
+}
+
+impl Comparacion {
+    /// Si las dos versiones tienen los mismos productos
+    pub fn es_igual(&self) -> bool {
+        self.agregados.is_empty() && self.eliminados.is_empty() && self.modificados.is_empty()
+    }
+}
+
+/// Comparar dos listas de productos
+pub fn comparar(antes: &[Producto], despues: &[Producto]) -> Comparacion {
+    let anteriores: HashMap<u32, &Producto> = antes.iter().map(|p| (p.id, p)).collect();
+    let actuales: HashMap<u32, &Producto> = despues.iter().map(|p| (p.id, p)).collect();
+    let mut comparacion = Comparacion::default();
+
+    for producto in despues {
+        match anteriores.get(&producto.id) {
+            None => comparacion.agregados.push(producto.clone()),
+            Some(&anterior) if anterior == producto => comparacion.sin_cambios += 1,
+            Some(&anterior) => comparacion.modificados.push(ProductoModificado {
+                antes: anterior.clone(),
+                despues: producto.clone(),
+            }),
+        }
+    }
+    comparacion.eliminados = antes.iter()
+        .filter(|p| !actuales.contains_key(&p.id))
+        .cloned()
+        .collect();
+
+    comparacion.agregados.sort_by_key(|p| p.id);
+    comparacion.eliminados.sort_by_key(|p| p.id);
+    comparacion.modificados.sort_by_key(|m| m.antes.id);
+    comparacion
+}
+
+/// Comparar los productos de dos archivos (CSV o JSON)
+pub fn comparar_archivos(ruta_antes: &str, ruta_despues: &str, modo: ModoCarga) -> Result<Comparacion, InventarioError> {
+    let (antes, _) = cargar_datos(ruta_antes, modo)?;
+    let (despues, _) = cargar_datos(ruta_despues, modo)?;
+    Ok(comparar(&antes.productos, &despues.productos))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Dinero, Moneda};
+
+    #[test]
+    fn detecta_altas_bajas_y_cambios() {
+        let usd = |unidades| Dinero::desde_unidades(unidades, Moneda::POR_DEFECTO);
+        let producto = |id, nombre: &str, precio, stock| Producto {
+            id, nombre: nombre.to_string(), precio: usd(precio), stock, ..Default::default()
+        };
+        let antes = vec![producto(1, "Lápiz", 1, 10), producto(2, "Goma", 2, 5), producto(3, "Regla", 3, 1)];
+        let mut lapiz = producto(1, "Lápiz", 2, 8);
+        lapiz.tags.insert("oficina".to_string());
+        let despues = vec![producto(4, "Cuaderno", 5, 2), producto(2, "Goma", 2, 5), lapiz];
+
+        let comparacion = comparar(&antes, &despues);
+        assert!(!comparacion.es_igual());
+        assert_eq!(comparacion.sin_cambios, 1);
+        assert_eq!(comparacion.agregados.iter().map(|p| p.id).collect::<Vec<_>>(), [4]);
+        assert_eq!(comparacion.eliminados.iter().map(|p| p.id).collect::<Vec<_>>(), [3]);
+        assert_eq!(comparacion.modificados.len(), 1);
+        assert_eq!(comparacion.modificados[0].campos(), [Campo::Precio, Campo::Stock, Campo::Tags]);
+        assert!(comparar(&despues, &despues).es_igual());
+    }
+}
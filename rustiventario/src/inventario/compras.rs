@@ -0,0 +1,162 @@
+// Sugerencias de reposición a partir de los productos con bajo stock
+
+use std::collections::BTreeMap;
+
+use crate::inventario::{InventarioError, InventarioManager};
+use crate::models::LineaOrden;
+
+/// Un producto que conviene volver a pedir
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SugerenciaReorden {
+    pub producto_id: u32,
+    pub nombre: String,
+    pub proveedor_id: Option<u32>,
+    pub stock: u32,
+    pub punto_reorden: u32,
+    /// Unidades ya pedidas en órdenes enviadas que todavía no llegaron
+    pub pendiente: u32,
+    /// Unidades a pedir
+    pub cantidad: u32,
+}
+
+/// Productos con bajo stock y cuánto pedir de cada uno, ordenados por ID.
+///
+/// Se repone hasta el doble del punto de reorden (o uno más, si el punto
+/// es cero), descontando lo que ya está pedido; los productos que con lo
+/// pedido ya llegan no aparecen.
+pub fn sugerir_reorden(inventario: &InventarioManager) -> Vec<SugerenciaReorden> {
+    let ordenes = inventario.ordenes();
+    let mut sugerencias: Vec<SugerenciaReorden> = inventario.productos_con_bajo_stock()
+        .into_iter()
+        .filter_map(|producto| {
+            let punto_reorden = inventario.punto_reorden_de(producto.id)?;
+            let pendiente: u32 = ordenes.iter()
+                .filter(|o| o.estado.abierta())
+                .map(|o| o.pendiente_de(producto.id))
+                .sum();
+            let objetivo = punto_reorden.saturating_mul(2).max(punto_reorden.saturating_add(1));
+            let cantidad = objetivo.saturating_sub(producto.stock.saturating_add(pendiente));
+
+            (cantidad > 0).then(|| SugerenciaReorden {
+                producto_id: producto.id,
+                nombre: producto.nombre_completo(),
+                proveedor_id: producto.proveedor_id,
+                stock: producto.stock,
+                punto_reorden,
+                pendiente,
+                cantidad,
+            })
+        })
+        .collect();
+
+    sugerencias.sort_by_key(|s| s.producto_id);
+    sugerencias
+}
+
+/// Crear una orden en borrador por proveedor con las sugerencias, al costo
+/// actual de cada producto. Los productos sin proveedor quedan afuera.
+/// Devuelve los IDs de las órdenes creadas.
+pub fn crear_ordenes_sugeridas(inventario: &mut InventarioManager) -> Result<Vec<u32>, InventarioError> {
+    let mut por_proveedor: BTreeMap<u32, Vec<LineaOrden>> = BTreeMap::new();
+    for sugerencia in sugerir_reorden(inventario) {
+        let Some(proveedor_id) = sugerencia.proveedor_id else {
+            continue;
+        };
+        let costo = inventario.obtener_producto(sugerencia.producto_id).and_then(|p| p.costo);
+        por_proveedor.entry(proveedor_id)
+            .or_default()
+            .push(LineaOrden::new(sugerencia.producto_id, sugerencia.cantidad, costo));
+    }
+
+    let mut creadas = Vec::new();
+    for (proveedor_id, lineas) in por_proveedor {
+        creadas.push(inventario.crear_orden(proveedor_id, lineas, Some("Reposición sugerida".to_string()))?);
+    }
+    Ok(creadas)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Dinero, EstadoOrden, Moneda, MotivoMovimiento};
+
+    #[test]
+    fn recibir_ordenes_repone_stock_y_ajusta_sugerencias() {
+        let mut inventario = InventarioManager::new();
+        let usd = |unidades| Dinero::desde_unidades(unidades, Moneda::POR_DEFECTO);
+        inventario.agregar_ubicacion("Bodega").unwrap();
+        let cafe = inventario.agregar_producto("Café".to_string(), usd(10), 2, Vec::new(), Some(5)).unwrap();
+        let te = inventario.agregar_producto("Té".to_string(), usd(5), 1, Vec::new(), Some(3)).unwrap();
+        inventario.agregar_producto("Azúcar".to_string(), usd(2), 50, Vec::new(), Some(5)).unwrap();
+        let proveedor = inventario.agregar_proveedor("Mayorista", Some("ventas@mayorista.com")).unwrap();
+        inventario.establecer_proveedor(cafe, Some(proveedor)).unwrap();
+        inventario.establecer_costo(cafe, Some(usd(6))).unwrap();
+
+        // El té no tiene proveedor: se sugiere pero no entra en ninguna orden
+        let sugerencias = sugerir_reorden(&inventario);
+        let cantidades: Vec<(u32, u32)> = sugerencias.iter().map(|s| (s.producto_id, s.cantidad)).collect();
+        assert_eq!(cantidades, [(cafe, 8), (te, 5)]);
+        let creadas = crear_ordenes_sugeridas(&mut inventario).unwrap();
+        assert_eq!(creadas.len(), 1);
+        let orden = creadas[0];
+        assert_eq!(inventario.ordenes()[0].lineas, [LineaOrden::new(cafe, 8, Some(usd(6)))]);
+
+        // No se recibe lo que no se envió, ni más de lo pedido
+        assert!(inventario.recibir_orden(orden, &[(cafe, 3)], None).is_err());
+        inventario.enviar_orden(orden).unwrap();
+        assert!(inventario.recibir_orden(orden, &[(cafe, 9)], Some("Bodega")).is_err());
+        assert!(inventario.eliminar_orden(orden).is_err());
+
+        inventario.recibir_orden(orden, &[(cafe, 3)], Some("Bodega")).unwrap();
+        assert_eq!(inventario.ordenes()[0].estado, EstadoOrden::RecibidaParcial);
+        let producto = inventario.obtener_producto(cafe).unwrap();
+        assert_eq!((producto.stock, producto.stock_en(Some("Bodega"))), (5, 3));
+        let compra = inventario.movimientos_de(cafe).last().unwrap();
+        assert_eq!((compra.motivo, compra.costo_unitario), (MotivoMovimiento::Compra, Some(usd(6))));
+
+        // Lo que falta llegar ya no se vuelve a sugerir
+        let ids: Vec<u32> = sugerir_reorden(&inventario).iter().map(|s| s.producto_id).collect();
+        assert_eq!(ids, [te]);
+
+        inventario.recibir_orden(orden, &[(cafe, 5)], None).unwrap();
+        assert_eq!(inventario.ordenes()[0].estado, EstadoOrden::Recibida);
+        assert!(!inventario.tiene_bajo_stock(cafe));
+        assert!(inventario.eliminar_proveedor(proveedor).is_err());
+
+        // La recepción se deshace de una vez: stock, movimiento y orden
+        assert_eq!(inventario.deshacer().unwrap().as_deref(), Some("Recibir orden #1"));
+        assert_eq!(inventario.obtener_producto(cafe).unwrap().stock, 5);
+        assert_eq!(inventario.ordenes()[0].estado, EstadoOrden::RecibidaParcial);
+        assert_eq!(inventario.movimientos_de(cafe).last().unwrap().delta, 3);
+        inventario.rehacer().unwrap();
+        assert_eq!(inventario.ordenes()[0].estado, EstadoOrden::Recibida);
+
+        // Y eliminar un proveedor también
+        let otro = inventario.agregar_proveedor("Distribuidora", None).unwrap();
+        inventario.eliminar_proveedor(otro).unwrap();
+        inventario.deshacer().unwrap();
+        assert_eq!(inventario.proveedor(otro).map(|p| p.nombre.as_str()), Some("Distribuidora"));
+
+        // Los IDs no se reusan aunque se elimine el último
+        inventario.eliminar_proveedor(otro).unwrap();
+        assert_eq!(inventario.agregar_proveedor("Importadora", None).unwrap(), otro + 1);
+        let borrador = inventario.crear_orden(proveedor, vec![LineaOrden::new(te, 1, None)], None).unwrap();
+        inventario.eliminar_orden(borrador).unwrap();
+        let otra = inventario.crear_orden(proveedor, vec![LineaOrden::new(te, 1, None)], None).unwrap();
+        assert_eq!(otra, borrador + 1);
+
+        // Crear, enviar y eliminar órdenes y dar de alta proveedores se deshace
+        inventario.enviar_orden(otra).unwrap();
+        assert_eq!(inventario.deshacer().unwrap().as_deref(), Some(format!("Enviar orden #{}", otra).as_str()));
+        let estado = |inventario: &InventarioManager, id| inventario.ordenes().iter().find(|o| o.id == id).map(|o| o.estado);
+        assert_eq!(estado(&inventario, otra), Some(EstadoOrden::Borrador));
+        inventario.deshacer().unwrap();
+        assert_eq!(estado(&inventario, otra), None);
+        inventario.deshacer().unwrap();
+        assert_eq!(estado(&inventario, borrador), Some(EstadoOrden::Borrador));
+        inventario.deshacer().unwrap();
+        inventario.deshacer().unwrap();
+        assert_eq!(inventario.proveedores().len(), 1);
+        assert_eq!(inventario.descripcion_deshacer(), Some("Eliminar proveedor «Distribuidora»"));
+    }
+}
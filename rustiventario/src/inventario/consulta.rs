@@ -0,0 +1,233 @@
+// Consultas booleanas sobre tags, por ejemplo
+// `(electrónica OR hogar) AND NOT móviles`
+
+use std::collections::HashSet;
+
+use crate::inventario::InventarioError;
+
+/// Consulta de tags ya interpretada
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConsultaTags {
+    /// Productos con este tag exacto
+    Tag(String),
+    /// Productos que cumplen ambas
+    Y(Box<ConsultaTags>, Box<ConsultaTags>),
+    /// Productos que cumplen alguna
+    O(Box<ConsultaTags>, Box<ConsultaTags>),
+    /// Productos que no la cumplen
+    No(Box<ConsultaTags>),
+}
+
+impl ConsultaTags {
+    /// Interpretar una consulta. `AND`, `OR` y `NOT` se pueden escribir en
+    /// minúsculas; los tags con espacios van entre comillas dobles.
+    /// `NOT` tiene más precedencia que `AND`, y `AND` más que `OR`.
+    pub fn parsear(texto: &str) -> Result<Self, InventarioError> {
+        let simbolos = separar(texto)?;
+        let fin = texto.chars().count() + 1;
+        let mut parser = Parser { simbolos, posicion: 0, fin };
+
+        let consulta = parser.o()?;
+        match parser.simbolos.get(parser.posicion) {
+            None => Ok(consulta),
+            Some((columna, simbolo)) => Err(error(*columna, format!("no se esperaba {}", simbolo))),
+        }
+    }
+
+    /// IDs que cumplen la consulta. `ids_de` da los productos de un tag y
+    /// `todos` el conjunto completo, que solo se pide para un `NOT` suelto.
+    pub fn evaluar<'a, F, G>(&self, ids_de: &F, todos: &G) -> HashSet<u32>
+    where
+        F: Fn(&str) -> Option<&'a HashSet<u32>>,
+        G: Fn() -> HashSet<u32>,
+    {
+        match self {
+            ConsultaTags::Tag(tag) => ids_de(tag).cloned().unwrap_or_default(),
+            // `a AND NOT b` se resuelve como diferencia, sin pasar por `todos`
+            ConsultaTags::Y(a, b) => match (a.as_ref(), b.as_ref()) {
+                (_, ConsultaTags::No(b)) => diferencia(a.evaluar(ids_de, todos), &b.evaluar(ids_de, todos)),
+                (ConsultaTags::No(a), _) => diferencia(b.evaluar(ids_de, todos), &a.evaluar(ids_de, todos)),
+                _ => {
+                    let a = a.evaluar(ids_de, todos);
+                    let b = b.evaluar(ids_de, todos);
+                    a.intersection(&b).copied().collect()
+                }
+            },
+            ConsultaTags::O(a, b) => {
+                let mut ids = a.evaluar(ids_de, todos);
+                ids.extend(b.evaluar(ids_de, todos));
+                ids
+            }
+            ConsultaTags::No(a) => diferencia(todos(), &a.evaluar(ids_de, todos)),
+        }
+    }
+}
+
+fn diferencia(mut ids: HashSet<u32>, quitar: &HashSet<u32>) -> HashSet<u32> {
+    ids.retain(|id| !quitar.contains(id));
+    ids
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Simbolo {
+    Abre,
+    Cierra,
+    Y,
+    O,
+    No,
+    Tag(String),
+}
+
+impl std::fmt::Display for Simbolo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Simbolo::Abre => write!(f, "\"(\""),
+            Simbolo::Cierra => write!(f, "\")\""),
+            Simbolo::Y => write!(f, "AND"),
+            Simbolo::O => write!(f, "OR"),
+            Simbolo::No => write!(f, "NOT"),
+            Simbolo::Tag(tag) => write!(f, "el tag \"{}\"", tag),
+        }
+    }
+}
+
+// Separar la consulta en símbolos, cada uno con su columna (desde 1)
+fn separar(texto: &str) -> Result<Vec<(usize, Simbolo)>, InventarioError> {
+    let mut simbolos = Vec::new();
+    let mut caracteres = texto.chars().enumerate().peekable();
+
+    while let Some((i, c)) = caracteres.next() {
+        let columna = i + 1;
+        match c {
+            c if c.is_whitespace() => {}
+            '(' => simbolos.push((columna, Simbolo::Abre)),
+            ')' => simbolos.push((columna, Simbolo::Cierra)),
+            '"' => {
+                let mut tag = String::new();
+                loop {
+                    match caracteres.next() {
+                        Some((_, '"')) => break,
+                        Some((_, c)) => tag.push(c),
+                        None => return Err(error(columna, "comillas sin cerrar".to_string())),
+                    }
+                }
+                simbolos.push((columna, Simbolo::Tag(tag)));
+            }
+            _ => {
+                let mut palabra = c.to_string();
+                while let Some((_, c)) = caracteres.next_if(|(_, c)| !c.is_whitespace() && !"()\"".contains(*c)) {
+                    palabra.push(c);
+                }
+                let simbolo = match palabra.to_uppercase().as_str() {
+                    "AND" => Simbolo::Y,
+                    "OR" => Simbolo::O,
+                    "NOT" => Simbolo::No,
+                    _ => Simbolo::Tag(palabra),
+                };
+                simbolos.push((columna, simbolo));
+            }
+        }
+    }
+
+    Ok(simbolos)
+}
+
+// Parser descendente: o := y (OR y)*, y := no (AND no)*, no := NOT no | átomo
+struct Parser {
+    simbolos: Vec<(usize, Simbolo)>,
+    posicion: usize,
+    // Columna que sigue al último carácter, para errores al final
+    fin: usize,
+}
+
+impl Parser {
+    fn o(&mut self) -> Result<ConsultaTags, InventarioError> {
+        let mut consulta = self.y()?;
+        while self.consumir(&Simbolo::O) {
+            consulta = ConsultaTags::O(Box::new(consulta), Box::new(self.y()?));
+        }
+        Ok(consulta)
+    }
+
+    fn y(&mut self) -> Result<ConsultaTags, InventarioError> {
+        let mut consulta = self.no()?;
+        while self.consumir(&Simbolo::Y) {
+            consulta = ConsultaTags::Y(Box::new(consulta), Box::new(self.no()?));
+        }
+        Ok(consulta)
+    }
+
+    fn no(&mut self) -> Result<ConsultaTags, InventarioError> {
+        if self.consumir(&Simbolo::No) {
+            return Ok(ConsultaTags::No(Box::new(self.no()?)));
+        }
+        self.atomo()
+    }
+
+    fn atomo(&mut self) -> Result<ConsultaTags, InventarioError> {
+        let Some((columna, simbolo)) = self.simbolos.get(self.posicion).cloned() else {
+            return Err(error(self.fin, "falta un tag al final".to_string()));
+        };
+        self.posicion += 1;
+
+        match simbolo {
+            Simbolo::Tag(tag) => Ok(ConsultaTags::Tag(tag)),
+            Simbolo::Abre => {
+                let consulta = self.o()?;
+                if !self.consumir(&Simbolo::Cierra) {
+                    return Err(error(columna, "paréntesis sin cerrar".to_string()));
+                }
+                Ok(consulta)
+            }
+            otro => Err(error(columna, format!("se esperaba un tag y hay {}", otro))),
+        }
+    }
+
+    fn consumir(&mut self, esperado: &Simbolo) -> bool {
+        let coincide = self.simbolos.get(self.posicion).is_some_and(|(_, s)| s == esperado);
+        if coincide {
+            self.posicion += 1;
+        }
+        coincide
+    }
+}
+
+fn error(columna: usize, mensaje: String) -> InventarioError {
+    InventarioError::Validacion(format!("consulta de tags, columna {}: {}", columna, mensaje))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tag(tag: &str) -> Box<ConsultaTags> {
+        Box::new(ConsultaTags::Tag(tag.to_string()))
+    }
+
+    #[test]
+    fn respeta_precedencia_y_parentesis() {
+        assert_eq!(
+            ConsultaTags::parsear("periféricos AND NOT móviles").unwrap(),
+            ConsultaTags::Y(tag("periféricos"), Box::new(ConsultaTags::No(tag("móviles"))))
+        );
+        assert_eq!(
+            ConsultaTags::parsear("(electrónica or hogar) and oferta").unwrap(),
+            ConsultaTags::Y(Box::new(ConsultaTags::O(tag("electrónica"), tag("hogar"))), tag("oferta"))
+        );
+        assert_eq!(
+            ConsultaTags::parsear("a OR b AND \"línea blanca\"").unwrap(),
+            ConsultaTags::O(tag("a"), Box::new(ConsultaTags::Y(tag("b"), tag("línea blanca"))))
+        );
+    }
+
+    #[test]
+    fn informa_la_columna_de_los_errores() {
+        let mensaje = |texto: &str| ConsultaTags::parsear(texto).unwrap_err().to_string();
+
+        assert!(mensaje("(a OR b").contains("columna 1: paréntesis sin cerrar"));
+        assert!(mensaje("a AND").contains("columna 6: falta un tag al final"));
+        assert!(mensaje("a b").contains("columna 3: no se esperaba el tag \"b\""));
+        assert!(mensaje("OR a").contains("columna 1: se esperaba un tag y hay OR"));
+        assert!(mensaje("").contains("falta un tag"));
+    }
+}
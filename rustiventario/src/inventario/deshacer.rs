@@ -0,0 +1,108 @@
+use crate::models::{ConfiguracionStock, Movimiento, OrdenCompra, Producto, Proveedor};
+
+/// Cantidad máxima de cambios que se pueden deshacer
+const LIMITE: usize = 100;
+
+/// Estado completo de un producto en un momento dado
+#[derive(Debug, Clone)]
+pub(crate) struct EstadoProducto {
+    pub producto: Producto,
+    pub movimientos: Vec<Movimiento>,
+}
+
+/// Un producto antes y después de un cambio.
+/// `None` significa que el producto no existía.
+#[derive(Debug, Clone)]
+pub(crate) struct CambioProducto {
+    pub id: u32,
+    pub antes: Option<EstadoProducto>,
+    pub despues: Option<EstadoProducto>,
+}
+
+/// Un proveedor o una orden de compra antes y después de un cambio.
+/// `None` significa que no existía.
+#[derive(Debug, Clone)]
+pub(crate) enum CambioCompras {
+    Proveedor { id: u32, antes: Option<Proveedor>, despues: Option<Proveedor> },
+    Orden { id: u32, antes: Option<OrdenCompra>, despues: Option<OrdenCompra> },
+}
+
+impl CambioCompras {
+    /// El cambio contrario, para deshacerlo
+    pub fn invertido(self) -> Self {
+        match self {
+            CambioCompras::Proveedor { id, antes, despues } => CambioCompras::Proveedor { id, antes: despues, despues: antes },
+            CambioCompras::Orden { id, antes, despues } => CambioCompras::Orden { id, antes: despues, despues: antes },
+        }
+    }
+}
+
+/// Los puntos de reorden antes y después de un cambio
+#[derive(Debug, Clone)]
+pub(crate) struct CambioConfiguracion {
+    pub antes: ConfiguracionStock,
+    pub despues: ConfiguracionStock,
+}
+
+/// Un cambio que se deshace de una vez: uno o varios productos (una
+/// venta, una importación) y, si lo tocó, un proveedor o una orden,
+/// o bien los puntos de reorden
+#[derive(Debug, Clone)]
+pub(crate) struct Comando {
+    pub descripcion: String,
+    pub productos: Vec<CambioProducto>,
+    pub compras: Option<CambioCompras>,
+    pub configuracion: Option<CambioConfiguracion>,
+}
+
+/// Pilas de cambios para deshacer y rehacer
+#[derive(Default)]
+pub(crate) struct Historial {
+    deshacer: Vec<Comando>,
+    rehacer: Vec<Comando>,
+}
+
+impl Historial {
+    /// Guardar un cambio nuevo; lo que se podía rehacer se descarta
+    pub fn registrar(&mut self, comando: Comando) {
+        self.rehacer.clear();
+        self.deshacer.push(comando);
+
+        if self.deshacer.len() > LIMITE {
+            self.deshacer.remove(0);
+        }
+    }
+
+    pub fn limpiar(&mut self) {
+        self.deshacer.clear();
+        self.rehacer.clear();
+    }
+
+    /// Quitar el último cambio sin pasarlo a rehacer, para reemplazarlo
+    /// por uno que lo incluya
+    pub fn descartar_ultimo(&mut self) -> Option<Comando> {
+        self.deshacer.pop()
+    }
+
+    pub fn siguiente_deshacer(&self) -> Option<&Comando> {
+        self.deshacer.last()
+    }
+
+    pub fn siguiente_rehacer(&self) -> Option<&Comando> {
+        self.rehacer.last()
+    }
+
+    /// Pasar el último cambio a la pila de rehacer
+    pub fn marcar_deshecho(&mut self) {
+        if let Some(comando) = self.deshacer.pop() {
+            self.rehacer.push(comando);
+        }
+    }
+
+    /// Pasar el último cambio deshecho de vuelta a la pila de deshacer
+    pub fn marcar_rehecho(&mut self) {
+        if let Some(comando) = self.rehacer.pop() {
+            self.deshacer.push(comando);
+        }
+    }
+}
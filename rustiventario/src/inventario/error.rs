@@ -0,0 +1,50 @@
+use std::fmt;
+use std::io;
+
+/// Errores que pueden producir el gestor de inventario y la persistencia
+#[derive(Debug)]
+pub enum InventarioError {
+    /// No existe un producto con ese ID
+    NoEncontrado(u32),
+    /// Ya existe un producto con ese ID
+    Duplicado(u32),
+    /// Un archivo no se pudo interpretar; línea y columna empiezan en 1
+    Parseo {
+        linea: usize,
+        columna: usize,
+        mensaje: String,
+    },
+    /// Error de entrada/salida al leer o escribir un archivo
+    Io(io::Error),
+    /// Los datos de un producto no son válidos
+    Validacion(String),
+}
+
+impl fmt::Display for InventarioError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InventarioError::NoEncontrado(id) => write!(f, "Producto con ID {} no encontrado", id),
+            InventarioError::Duplicado(id) => write!(f, "Producto con ID {} duplicado", id),
+            InventarioError::Parseo { linea, columna, mensaje } => {
+                write!(f, "Error en línea {}, columna {}: {}", linea, columna, mensaje)
+            }
+            InventarioError::Io(e) => write!(f, "Error de archivo: {}", e),
+            InventarioError::Validacion(mensaje) => write!(f, "Datos inválidos: {}", mensaje),
+        }
+    }
+}
+
+impl std::error::Error for InventarioError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            InventarioError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for InventarioError {
+    fn from(e: io::Error) -> Self {
+        InventarioError::Io(e)
+    }
+}
@@ -0,0 +1,129 @@
+use std::collections::BTreeMap;
+use std::sync::mpsc::{channel, Receiver, Sender};
+
+/// Cambio en el inventario, avisado a quien se haya suscrito con
+/// `InventarioManager::suscribir`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EventoInventario {
+    ProductoAgregado { id: u32 },
+    /// Cambió el stock total (no se avisa de las transferencias)
+    StockCambiado { id: u32, anterior: u32, nuevo: u32 },
+    ProductoEliminado { id: u32 },
+    /// El producto llegó a su punto de reorden o quedó por debajo
+    BajoStock { id: u32, stock: u32 },
+    /// El producto volvió a quedar por encima de su punto de reorden
+    Repuesto { id: u32, stock: u32 },
+    /// Cambió el nombre, también cuando lo toma del padre al ser variante
+    Renombrado { id: u32 },
+}
+
+/// Stock, bajo stock y nombre de algunos productos en un momento dado;
+/// `None` significa que el producto no existía
+pub(crate) type Foto = BTreeMap<u32, Option<(u32, bool, String)>>;
+
+/// Canales de los suscriptores a los eventos del inventario
+#[derive(Default)]
+pub(crate) struct Observadores {
+    suscriptores: Vec<Sender<EventoInventario>>,
+}
+
+impl Observadores {
+    /// Nuevo suscriptor; deja de recibir eventos al soltar el `Receiver`
+    pub fn suscribir(&mut self) -> Receiver<EventoInventario> {
+        let (emisor, receptor) = channel();
+        self.suscriptores.push(emisor);
+        receptor
+    }
+
+    /// Si hay alguien escuchando (si no, no hace falta comparar nada)
+    pub fn activos(&self) -> bool {
+        !self.suscriptores.is_empty()
+    }
+
+    /// Enviar los eventos a todos, olvidando a los que ya no escuchan
+    pub fn emitir(&mut self, eventos: Vec<EventoInventario>) {
+        for evento in eventos {
+            self.suscriptores.retain(|s| s.send(evento.clone()).is_ok());
+        }
+    }
+}
+
+/// Eventos que explican el paso de `antes` a `despues`, producto por producto
+pub(crate) fn diferencias(antes: &Foto, despues: &Foto) -> Vec<EventoInventario> {
+    let mut eventos = Vec::new();
+
+    for (&id, anterior) in antes {
+        let actual = despues.get(&id).and_then(Option::as_ref);
+        match (anterior, actual) {
+            (None, None) => {}
+            (Some(_), None) => eventos.push(EventoInventario::ProductoEliminado { id }),
+            (None, Some(&(stock, bajo, _))) => {
+                eventos.push(EventoInventario::ProductoAgregado { id });
+                if bajo {
+                    eventos.push(EventoInventario::BajoStock { id, stock });
+                }
+            }
+            (Some((stock_anterior, bajo_anterior, nombre_anterior)), Some((stock, bajo, nombre))) => {
+                let (stock_anterior, bajo_anterior, stock, bajo) = (*stock_anterior, *bajo_anterior, *stock, *bajo);
+                if nombre != nombre_anterior {
+                    eventos.push(EventoInventario::Renombrado { id });
+                }
+                if stock != stock_anterior {
+                    eventos.push(EventoInventario::StockCambiado { id, anterior: stock_anterior, nuevo: stock });
+                }
+                match (bajo_anterior, bajo) {
+                    (false, true) => eventos.push(EventoInventario::BajoStock { id, stock }),
+                    (true, false) => eventos.push(EventoInventario::Repuesto { id, stock }),
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    eventos
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::inventario::InventarioManager;
+    use crate::models::{Dinero, Moneda, MotivoMovimiento};
+
+    #[test]
+    fn los_cambios_de_stock_se_avisan_a_los_suscriptores() {
+        let mut inventario = InventarioManager::new();
+        let eventos = inventario.suscribir();
+        let precio = Dinero::desde_unidades(2, Moneda::POR_DEFECTO);
+
+        let id = inventario.agregar_producto("Pila".to_string(), precio, 8, Vec::new(), Some(5)).unwrap();
+        inventario.registrar_movimiento(id, -4, MotivoMovimiento::Venta, None).unwrap();
+        inventario.registrar_movimiento(id, 6, MotivoMovimiento::Compra, None).unwrap();
+        inventario.deshacer().unwrap();
+        inventario.eliminar_producto(id).unwrap();
+
+        assert_eq!(eventos.try_iter().collect::<Vec<_>>(), [
+            EventoInventario::ProductoAgregado { id },
+            EventoInventario::StockCambiado { id, anterior: 8, nuevo: 4 },
+            EventoInventario::BajoStock { id, stock: 4 },
+            EventoInventario::StockCambiado { id, anterior: 4, nuevo: 10 },
+            EventoInventario::Repuesto { id, stock: 10 },
+            EventoInventario::StockCambiado { id, anterior: 10, nuevo: 4 },
+            EventoInventario::BajoStock { id, stock: 4 },
+            EventoInventario::ProductoEliminado { id },
+        ]);
+
+        // Un suscriptor que ya no escucha no impide seguir avisando a los demás
+        drop(eventos);
+        let otros = inventario.suscribir();
+        inventario.establecer_punto_reorden_global(100).unwrap();
+        let id = inventario.agregar_producto("Cable".to_string(), precio, 3, Vec::new(), None).unwrap();
+        assert_eq!(otros.try_iter().collect::<Vec<_>>(), [
+            EventoInventario::ProductoAgregado { id },
+            EventoInventario::BajoStock { id, stock: 3 },
+        ]);
+
+        // Un cambio de nombre cambia lo que se encuentra al buscar
+        inventario.actualizar_producto(id, "Cable USB".to_string(), precio, 3, Vec::new(), None).unwrap();
+        assert_eq!(otros.try_iter().collect::<Vec<_>>(), [EventoInventario::Renombrado { id }]);
+    }
+}
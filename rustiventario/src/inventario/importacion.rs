@@ -0,0 +1,429 @@
+// Importación que combina un archivo con el inventario actual en vez de
+// reemplazarlo: primero se arma un plan para revisar y después se aplica
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use crate::inventario::{InventarioError, InventarioManager};
+use crate::models::codigo::{clave_codigo_barras, clave_sku};
+use crate::models::Producto;
+use crate::utils::file_io::{cargar_datos, ModoCarga, ReporteCarga};
+
+/// Cómo se reconoce en el archivo un producto que ya está en el inventario
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ClaveImportacion {
+    #[default]
+    Id,
+    /// Los productos sin SKU no se pueden combinar
+    Sku,
+}
+
+impl ClaveImportacion {
+    pub const TODAS: [ClaveImportacion; 2] = [ClaveImportacion::Id, ClaveImportacion::Sku];
+}
+
+impl fmt::Display for ClaveImportacion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let texto = match self {
+            ClaveImportacion::Id => "ID",
+            ClaveImportacion::Sku => "SKU",
+        };
+        write!(f, "{}", texto)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct OpcionesImportacion {
+    pub clave: ClaveImportacion,
+    /// Eliminar los productos del inventario que no están en el archivo
+    pub eliminar_faltantes: bool,
+}
+
+/// Un producto existente y cómo queda después de importar
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Actualizacion {
+    pub anterior: Producto,
+    pub nuevo: Producto,
+}
+
+/// Un producto del archivo que no se puede importar
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Conflicto {
+    pub producto: Producto,
+    pub motivo: String,
+}
+
+/// Lo que haría una importación, para revisarlo antes de aplicarlo con
+/// `InventarioManager::importar`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PlanImportacion {
+    /// Productos nuevos; reciben un ID nuevo al aplicar el plan
+    pub agregar: Vec<Producto>,
+    pub actualizar: Vec<Actualizacion>,
+    pub eliminar: Vec<Producto>,
+    pub conflictos: Vec<Conflicto>,
+    /// Productos del archivo iguales a los del inventario
+    pub sin_cambios: usize,
+    /// Líneas del archivo rechazadas o corregidas al leerlo
+    pub reporte: ReporteCarga,
+}
+
+impl PlanImportacion {
+    /// Si aplicar el plan no cambiaría nada
+    pub fn esta_vacio(&self) -> bool {
+        self.agregar.is_empty() && self.actualizar.is_empty() && self.eliminar.is_empty()
+    }
+}
+
+/// Leer un archivo (CSV o JSON) y armar el plan para combinarlo con el
+/// inventario, sin cambiar nada todavía
+pub fn planificar(
+    inventario: &InventarioManager,
+    ruta: &str,
+    modo: ModoCarga,
+    opciones: OpcionesImportacion,
+) -> Result<PlanImportacion, InventarioError> {
+    let (datos, reporte) = cargar_datos(ruta, modo)?;
+    let mut plan = planificar_productos(inventario, datos.productos, opciones);
+    plan.reporte = reporte;
+    Ok(plan)
+}
+
+/// Armar el plan para combinar `productos` con el inventario.
+///
+/// De un producto existente se toman del archivo el nombre, el precio, el
+/// stock, los tags, el punto de reorden y los códigos, y el costo si lo
+/// trae; las existencias por ubicación, el proveedor y la variante se
+/// conservan. Los productos nuevos entran con todo su stock sin ubicación,
+/// sin proveedor y sin ser variantes.
+///
+/// Las variantes siguen a su padre como en `InventarioManager::actualizar_producto`:
+/// no pueden cambiar de nombre ni de tags por su cuenta, y si el padre
+/// cambia también se actualizan. Un padre no se elimina mientras le
+/// queden variantes.
+pub fn planificar_productos(
+    inventario: &InventarioManager,
+    productos: Vec<Producto>,
+    opciones: OpcionesImportacion,
+) -> PlanImportacion {
+    let clave_de = |producto: &Producto| match opciones.clave {
+        ClaveImportacion::Id => Some(producto.id.to_string()),
+        ClaveImportacion::Sku => producto.sku.as_deref().map(clave_sku),
+    };
+    let existentes: HashMap<String, &Producto> = inventario.obtener_todos_productos()
+        .into_iter()
+        .filter_map(|p| clave_de(p).map(|clave| (clave, p)))
+        .collect();
+
+    let mut plan = PlanImportacion::default();
+    let mut vistas = HashSet::new();
+    // Pares (producto actual, cómo queda), antes de revisar los códigos
+    let mut candidatos = Vec::new();
+
+    for producto in productos {
+        let Some(clave) = clave_de(&producto) else {
+            plan.conflictos.push(Conflicto { producto, motivo: "no tiene SKU".to_string() });
+            continue;
+        };
+        if !vistas.insert(clave.clone()) {
+            let motivo = format!("otro producto del archivo tiene el mismo {}", opciones.clave);
+            plan.conflictos.push(Conflicto { producto, motivo });
+            continue;
+        }
+
+        match existentes.get(&clave) {
+            Some(&actual) => {
+                let nuevo = combinar(actual, producto);
+                let repartido: u32 = nuevo.existencias.values().sum();
+                if nuevo == *actual {
+                    plan.sin_cambios += 1;
+                } else if nuevo.stock < repartido {
+                    let motivo = format!(
+                        "el stock {} no alcanza para las {} unidades repartidas en ubicaciones", nuevo.stock, repartido
+                    );
+                    plan.conflictos.push(Conflicto { producto: nuevo, motivo });
+                } else {
+                    candidatos.push((Some(actual), nuevo));
+                }
+            }
+            None => {
+                let nuevo = Producto {
+                    id: 0, existencias: Default::default(), proveedor_id: None, variante: None, ..producto
+                };
+                candidatos.push((None, nuevo));
+            }
+        }
+    }
+
+    if opciones.eliminar_faltantes {
+        plan.eliminar = existentes.iter()
+            .filter(|(clave, _)| !vistas.contains(*clave))
+            .map(|(_, producto)| (*producto).clone())
+            .collect();
+        plan.eliminar.sort_by_key(|p| p.id);
+        
+        // Un padre solo se va con todas sus variantes
+        let faltantes: HashSet<u32> = plan.eliminar.iter().map(|p| p.id).collect();
+        let (eliminar, con_variantes): (Vec<Producto>, Vec<Producto>) = std::mem::take(&mut plan.eliminar)
+            .into_iter()
+            .partition(|p| inventario.variantes_de(p.id).iter().all(|v| faltantes.contains(&v.id)));
+        plan.eliminar = eliminar;
+        for producto in con_variantes {
+            plan.conflictos.push(Conflicto { producto, motivo: "tiene variantes que no se eliminan".to_string() });
+        }
+    }
+
+    // Los códigos tienen que seguir siendo únicos. Los productos que se
+    // actualizan conservan sus códigos anteriores hasta el final, así que
+    // un código no puede pasar de un producto a otro en la misma importación.
+    let eliminados: HashSet<u32> = plan.eliminar.iter().map(|p| p.id).collect();
+    let mut codigos: HashMap<String, Option<u32>> = HashMap::new();
+    for producto in inventario.obtener_todos_productos() {
+        if !eliminados.contains(&producto.id) {
+            for (codigo, _) in codigos_de(producto) {
+                codigos.insert(codigo, Some(producto.id));
+            }
+        }
+    }
+
+    for (actual, nuevo) in candidatos {
+        let dueno = actual.map(|p| p.id);
+        let choque = codigos_de(&nuevo).into_iter().find_map(|(codigo, descripcion)| {
+            codigos.get(&codigo)
+                .filter(|otro| **otro != dueno || dueno.is_none())
+                .map(|otro| match otro {
+                    Some(id) => format!("{} ya lo usa el producto ID {}", descripcion, id),
+                    None => format!("{} ya lo usa otro producto del archivo", descripcion),
+                })
+        });
+        if let Some(motivo) = choque {
+            plan.conflictos.push(Conflicto { producto: nuevo, motivo });
+            continue;
+        }
+
+        for (codigo, _) in codigos_de(&nuevo) {
+            codigos.insert(codigo, dueno);
+        }
+        match actual {
+            Some(anterior) => plan.actualizar.push(Actualizacion { anterior: anterior.clone(), nuevo }),
+            None => plan.agregar.push(nuevo),
+        }
+    }
+
+    seguir_a_los_padres(inventario, &mut plan);
+    plan
+}
+
+/// Ajustar las variantes del plan a cómo queda su padre y sumar las que
+/// no venían en el archivo pero cambian porque cambió el padre
+fn seguir_a_los_padres(inventario: &InventarioManager, plan: &mut PlanImportacion) {
+    let padres: HashMap<u32, Producto> = plan.actualizar.iter()
+        .filter(|a| a.nuevo.variante.is_none())
+        .map(|a| (a.nuevo.id, a.nuevo.clone()))
+        .collect();
+    let padre_de = |id: u32| padres.get(&id).or_else(|| inventario.obtener_producto(id));
+
+    for Actualizacion { anterior, mut nuevo } in std::mem::take(&mut plan.actualizar) {
+        let Some(padre) = nuevo.variante.as_ref().and_then(|v| padre_de(v.padre_id)) else {
+            plan.actualizar.push(Actualizacion { anterior, nuevo });
+            continue;
+        };
+
+        // El archivo puede traer el nombre y los tags de antes o los nuevos del padre
+        let cambia_nombre = (&nuevo.nombre, &nuevo.tags) != (&anterior.nombre, &anterior.tags)
+            && (&nuevo.nombre, &nuevo.tags) != (&padre.nombre, &padre.tags);
+        if cambia_nombre {
+            let motivo = format!("una variante lleva el nombre y los tags de «{}»", padre.nombre);
+            plan.conflictos.push(Conflicto { producto: nuevo, motivo });
+            continue;
+        }
+        if nuevo.precio.moneda != padre.precio.moneda {
+            let motivo = format!("el precio de la variante tiene que estar en {}", padre.precio.moneda);
+            plan.conflictos.push(Conflicto { producto: nuevo, motivo });
+            continue;
+        }
+
+        nuevo.nombre = padre.nombre.clone();
+        nuevo.tags = padre.tags.clone();
+        // Sin precio propio y con el mismo precio de antes, sigue al padre
+        let seguia = anterior.variante.as_ref().is_some_and(|v| !v.precio_propio);
+        if seguia && nuevo.precio == anterior.precio {
+            nuevo.precio = padre.precio;
+        }
+        let precio_propio = nuevo.precio != padre.precio;
+        if let Some(variante) = nuevo.variante.as_mut() {
+            variante.precio_propio = precio_propio;
+        }
+
+        if nuevo == anterior {
+            plan.sin_cambios += 1;
+        } else {
+            plan.actualizar.push(Actualizacion { anterior, nuevo });
+        }
+    }
+
+    // Las variantes que no venían (o no se pudieron importar) siguen al padre
+    let ajustadas: HashSet<u32> = plan.actualizar.iter().map(|a| a.anterior.id)
+        .chain(plan.eliminar.iter().map(|p| p.id))
+        .collect();
+    let mut ids: Vec<&u32> = padres.keys().collect();
+    ids.sort();
+    for padre in ids.into_iter().map(|id| &padres[id]) {
+        for variante in inventario.variantes_de(padre.id) {
+            if ajustadas.contains(&variante.id) {
+                continue;
+            }
+            let precio_propio = variante.variante.as_ref().is_some_and(|v| v.precio_propio);
+            let precio = if precio_propio { variante.precio } else { padre.precio };
+            let nuevo = Producto {
+                nombre: padre.nombre.clone(),
+                tags: padre.tags.clone(),
+                precio,
+                costo: variante.costo.filter(|costo| costo.moneda == precio.moneda),
+                ..variante.clone()
+            };
+            if nuevo != *variante {
+                plan.actualizar.push(Actualizacion { anterior: variante.clone(), nuevo });
+            }
+        }
+    }
+}
+
+/// Un producto existente con los datos que trae el archivo
+fn combinar(actual: &Producto, del_archivo: Producto) -> Producto {
+    let precio = del_archivo.precio;
+    let costo = del_archivo.costo.or(actual.costo).filter(|c| c.moneda == precio.moneda);
+    Producto {
+        id: actual.id,
+        existencias: actual.existencias.clone(),
+        proveedor_id: actual.proveedor_id,
+        variante: actual.variante.clone(),
+        costo,
+        ..del_archivo
+    }
+}
+
+/// Claves normalizadas de los códigos de un producto, con una descripción
+/// para los mensajes
+fn codigos_de(producto: &Producto) -> Vec<(String, String)> {
+    let sku = producto.sku.iter()
+        .map(|sku| (format!("sku:{}", clave_sku(sku)), format!("el SKU \"{}\"", sku)));
+    let codigo_barras = producto.codigo_barras.iter()
+        .map(|codigo| (format!("barras:{}", clave_codigo_barras(codigo)), format!("el código de barras \"{}\"", codigo)));
+    sku.chain(codigo_barras).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Dinero, Moneda};
+
+    #[test]
+    fn combina_por_sku_y_avisa_los_conflictos() {
+        let mut inventario = InventarioManager::new();
+        let usd = |unidades| Dinero::desde_unidades(unidades, Moneda::POR_DEFECTO);
+        let lapiz = inventario.agregar_producto("Lápiz".to_string(), usd(1), 10, Vec::new(), None).unwrap();
+        let goma = inventario.agregar_producto("Goma".to_string(), usd(2), 5, Vec::new(), None).unwrap();
+        let regla = inventario.agregar_producto("Regla".to_string(), usd(3), 1, Vec::new(), None).unwrap();
+        inventario.establecer_codigos(lapiz, Some("LAP-1"), None).unwrap();
+        inventario.establecer_codigos(goma, Some("GOM-1"), None).unwrap();
+        inventario.establecer_codigos(regla, Some("REG-1"), None).unwrap();
+
+        // Los IDs del proveedor no tienen nada que ver con los nuestros
+        let producto = |id, nombre: &str, precio, stock, sku: Option<&str>| Producto {
+            id, nombre: nombre.to_string(), precio: usd(precio), stock, sku: sku.map(String::from), ..Default::default()
+        };
+        let archivo = vec![
+            producto(70, "Lápiz HB", 1, 12, Some("lap-1")),
+            producto(71, "Goma", 2, 5, Some("GOM-1")),
+            producto(72, "Sacapuntas", 4, 3, Some("SAC-1")),
+            producto(73, "Cuaderno", 5, 2, None),
+            producto(74, "Sacapuntas doble", 6, 1, Some("SAC-1")),
+        ];
+        let opciones = OpcionesImportacion { clave: ClaveImportacion::Sku, eliminar_faltantes: true };
+        let plan = planificar_productos(&inventario, archivo, opciones);
+
+        assert_eq!(plan.sin_cambios, 1);
+        assert_eq!(plan.actualizar.len(), 1);
+        assert_eq!(plan.actualizar[0].nuevo.nombre, "Lápiz HB");
+        assert_eq!(plan.actualizar[0].nuevo.id, lapiz);
+        assert_eq!(plan.agregar.iter().map(|p| p.nombre.as_str()).collect::<Vec<_>>(), ["Sacapuntas"]);
+        assert_eq!(plan.eliminar.iter().map(|p| p.id).collect::<Vec<_>>(), [regla]);
+        assert_eq!(plan.conflictos.iter().map(|c| c.motivo.as_str()).collect::<Vec<_>>(), [
+            "no tiene SKU",
+            "otro producto del archivo tiene el mismo SKU",
+        ]);
+
+        let nuevos = inventario.importar(&plan).unwrap();
+        assert_eq!(inventario.obtener_todos_productos().len(), 3);
+        assert_eq!(inventario.obtener_producto(lapiz).unwrap().stock, 12);
+        assert_eq!(inventario.buscar_por_codigo("SAC-1").unwrap().id, nuevos[0]);
+        assert!(inventario.obtener_producto(regla).is_none());
+
+        // El plan ya no coincide con el inventario
+        assert!(inventario.importar(&plan).is_err());
+
+        // La importación se deshace de una vez, con los índices
+        assert_eq!(inventario.deshacer().unwrap().as_deref(), Some("Importar"));
+        assert_eq!(inventario.obtener_producto(lapiz).unwrap().stock, 10);
+        assert_eq!(inventario.buscar_por_codigo("REG-1").unwrap().id, regla);
+        assert!(inventario.buscar_por_codigo("SAC-1").is_none());
+        assert_eq!(inventario.descripcion_deshacer(), Some("Cambiar códigos de «Regla»"));
+    }
+
+    #[test]
+    fn las_variantes_siguen_al_padre() {
+        let mut inventario = InventarioManager::new();
+        let usd = |unidades| Dinero::desde_unidades(unidades, Moneda::POR_DEFECTO);
+        let talla = |valor: &str| [("talla".to_string(), valor.to_string())].into();
+        let camisa = inventario.agregar_producto("Camisa".to_string(), usd(20), 0, vec!["ropa".to_string()], None).unwrap();
+        let m = inventario.agregar_variante(camisa, talla("M"), 4, None).unwrap();
+        let l = inventario.agregar_variante(camisa, talla("L"), 2, Some(usd(25))).unwrap();
+        let copia = |id| inventario.obtener_producto(id).unwrap().clone();
+        let por_id = OpcionesImportacion { clave: ClaveImportacion::Id, eliminar_faltantes: false };
+
+        // El padre cambia de nombre y precio; la M viene con los datos de
+        // antes y la L no viene: las dos toman el nombre nuevo
+        let archivo = vec![
+            Producto { nombre: "Camisa lino".to_string(), precio: usd(22), ..copia(camisa) },
+            Producto { stock: 6, ..copia(m) },
+        ];
+        let plan = planificar_productos(&inventario, archivo, por_id);
+        assert!(plan.conflictos.is_empty());
+        let nuevos: Vec<(u32, &str, Dinero)> = plan.actualizar.iter()
+            .map(|a| (a.nuevo.id, a.nuevo.nombre.as_str(), a.nuevo.precio))
+            .collect();
+        assert_eq!(nuevos, [(camisa, "Camisa lino", usd(22)), (m, "Camisa lino", usd(22)), (l, "Camisa lino", usd(25))]);
+
+        // Una variante no cambia de nombre ni de tags por su cuenta
+        let archivo = vec![
+            Producto { nombre: "Camiseta".to_string(), ..copia(m) },
+            Producto { tags: ["oferta".to_string()].into(), precio: usd(30), ..copia(l) },
+        ];
+        let plan = planificar_productos(&inventario, archivo, por_id);
+        assert!(plan.esta_vacio());
+        assert_eq!(plan.conflictos.iter().map(|c| c.motivo.as_str()).collect::<Vec<_>>(), [
+            "una variante lleva el nombre y los tags de «Camisa»",
+            "una variante lleva el nombre y los tags de «Camisa»",
+        ]);
+
+        // Un precio distinto al del padre pasa a ser propio
+        let plan = planificar_productos(&inventario, vec![Producto { precio: usd(18), ..copia(m) }], por_id);
+        assert_eq!(plan.actualizar[0].nuevo.variante.as_ref().map(|v| v.precio_propio), Some(true));
+
+        // El padre no se elimina si quedan sus variantes; con ellas sí
+        let faltantes = OpcionesImportacion { eliminar_faltantes: true, ..por_id };
+        let plan = planificar_productos(&inventario, vec![copia(m), copia(l)], faltantes);
+        assert!(plan.eliminar.is_empty());
+        assert_eq!(plan.conflictos.iter().map(|c| (c.producto.id, c.motivo.as_str())).collect::<Vec<_>>(), [
+            (camisa, "tiene variantes que no se eliminan"),
+        ]);
+        let plan = planificar_productos(&inventario, vec![copia(l)], faltantes);
+        assert_eq!(plan.eliminar.iter().map(|p| p.id).collect::<Vec<_>>(), [m]);
+        assert_eq!(plan.conflictos.len(), 1);
+        let plan = planificar_productos(&inventario, Vec::new(), faltantes);
+        assert_eq!(plan.eliminar.len(), 3);
+        inventario.importar(&plan).unwrap();
+        assert!(inventario.obtener_todos_productos().is_empty());
+    }
+}
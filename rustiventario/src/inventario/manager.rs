@@ -0,0 +1,1967 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::ops::Bound;
+use std::sync::mpsc::Receiver;
+
+use crate::inventario::{ConsultaTags, InventarioError};
+use crate::inventario::busqueda::IndiceNombres;
+use crate::inventario::comparacion::{comparar, Comparacion};
+use crate::inventario::deshacer::{CambioCompras, CambioConfiguracion, CambioProducto, Comando, EstadoProducto, Historial};
+use crate::inventario::eventos::{diferencias, EventoInventario, Foto, Observadores};
+use crate::inventario::importacion::{Actualizacion, PlanImportacion};
+use crate::models::codigo::{clave_codigo_barras, clave_sku, validar_codigo_barras, validar_sku};
+use crate::models::{
+    ConfiguracionStock, Dinero, EstadoOrden, LineaOrden, Moneda, Movimiento, MotivoMovimiento, OrdenCompra, Producto,
+    Proveedor, Variante,
+};
+use crate::storage::{Restauracion, Storage};
+use crate::utils::file_io::{guardar_datos, cargar_datos, DatosInventario, ModoCarga, ReporteCarga};
+
+/// Estructura principal para gestionar el inventario
+pub struct InventarioManager {
+    productos_por_id: HashMap<u32, Producto>,
+    productos_por_tag: HashMap<String, HashSet<u32>>,
+    // Pares (precio, id) ordenados, para consultas por precio en O(log n);
+    // los precios de cada moneda quedan juntos
+    productos_por_precio: BTreeSet<(Dinero, u32)>,
+    // Cantidad de productos con precio en cada moneda
+    productos_por_moneda: HashMap<Moneda, usize>,
+    productos_bajo_stock: HashSet<u32>,
+    indice_nombres: IndiceNombres,
+    // Códigos únicos, por su clave normalizada (ver `models::codigo`)
+    ids_por_sku: HashMap<String, u32>,
+    ids_por_codigo_barras: HashMap<String, u32>,
+    // IDs de las variantes de cada producto que las tiene
+    variantes_por_padre: HashMap<u32, BTreeSet<u32>>,
+    movimientos: HashMap<u32, Vec<Movimiento>>,
+    configuracion: ConfiguracionStock,
+    ubicaciones: BTreeSet<String>,
+    proveedores: BTreeMap<u32, Proveedor>,
+    ordenes: BTreeMap<u32, OrdenCompra>,
+    siguiente_id: u32,
+    siguiente_proveedor_id: u32,
+    siguiente_orden_id: u32,
+    almacenamiento: Option<Box<dyn Storage>>,
+    historial: Historial,
+    observadores: Observadores,
+}
+
+impl Default for InventarioManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl InventarioManager {
+    pub fn new() -> Self {
+        Self {
+            productos_por_id: HashMap::new(),
+            productos_por_tag: HashMap::new(),
+            productos_por_precio: BTreeSet::new(),
+            productos_por_moneda: HashMap::new(),
+            productos_bajo_stock: HashSet::new(),
+            indice_nombres: IndiceNombres::default(),
+            ids_por_sku: HashMap::new(),
+            ids_por_codigo_barras: HashMap::new(),
+            variantes_por_padre: HashMap::new(),
+            movimientos: HashMap::new(),
+            configuracion: ConfiguracionStock::default(),
+            ubicaciones: BTreeSet::new(),
+            proveedores: BTreeMap::new(),
+            ordenes: BTreeMap::new(),
+            siguiente_id: 1,
+            siguiente_proveedor_id: 1,
+            siguiente_orden_id: 1,
+            almacenamiento: None,
+            historial: Historial::default(),
+            observadores: Observadores::default(),
+        }
+    }
+    
+    /// Crear un gestor que carga desde `almacenamiento` y guarda ahí cada cambio
+    pub fn con_almacenamiento(mut almacenamiento: Box<dyn Storage>) -> Result<Self, InventarioError> {
+        let datos = almacenamiento.cargar()?;
+        
+        let mut manager = Self::new();
+        manager.reconstruir(datos);
+        manager.almacenamiento = Some(almacenamiento);
+        
+        Ok(manager)
+    }
+    
+    /// Descripción del almacenamiento conectado, si hay alguno
+    pub fn descripcion_almacenamiento(&self) -> Option<String> {
+        self.almacenamiento.as_ref().map(|s| s.descripcion())
+    }
+    
+    /// Recibir un evento por cada cambio de stock, alta o baja de un
+    /// producto. Los eventos se acumulan en el canal hasta leerlos.
+    pub fn suscribir(&mut self) -> Receiver<EventoInventario> {
+        self.observadores.suscribir()
+    }
+    
+    /// Cambiar el almacenamiento conectado (o quitarlo), devolviendo el
+    /// anterior. No se escribe nada: el nuevo ya tiene que tener el mismo
+    /// contenido que el gestor.
+    pub fn reemplazar_almacenamiento(&mut self, almacenamiento: Option<Box<dyn Storage>>) -> Option<Box<dyn Storage>> {
+        std::mem::replace(&mut self.almacenamiento, almacenamiento)
+    }
+    
+    /// Volcar los cambios que el almacenamiento tenga acumulados (ver
+    /// `Storage::sincronizar`); se usa al guardar automáticamente
+    pub fn sincronizar(&mut self) -> Result<(), InventarioError> {
+        if self.almacenamiento.is_none() {
+            return Ok(());
+        }
+        let datos = self.datos();
+        self.persistir(|s| s.sincronizar(&datos))
+    }
+    
+    ///Obtener todo
+    pub fn obtener_todos_productos(&self) -> Vec<&Producto> {
+        self.productos_por_id.values().collect()
+    }
+    
+    /// Obtener un producto por su ID
+    pub fn obtener_producto(&self, id: u32) -> Option<&Producto> {
+        self.productos_por_id.get(&id)
+    }
+
+    /// Agregar un nuevo producto
+    pub fn agregar_producto(&mut self, nombre: String, precio: Dinero, stock: u32, tags: Vec<String>, punto_reorden: Option<u32>) -> Result<u32, InventarioError> {
+        validar_nombre(&nombre)?;
+
+        let tags = tags.into_iter().collect();
+        let producto = Producto { nombre, precio, stock, tags, punto_reorden, ..Default::default() };
+        self.agregar(producto)
+    }
+    
+    /// Agregar una variante (por ejemplo una talla) de un producto. Toma
+    /// el nombre, los tags y, sin `precio`, el precio del padre.
+    pub fn agregar_variante(&mut self, padre_id: u32, atributos: BTreeMap<String, String>, stock: u32, precio: Option<Dinero>) -> Result<u32, InventarioError> {
+        let padre = self.productos_por_id.get(&padre_id)
+            .ok_or(InventarioError::NoEncontrado(padre_id))?;
+        if padre.variante.is_some() {
+            return Err(InventarioError::Validacion("una variante no puede tener variantes".to_string()));
+        }
+        
+        let atributos: BTreeMap<String, String> = atributos.into_iter()
+            .map(|(atributo, valor)| (atributo.trim().to_string(), valor.trim().to_string()))
+            .collect();
+        if atributos.is_empty() || atributos.iter().any(|(atributo, valor)| atributo.is_empty() || valor.is_empty()) {
+            return Err(InventarioError::Validacion("la variante necesita atributos con nombre y valor".to_string()));
+        }
+        // En los archivos se guardan como `atributo=valor`
+        if atributos.keys().any(|atributo| atributo.contains('=')) {
+            return Err(InventarioError::Validacion("el nombre de un atributo no puede llevar \"=\"".to_string()));
+        }
+        let repetida = self.variantes_de(padre_id).into_iter()
+            .any(|v| v.variante.as_ref().is_some_and(|v| v.atributos == atributos));
+        if repetida {
+            return Err(InventarioError::Validacion(format!("«{}» ya tiene esa variante", padre.nombre)));
+        }
+        if let Some(precio) = precio {
+            if precio.moneda != padre.precio.moneda {
+                return Err(InventarioError::Validacion(format!(
+                    "el precio de la variante tiene que estar en {}", padre.precio.moneda
+                )));
+            }
+        }
+        
+        let producto = Producto {
+            nombre: padre.nombre.clone(),
+            precio: precio.unwrap_or(padre.precio),
+            stock,
+            tags: padre.tags.clone(),
+            variante: Some(Variante { padre_id, atributos, precio_propio: precio.is_some() }),
+            ..Default::default()
+        };
+        self.agregar(producto)
+    }
+    
+    // Método privado para dar de alta un producto nuevo con el siguiente ID
+    fn agregar(&mut self, producto: Producto) -> Result<u32, InventarioError> {
+        let id = self.siguiente_id;
+        let producto = Producto { id, ..producto };
+        
+        // El stock inicial también queda en el historial
+        let movimiento = (producto.stock > 0).then(|| {
+            Movimiento::nuevo(id, producto.stock as i64, MotivoMovimiento::Ajuste, Some("Stock inicial".to_string()))
+        });
+        
+        // Con su primera variante el padre deja de contar como bajo stock
+        let padre = producto.variante.as_ref().map(|v| v.padre_id);
+        let foto = self.foto([id].into_iter().chain(padre));
+        self.persistir(|s| s.guardar_producto(&producto, id + 1, movimiento.as_slice()))?;
+        self.siguiente_id += 1;
+        self.movimientos.insert(id, movimiento.into_iter().collect());
+        self.indexar(producto);
+        
+        self.registrar_comando("Agregar", id, None);
+        self.notificar(foto);
+        
+        Ok(id)
+    }
+    
+    /// Variantes de un producto, por ID
+    pub fn variantes_de(&self, id: u32) -> Vec<&Producto> {
+        self.variantes_por_padre.get(&id)
+            .map(|ids| ids.iter().filter_map(|id| self.productos_por_id.get(id)).collect())
+            .unwrap_or_default()
+    }
+    
+    /// Stock de un producto más el de todas sus variantes
+    pub fn stock_agregado(&self, id: u32) -> u32 {
+        let propio = self.productos_por_id.get(&id).map(|p| p.stock).unwrap_or(0);
+        propio + self.variantes_de(id).iter().map(|v| v.stock).sum::<u32>()
+    }
+
+    
+    /// Actualizar el stock total de un producto, registrando la diferencia
+    /// como movimiento. La diferencia va al stock sin ubicación.
+    pub fn actualizar_stock(&mut self, id: u32, nuevo_stock: u32, motivo: MotivoMovimiento, nota: Option<String>) -> Result<(), InventarioError> {
+        let mut producto = self.productos_por_id.get(&id)
+            .ok_or(InventarioError::NoEncontrado(id))?
+            .clone();
+        validar_stock_total(&producto, nuevo_stock)?;
+        let antes = self.estado(id);
+        let delta = nuevo_stock as i64 - producto.stock as i64;
+        producto.stock = nuevo_stock;
+        
+        let movimiento = (delta != 0).then(|| Movimiento::nuevo(id, delta, motivo, nota));
+        
+        let foto = self.foto([id]);
+        let siguiente_id = self.siguiente_id;
+        self.persistir(|s| s.guardar_producto(&producto, siguiente_id, movimiento.as_slice()))?;
+        self.productos_por_id.insert(id, producto);
+        self.movimientos.entry(id).or_default().extend(movimiento);
+        
+        // Actualizar lista de bajo stock
+        self.actualizar_bajo_stock(id);
+        
+        self.registrar_comando("Cambiar stock de", id, antes);
+        self.notificar(foto);
+        
+        Ok(())
+    }
+    
+    /// Sumar (o restar, con `delta` negativo) unidades al stock de un producto.
+    /// Devuelve el stock resultante.
+    pub fn registrar_movimiento(&mut self, id: u32, delta: i64, motivo: MotivoMovimiento, nota: Option<String>) -> Result<u32, InventarioError> {
+        self.registrar_movimiento_en(id, None, delta, motivo, nota)?;
+        Ok(self.productos_por_id[&id].stock)
+    }
+    
+    /// Sumar o restar unidades en una ubicación (`None` = sin ubicación).
+    /// Devuelve las unidades que quedan en esa ubicación.
+    pub fn registrar_movimiento_en(&mut self, id: u32, ubicacion: Option<&str>, delta: i64, motivo: MotivoMovimiento, nota: Option<String>) -> Result<u32, InventarioError> {
+        self.aplicar_movimiento(id, ubicacion, delta, motivo, nota, None)
+    }
+    
+    /// Registrar la compra de `cantidad` unidades a `costo_unitario`, que
+    /// pasa a ser el costo del producto. Devuelve las unidades que quedan
+    /// en la ubicación.
+    pub fn registrar_compra(&mut self, id: u32, ubicacion: Option<&str>, cantidad: u32, costo_unitario: Dinero, nota: Option<String>) -> Result<u32, InventarioError> {
+        let producto = self.productos_por_id.get(&id)
+            .ok_or(InventarioError::NoEncontrado(id))?;
+        validar_moneda_costo(producto, costo_unitario)?;
+        if cantidad == 0 {
+            return Err(InventarioError::Validacion("la cantidad comprada debe ser mayor que cero".to_string()));
+        }
+        
+        self.aplicar_movimiento(id, ubicacion, cantidad as i64, MotivoMovimiento::Compra, nota, Some(costo_unitario))
+    }
+    
+    // Método privado para aplicar un movimiento de stock, con el costo
+    // unitario si es una compra que lo indica
+    fn aplicar_movimiento(
+        &mut self,
+        id: u32,
+        ubicacion: Option<&str>,
+        delta: i64,
+        motivo: MotivoMovimiento,
+        nota: Option<String>,
+        costo_unitario: Option<Dinero>,
+    ) -> Result<u32, InventarioError> {
+        let mut producto = self.productos_por_id.get(&id)
+            .ok_or(InventarioError::NoEncontrado(id))?
+            .clone();
+        self.validar_ubicacion(ubicacion)?;
+        
+        let disponible = producto.stock_en(ubicacion);
+        let fuera_de_rango = || InventarioError::Validacion(format!(
+            "el movimiento de {} unidades deja el stock de {} fuera de rango", delta, disponible
+        ));
+        let nuevo = u32::try_from(disponible as i64 + delta).map_err(|_| fuera_de_rango())?;
+        producto.stock = u32::try_from(producto.stock as i64 + delta).map_err(|_| fuera_de_rango())?;
+        if let Some(nombre) = ubicacion {
+            asignar_existencia(&mut producto, nombre, nuevo);
+        }
+        if costo_unitario.is_some() {
+            producto.costo = costo_unitario;
+        }
+        
+        let antes = self.estado(id);
+        let foto = self.foto([id]);
+        let movimiento = (delta != 0).then(|| Movimiento {
+            costo_unitario,
+            ..Movimiento::nuevo(id, delta, motivo, nota).en_ubicacion(ubicacion)
+        });
+        
+        let siguiente_id = self.siguiente_id;
+        self.persistir(|s| s.guardar_producto(&producto, siguiente_id, movimiento.as_slice()))?;
+        self.productos_por_id.insert(id, producto);
+        self.movimientos.entry(id).or_default().extend(movimiento);
+        
+        self.actualizar_bajo_stock(id);
+        self.registrar_comando("Cambiar stock de", id, antes);
+        self.notificar(foto);
+        
+        Ok(nuevo)
+    }
+    
+    /// Mover unidades de una ubicación a otra (`None` = sin ubicación).
+    /// El stock total no cambia; queda un par de movimientos de transferencia.
+    pub fn transferir(&mut self, id: u32, origen: Option<&str>, destino: Option<&str>, cantidad: u32, nota: Option<String>) -> Result<(), InventarioError> {
+        let mut producto = self.productos_por_id.get(&id)
+            .ok_or(InventarioError::NoEncontrado(id))?
+            .clone();
+        self.validar_ubicacion(origen)?;
+        self.validar_ubicacion(destino)?;
+        if origen == destino {
+            return Err(InventarioError::Validacion("el origen y el destino son la misma ubicación".to_string()));
+        }
+        if cantidad == 0 {
+            return Err(InventarioError::Validacion("la cantidad a transferir debe ser mayor que cero".to_string()));
+        }
+        
+        let disponible = producto.stock_en(origen);
+        if disponible < cantidad {
+            return Err(InventarioError::Validacion(format!(
+                "no se pueden transferir {} unidades desde {}: solo hay {}",
+                cantidad, origen.unwrap_or("sin ubicación"), disponible
+            )));
+        }
+        if let Some(nombre) = origen {
+            asignar_existencia(&mut producto, nombre, disponible - cantidad);
+        }
+        if let Some(nombre) = destino {
+            let actual = producto.stock_en(Some(nombre));
+            asignar_existencia(&mut producto, nombre, actual + cantidad);
+        }
+        
+        // Ambas mitades con la misma fecha, para poder emparejarlas
+        let salida = Movimiento::nuevo(id, -(cantidad as i64), MotivoMovimiento::Transferencia, nota.clone())
+            .en_ubicacion(origen);
+        let entrada = Movimiento { delta: cantidad as i64, nota, ubicacion: destino.map(String::from), ..salida.clone() };
+        let movimientos = [salida, entrada];
+        
+        let antes = self.estado(id);
+        let siguiente_id = self.siguiente_id;
+        self.persistir(|s| s.guardar_producto(&producto, siguiente_id, &movimientos))?;
+        self.productos_por_id.insert(id, producto);
+        self.movimientos.entry(id).or_default().extend(movimientos);
+        
+        self.registrar_comando("Transferir", id, antes);
+        
+        Ok(())
+    }
+    
+    /// Registrar una venta de varios productos: `lineas` son pares
+    /// (producto, unidades) que salen de `ubicacion`. Si algún producto no
+    /// tiene stock suficiente no se descuenta nada. La venta completa se
+    /// deshace de una vez.
+    pub fn registrar_venta(&mut self, lineas: &[(u32, u32)], ubicacion: Option<&str>, nota: Option<String>) -> Result<(), InventarioError> {
+        self.validar_ubicacion(ubicacion)?;
+        
+        // Un producto puede venir en varias líneas
+        let mut cantidades: BTreeMap<u32, u32> = BTreeMap::new();
+        for &(id, cantidad) in lineas {
+            let total = cantidades.entry(id).or_default();
+            *total = total.checked_add(cantidad)
+                .ok_or_else(|| InventarioError::Validacion(format!("cantidad fuera de rango para el producto ID {}", id)))?;
+        }
+        cantidades.retain(|_, cantidad| *cantidad > 0);
+        if cantidades.is_empty() {
+            return Err(InventarioError::Validacion("la venta no tiene productos".to_string()));
+        }
+        
+        let mut productos = Vec::new();
+        let mut movimientos = Vec::new();
+        for (&id, &cantidad) in &cantidades {
+            let mut producto = self.productos_por_id.get(&id)
+                .ok_or(InventarioError::NoEncontrado(id))?
+                .clone();
+            let disponible = producto.stock_en(ubicacion);
+            if cantidad > disponible {
+                return Err(InventarioError::Validacion(format!(
+                    "no hay stock suficiente de «{}»: se piden {} y hay {}", producto.nombre, cantidad, disponible
+                )));
+            }
+            
+            producto.stock -= cantidad;
+            if let Some(nombre) = ubicacion {
+                asignar_existencia(&mut producto, nombre, disponible - cantidad);
+            }
+            movimientos.push(Movimiento::nuevo(id, -(cantidad as i64), MotivoMovimiento::Venta, nota.clone()).en_ubicacion(ubicacion));
+            productos.push(producto);
+        }
+        
+        let foto = self.foto(cantidades.keys().copied());
+        let antes = cantidades.keys().map(|&id| (id, self.estado(id))).collect();
+        let siguiente_id = self.siguiente_id;
+        self.persistir(|s| s.guardar_productos(&productos, siguiente_id, &movimientos))?;
+        for movimiento in movimientos {
+            self.movimientos.entry(movimiento.producto_id).or_default().push(movimiento);
+        }
+        for producto in productos {
+            let id = producto.id;
+            self.productos_por_id.insert(id, producto);
+            self.actualizar_bajo_stock(id);
+        }
+        self.registrar_grupo("Registrar venta".to_string(), antes, None);
+        self.notificar(foto);
+        
+        Ok(())
+    }
+    
+    /// Nombres de las ubicaciones, en orden alfabético
+    pub fn ubicaciones(&self) -> Vec<&str> {
+        self.ubicaciones.iter().map(String::as_str).collect()
+    }
+    
+    /// Dar de alta una ubicación nueva
+    pub fn agregar_ubicacion(&mut self, nombre: &str) -> Result<(), InventarioError> {
+        let nombre = nombre.trim();
+        if nombre.is_empty() {
+            return Err(InventarioError::Validacion("el nombre de la ubicación no puede estar vacío".to_string()));
+        }
+        if self.ubicaciones.contains(nombre) {
+            return Err(InventarioError::Validacion(format!("ya existe la ubicación \"{}\"", nombre)));
+        }
+        
+        let mut ubicaciones = self.ubicaciones.clone();
+        ubicaciones.insert(nombre.to_string());
+        self.aplicar_ubicaciones(ubicaciones)
+    }
+    
+    /// Quitar una ubicación; solo se puede si ya no tiene stock
+    pub fn eliminar_ubicacion(&mut self, nombre: &str) -> Result<(), InventarioError> {
+        if !self.ubicaciones.contains(nombre) {
+            return Err(InventarioError::Validacion(format!("no existe la ubicación \"{}\"", nombre)));
+        }
+        if self.productos_por_id.values().any(|p| p.existencias.contains_key(nombre)) {
+            return Err(InventarioError::Validacion(format!("la ubicación \"{}\" todavía tiene stock", nombre)));
+        }
+        
+        let mut ubicaciones = self.ubicaciones.clone();
+        ubicaciones.remove(nombre);
+        self.aplicar_ubicaciones(ubicaciones)
+    }
+    
+    /// Unidades totales en cada ubicación, incluidas las que están vacías
+    pub fn totales_por_ubicacion(&self) -> BTreeMap<String, u32> {
+        let mut totales: BTreeMap<String, u32> = self.ubicaciones.iter()
+            .map(|nombre| (nombre.clone(), 0))
+            .collect();
+        for producto in self.productos_por_id.values() {
+            for (nombre, cantidad) in &producto.existencias {
+                *totales.entry(nombre.clone()).or_default() += cantidad;
+            }
+        }
+        totales
+    }
+    
+    /// Unidades totales que no tienen ubicación asignada
+    pub fn stock_sin_ubicacion(&self) -> u32 {
+        self.productos_por_id.values().map(|p| p.stock_en(None)).sum()
+    }
+    
+    // Método privado para comprobar que una ubicación esté dada de alta
+    fn validar_ubicacion(&self, ubicacion: Option<&str>) -> Result<(), InventarioError> {
+        match ubicacion {
+            Some(nombre) if !self.ubicaciones.contains(nombre) => {
+                Err(InventarioError::Validacion(format!("no existe la ubicación \"{}\"", nombre)))
+            }
+            _ => Ok(()),
+        }
+    }
+    
+    // Método privado para guardar la lista nueva de ubicaciones
+    fn aplicar_ubicaciones(&mut self, ubicaciones: BTreeSet<String>) -> Result<(), InventarioError> {
+        let lista: Vec<String> = ubicaciones.iter().cloned().collect();
+        self.persistir(|s| s.guardar_ubicaciones(&lista))?;
+        self.ubicaciones = ubicaciones;
+        Ok(())
+    }
+    
+    /// Proveedores, ordenados por ID
+    pub fn proveedores(&self) -> Vec<&Proveedor> {
+        self.proveedores.values().collect()
+    }
+    
+    pub fn proveedor(&self, id: u32) -> Option<&Proveedor> {
+        self.proveedores.get(&id)
+    }
+    
+    /// Dar de alta un proveedor. Devuelve su ID.
+    pub fn agregar_proveedor(&mut self, nombre: &str, contacto: Option<&str>) -> Result<u32, InventarioError> {
+        let nombre = nombre.trim();
+        if nombre.is_empty() {
+            return Err(InventarioError::Validacion("el nombre del proveedor no puede estar vacío".to_string()));
+        }
+        
+        // Los IDs no se reusan aunque se haya eliminado el último
+        let id = self.siguiente_proveedor_id;
+        let contacto = contacto.map(str::trim).filter(|c| !c.is_empty()).map(String::from);
+        let proveedor = Proveedor { id, nombre: nombre.to_string(), contacto };
+        let mut proveedores = self.proveedores.clone();
+        proveedores.insert(id, proveedor.clone());
+        
+        let ordenes = self.ordenes.clone();
+        self.siguiente_proveedor_id += 1;
+        self.aplicar_compras(proveedores, ordenes).inspect_err(|_| self.siguiente_proveedor_id = id)?;
+        
+        let descripcion = format!("Agregar proveedor «{}»", proveedor.nombre);
+        self.registrar_grupo(descripcion, Vec::new(), Some(CambioCompras::Proveedor { id, antes: None, despues: Some(proveedor) }));
+        Ok(id)
+    }
+    
+    /// Quitar un proveedor; solo se puede si ningún producto ni orden lo usa
+    pub fn eliminar_proveedor(&mut self, id: u32) -> Result<(), InventarioError> {
+        let proveedor = self.proveedores.get(&id)
+            .ok_or_else(|| InventarioError::Validacion(format!("no existe el proveedor ID {}", id)))?;
+        if self.productos_por_id.values().any(|p| p.proveedor_id == Some(id)) {
+            return Err(InventarioError::Validacion(format!("el proveedor \"{}\" todavía tiene productos", proveedor.nombre)));
+        }
+        if self.ordenes.values().any(|o| o.proveedor_id == id) {
+            return Err(InventarioError::Validacion(format!("el proveedor \"{}\" tiene órdenes de compra", proveedor.nombre)));
+        }
+        
+        let descripcion = format!("Eliminar proveedor «{}»", proveedor.nombre);
+        let mut proveedores = self.proveedores.clone();
+        let anterior = proveedores.remove(&id);
+        let ordenes = self.ordenes.clone();
+        self.aplicar_compras(proveedores, ordenes)?;
+        
+        // Deshacer un cambio de proveedor anterior podría volver a apuntar a
+        // este, así que su eliminación también se deshace
+        self.registrar_grupo(descripcion, Vec::new(), Some(CambioCompras::Proveedor { id, antes: anterior, despues: None }));
+        Ok(())
+    }
+    
+    /// Asignar (o quitar, con `None`) el proveedor de un producto
+    pub fn establecer_proveedor(&mut self, id: u32, proveedor_id: Option<u32>) -> Result<(), InventarioError> {
+        let mut producto = self.productos_por_id.get(&id)
+            .ok_or(InventarioError::NoEncontrado(id))?
+            .clone();
+        if let Some(proveedor_id) = proveedor_id {
+            self.validar_proveedor(proveedor_id)?;
+        }
+        
+        let antes = self.estado(id);
+        producto.proveedor_id = proveedor_id;
+        
+        let siguiente_id = self.siguiente_id;
+        self.persistir(|s| s.guardar_producto(&producto, siguiente_id, &[]))?;
+        // El proveedor no está en ningún índice
+        self.productos_por_id.insert(id, producto);
+        
+        self.registrar_comando("Cambiar proveedor de", id, antes);
+        
+        Ok(())
+    }
+    
+    /// Órdenes de compra, ordenadas por ID
+    pub fn ordenes(&self) -> Vec<&OrdenCompra> {
+        self.ordenes.values().collect()
+    }
+    
+    /// Crear una orden de compra en borrador. Los costos de las líneas, si
+    /// los hay, van en la moneda del precio de cada producto. Devuelve su ID.
+    pub fn crear_orden(&mut self, proveedor_id: u32, lineas: Vec<LineaOrden>, nota: Option<String>) -> Result<u32, InventarioError> {
+        self.validar_proveedor(proveedor_id)?;
+        for linea in &lineas {
+            let producto = self.productos_por_id.get(&linea.producto_id)
+                .ok_or(InventarioError::NoEncontrado(linea.producto_id))?;
+            if linea.cantidad == 0 {
+                return Err(InventarioError::Validacion("la cantidad pedida debe ser mayor que cero".to_string()));
+            }
+            if let Some(costo) = linea.costo_unitario {
+                validar_moneda_costo(producto, costo)?;
+            }
+        }
+        
+        let id = self.siguiente_orden_id;
+        // Una orden nueva no trae nada recibido
+        let lineas = lineas.into_iter().map(|l| LineaOrden { recibida: 0, ..l }).collect();
+        let nota = nota.filter(|n| !n.trim().is_empty());
+        let orden = OrdenCompra::nueva(id, proveedor_id, lineas, nota);
+        let mut ordenes = self.ordenes.clone();
+        ordenes.insert(id, orden.clone());
+        
+        let proveedores = self.proveedores.clone();
+        self.siguiente_orden_id += 1;
+        self.aplicar_compras(proveedores, ordenes).inspect_err(|_| self.siguiente_orden_id = id)?;
+        
+        self.registrar_grupo(format!("Crear orden #{}", id), Vec::new(), Some(CambioCompras::Orden { id, antes: None, despues: Some(orden) }));
+        Ok(id)
+    }
+    
+    /// Eliminar una orden; solo las que siguen en borrador
+    pub fn eliminar_orden(&mut self, id: u32) -> Result<(), InventarioError> {
+        let orden = self.buscar_orden(id)?;
+        if orden.estado != EstadoOrden::Borrador {
+            return Err(InventarioError::Validacion(format!("la orden #{} ya se envió y no se puede eliminar", id)));
+        }
+        
+        let mut ordenes = self.ordenes.clone();
+        let anterior = ordenes.remove(&id);
+        let proveedores = self.proveedores.clone();
+        self.aplicar_compras(proveedores, ordenes)?;
+        
+        self.registrar_grupo(format!("Eliminar orden #{}", id), Vec::new(), Some(CambioCompras::Orden { id, antes: anterior, despues: None }));
+        Ok(())
+    }
+    
+    /// Marcar como enviada al proveedor una orden en borrador
+    pub fn enviar_orden(&mut self, id: u32) -> Result<(), InventarioError> {
+        let orden = self.buscar_orden(id)?;
+        if orden.estado != EstadoOrden::Borrador {
+            return Err(InventarioError::Validacion(format!("la orden #{} ya se envió", id)));
+        }
+        if orden.lineas.is_empty() {
+            return Err(InventarioError::Validacion(format!("la orden #{} no tiene productos", id)));
+        }
+        
+        let mut enviada = orden.clone();
+        enviada.estado = EstadoOrden::Enviada;
+        let mut ordenes = self.ordenes.clone();
+        let anterior = ordenes.insert(id, enviada.clone());
+        let proveedores = self.proveedores.clone();
+        self.aplicar_compras(proveedores, ordenes)?;
+        
+        self.registrar_grupo(format!("Enviar orden #{}", id), Vec::new(), Some(CambioCompras::Orden { id, antes: anterior, despues: Some(enviada) }));
+        Ok(())
+    }
+    
+    /// Recibir mercadería de una orden enviada: `cantidades` son pares
+    /// (producto, unidades que llegaron) y entran al stock en `ubicacion`
+    /// como compras, al costo de la línea si lo tiene. La orden queda
+    /// recibida cuando no falta nada. La recepción se deshace de una vez.
+    pub fn recibir_orden(&mut self, id: u32, cantidades: &[(u32, u32)], ubicacion: Option<&str>) -> Result<(), InventarioError> {
+        let mut orden = self.buscar_orden(id)?.clone();
+        if !orden.estado.abierta() {
+            return Err(InventarioError::Validacion(format!("la orden #{} no está esperando mercadería", id)));
+        }
+        self.validar_ubicacion(ubicacion)?;
+        
+        let mut productos: BTreeMap<u32, Producto> = BTreeMap::new();
+        let mut movimientos = Vec::new();
+        for &(producto_id, cantidad) in cantidades.iter().filter(|(_, cantidad)| *cantidad > 0) {
+            let linea = orden.lineas.iter_mut()
+                .find(|l| l.producto_id == producto_id && l.pendiente() > 0)
+                .ok_or_else(|| InventarioError::Validacion(format!(
+                    "la orden #{} no tiene pendiente el producto ID {}", id, producto_id
+                )))?;
+            if cantidad > linea.pendiente() {
+                return Err(InventarioError::Validacion(format!(
+                    "se recibieron {} unidades del producto ID {} y solo faltaban {}", cantidad, producto_id, linea.pendiente()
+                )));
+            }
+            linea.recibida += cantidad;
+            
+            // Un producto puede aparecer en varias líneas de la orden
+            let mut producto = match productos.remove(&producto_id) {
+                Some(producto) => producto,
+                None => self.productos_por_id.get(&producto_id)
+                    .ok_or(InventarioError::NoEncontrado(producto_id))?
+                    .clone(),
+            };
+            producto.stock = producto.stock.checked_add(cantidad).ok_or_else(|| InventarioError::Validacion(format!(
+                "el stock del producto ID {} queda fuera de rango", producto_id
+            )))?;
+            if let Some(nombre) = ubicacion {
+                let actual = producto.stock_en(Some(nombre));
+                asignar_existencia(&mut producto, nombre, actual + cantidad);
+            }
+            // Un costo en otra moneda (el precio cambió después de pedir) no se usa
+            let costo_unitario = linea.costo_unitario.filter(|c| c.moneda == producto.precio.moneda);
+            if costo_unitario.is_some() {
+                producto.costo = costo_unitario;
+            }
+            movimientos.push(Movimiento {
+                costo_unitario,
+                ..Movimiento::nuevo(producto_id, cantidad as i64, MotivoMovimiento::Compra, Some(format!("Orden #{}", id)))
+                    .en_ubicacion(ubicacion)
+            });
+            productos.insert(producto_id, producto);
+        }
+        if movimientos.is_empty() {
+            return Err(InventarioError::Validacion("no se indicó ninguna cantidad recibida".to_string()));
+        }
+        
+        orden.estado = if orden.lineas.iter().all(|l| l.pendiente() == 0) {
+            EstadoOrden::Recibida
+        } else {
+            EstadoOrden::RecibidaParcial
+        };
+        let mut ordenes = self.ordenes.clone();
+        let cambio = CambioCompras::Orden { id, antes: ordenes.insert(id, orden.clone()), despues: Some(orden) };
+        
+        let lista_proveedores: Vec<Proveedor> = self.proveedores.values().cloned().collect();
+        let lista_ordenes: Vec<OrdenCompra> = ordenes.values().cloned().collect();
+        let lista_productos: Vec<Producto> = productos.values().cloned().collect();
+        let foto = self.foto(productos.keys().copied());
+        let antes = productos.keys().map(|&producto_id| (producto_id, self.estado(producto_id))).collect();
+        let (siguiente_proveedor_id, siguiente_orden_id) = (self.siguiente_proveedor_id, self.siguiente_orden_id);
+        self.persistir(|s| s.guardar_compras(
+            &lista_proveedores, &lista_ordenes, siguiente_proveedor_id, siguiente_orden_id, &lista_productos, &movimientos
+        ))?;
+        self.ordenes = ordenes;
+        for movimiento in movimientos {
+            self.movimientos.entry(movimiento.producto_id).or_default().push(movimiento);
+        }
+        for (producto_id, producto) in productos {
+            self.productos_por_id.insert(producto_id, producto);
+            self.actualizar_bajo_stock(producto_id);
+        }
+        self.registrar_grupo(format!("Recibir orden #{}", id), antes, Some(cambio));
+        self.notificar(foto);
+        
+        Ok(())
+    }
+    
+    // Método privado para buscar una orden o avisar que no existe
+    fn buscar_orden(&self, id: u32) -> Result<&OrdenCompra, InventarioError> {
+        self.ordenes.get(&id)
+            .ok_or_else(|| InventarioError::Validacion(format!("no existe la orden #{}", id)))
+    }
+    
+    // Método privado para comprobar que un proveedor esté dado de alta
+    fn validar_proveedor(&self, id: u32) -> Result<(), InventarioError> {
+        if !self.proveedores.contains_key(&id) {
+            return Err(InventarioError::Validacion(format!("no existe el proveedor ID {}", id)));
+        }
+        Ok(())
+    }
+    
+    // Método privado para guardar las listas nuevas de proveedores y órdenes
+    fn aplicar_compras(&mut self, proveedores: BTreeMap<u32, Proveedor>, ordenes: BTreeMap<u32, OrdenCompra>) -> Result<(), InventarioError> {
+        let lista_proveedores: Vec<Proveedor> = proveedores.values().cloned().collect();
+        let lista_ordenes: Vec<OrdenCompra> = ordenes.values().cloned().collect();
+        let (siguiente_proveedor_id, siguiente_orden_id) = (self.siguiente_proveedor_id, self.siguiente_orden_id);
+        self.persistir(|s| s.guardar_compras(&lista_proveedores, &lista_ordenes, siguiente_proveedor_id, siguiente_orden_id, &[], &[]))?;
+        self.proveedores = proveedores;
+        self.ordenes = ordenes;
+        Ok(())
+    }
+    
+    /// Historial de movimientos de un producto, del más antiguo al más reciente
+    pub fn movimientos_de(&self, id: u32) -> &[Movimiento] {
+        self.movimientos.get(&id).map(Vec::as_slice).unwrap_or_default()
+    }
+    
+    /// Actualizar todos los campos de un producto. Una variante conserva
+    /// el nombre y los tags del padre, y su precio pasa a ser propio si es
+    /// distinto al del padre; al cambiar un padre cambian sus variantes.
+    pub fn actualizar_producto(&mut self, id: u32, nombre: String, precio: Dinero, stock: u32, tags: Vec<String>, punto_reorden: Option<u32>) -> Result<(), InventarioError> {
+        validar_nombre(&nombre)?;
+        
+        // Una variante conserva el nombre y los tags del padre
+        let mut variante = self.productos_por_id.get(&id).and_then(|p| p.variante.clone());
+        let padre = variante.as_ref().and_then(|v| self.productos_por_id.get(&v.padre_id));
+        let (nombre, tags) = match (variante.as_mut(), padre) {
+            (Some(variante), Some(padre)) => {
+                if precio.moneda != padre.precio.moneda {
+                    return Err(InventarioError::Validacion(format!(
+                        "el precio de la variante tiene que estar en {}", padre.precio.moneda
+                    )));
+                }
+                variante.precio_propio = precio != padre.precio;
+                (padre.nombre.clone(), padre.tags.iter().cloned().collect())
+            }
+            _ => (nombre, tags),
+        };
+
+        // Las existencias, los códigos, el costo y el proveedor se cambian por separado;
+        // un costo en otra moneda que el nuevo precio deja de servir
+        let anterior = self.productos_por_id.get(&id)
+            .ok_or(InventarioError::NoEncontrado(id))?;
+        let existencias = anterior.existencias.clone();
+        let (sku, codigo_barras) = (anterior.sku.clone(), anterior.codigo_barras.clone());
+        let costo = anterior.costo.filter(|costo| costo.moneda == precio.moneda);
+        let proveedor_id = anterior.proveedor_id;
+
+        let antes = self.estado(id);
+        let tags_nuevos: HashSet<String> = tags.into_iter().collect();
+        let producto = Producto {
+            id, nombre, precio, stock, tags: tags_nuevos.clone(), punto_reorden, existencias, sku, codigo_barras, costo,
+            proveedor_id, variante,
+        };
+        validar_stock_total(&producto, stock)?;
+
+        // Un cambio de stock desde el formulario cuenta como ajuste
+        let delta = stock as i64 - self.productos_por_id[&id].stock as i64;
+        let movimiento = (delta != 0).then(|| {
+            Movimiento::nuevo(id, delta, MotivoMovimiento::Ajuste, Some("Edición del producto".to_string()))
+        });
+
+        let variantes = self.variantes_siguiendo(&producto);
+        if !variantes.is_empty() {
+            return self.actualizar_familia(producto, movimiento, variantes);
+        }
+
+        let foto = self.foto([id]);
+        let siguiente_id = self.siguiente_id;
+        self.persistir(|s| s.guardar_producto(&producto, siguiente_id, movimiento.as_slice()))?;
+        self.movimientos.entry(id).or_default().extend(movimiento);
+        let tags_anteriores = self.productos_por_id.insert(id, producto)
+            .map(|anterior| {
+                // Reubicar en el índice de precios
+                self.desindexar_precio(anterior.precio, id);
+                anterior.tags
+            })
+            .unwrap_or_default();
+        self.indexar_precio(precio, id);
+        self.indice_nombres.insertar(id, &self.productos_por_id[&id].nombre);
+
+        // Quitar el producto de los tags que ya no tiene
+        for tag in tags_anteriores.difference(&tags_nuevos) {
+            if let Some(ids) = self.productos_por_tag.get_mut(tag) {
+                ids.remove(&id);
+
+                // Eliminar tag si ya no tiene productos
+                if ids.is_empty() {
+                    self.productos_por_tag.remove(tag);
+                }
+            }
+        }
+
+        // Indexar los tags nuevos
+        for tag in tags_nuevos.difference(&tags_anteriores) {
+            self.productos_por_tag
+                .entry(tag.clone())
+                .or_default()
+                .insert(id);
+        }
+
+        // Actualizar lista de bajo stock
+        self.actualizar_bajo_stock(id);
+
+        self.registrar_comando("Editar", id, antes);
+        self.notificar(foto);
+
+        Ok(())
+    }
+
+    // Método privado con las variantes de `padre` que cambian para seguirlo
+    fn variantes_siguiendo(&self, padre: &Producto) -> Vec<Producto> {
+        self.variantes_de(padre.id).into_iter()
+            .filter_map(|variante| {
+                let precio_propio = variante.variante.as_ref().is_some_and(|v| v.precio_propio);
+                let precio = if precio_propio { variante.precio } else { padre.precio };
+                let actualizada = Producto {
+                    nombre: padre.nombre.clone(),
+                    tags: padre.tags.clone(),
+                    precio,
+                    costo: variante.costo.filter(|costo| costo.moneda == precio.moneda),
+                    ..variante.clone()
+                };
+                (actualizada != *variante).then_some(actualizada)
+            })
+            .collect()
+    }
+    
+    // Método privado para guardar un padre junto con sus variantes, todo o
+    // nada; se deshace de una vez
+    fn actualizar_familia(&mut self, padre: Producto, movimiento: Option<Movimiento>, variantes: Vec<Producto>) -> Result<(), InventarioError> {
+        let (padre_id, descripcion) = (padre.id, format!("Editar «{}»", padre.nombre));
+        let productos: Vec<Producto> = [padre].into_iter().chain(variantes).collect();
+        
+        let foto = self.foto(productos.iter().map(|p| p.id));
+        let antes = productos.iter().map(|p| (p.id, self.estado(p.id))).collect();
+        let siguiente_id = self.siguiente_id;
+        self.persistir(|s| s.guardar_productos(&productos, siguiente_id, movimiento.as_slice()))?;
+        self.movimientos.entry(padre_id).or_default().extend(movimiento);
+        for producto in productos {
+            self.desindexar(producto.id);
+            self.indexar(producto);
+        }
+        self.registrar_grupo(descripcion, antes, None);
+        self.notificar(foto);
+        
+        Ok(())
+    }
+    
+    /// Asignar (o quitar, con `None`) el SKU y el código de barras de un
+    /// producto. Ninguno puede estar repetido en otro producto.
+    pub fn establecer_codigos(&mut self, id: u32, sku: Option<&str>, codigo_barras: Option<&str>) -> Result<(), InventarioError> {
+        let mut producto = self.productos_por_id.get(&id)
+            .ok_or(InventarioError::NoEncontrado(id))?
+            .clone();
+        let sku = sku.map(str::trim).filter(|s| !s.is_empty());
+        let codigo_barras = codigo_barras.map(str::trim).filter(|c| !c.is_empty());
+        self.comprobar_codigos(Some(id), sku, codigo_barras)?;
+        
+        let antes = self.estado(id);
+        producto.sku = sku.map(String::from);
+        producto.codigo_barras = codigo_barras.map(String::from);
+        
+        let siguiente_id = self.siguiente_id;
+        self.persistir(|s| s.guardar_producto(&producto, siguiente_id, &[]))?;
+        // Los movimientos se conservan, solo cambian los índices
+        self.desindexar(id);
+        self.indexar(producto);
+        
+        self.registrar_comando("Cambiar códigos de", id, antes);
+        
+        Ok(())
+    }
+    
+    /// Asignar (o quitar, con `None`) el costo unitario de un producto,
+    /// que debe estar en la moneda de su precio
+    pub fn establecer_costo(&mut self, id: u32, costo: Option<Dinero>) -> Result<(), InventarioError> {
+        let mut producto = self.productos_por_id.get(&id)
+            .ok_or(InventarioError::NoEncontrado(id))?
+            .clone();
+        if let Some(costo) = costo {
+            validar_moneda_costo(&producto, costo)?;
+        }
+        
+        let antes = self.estado(id);
+        producto.costo = costo;
+        
+        let siguiente_id = self.siguiente_id;
+        self.persistir(|s| s.guardar_producto(&producto, siguiente_id, &[]))?;
+        // El costo no está en ningún índice
+        self.productos_por_id.insert(id, producto);
+        
+        self.registrar_comando("Cambiar costo de", id, antes);
+        
+        Ok(())
+    }
+    
+    /// Comprobar que un SKU y un código de barras sean válidos y no los
+    /// use otro producto que `id` (con `None`, ningún producto)
+    pub fn comprobar_codigos(&self, id: Option<u32>, sku: Option<&str>, codigo_barras: Option<&str>) -> Result<(), InventarioError> {
+        let en_uso = |dueno: Option<&u32>| dueno.filter(|dueno| Some(**dueno) != id).copied();
+        
+        if let Some(sku) = sku {
+            validar_sku(sku).map_err(InventarioError::Validacion)?;
+            if let Some(otro) = en_uso(self.ids_por_sku.get(&clave_sku(sku))) {
+                return Err(InventarioError::Validacion(format!("el SKU \"{}\" ya lo usa el producto ID {}", sku, otro)));
+            }
+        }
+        if let Some(codigo) = codigo_barras {
+            validar_codigo_barras(codigo).map_err(InventarioError::Validacion)?;
+            if let Some(otro) = en_uso(self.ids_por_codigo_barras.get(&clave_codigo_barras(codigo))) {
+                return Err(InventarioError::Validacion(format!(
+                    "el código de barras \"{}\" ya lo usa el producto ID {}", codigo, otro
+                )));
+            }
+        }
+        
+        Ok(())
+    }
+    
+    /// Buscar un producto por código de barras o por SKU, por ejemplo lo
+    /// que escribe un lector de códigos. Un UPC-A encuentra su EAN-13.
+    pub fn buscar_por_codigo(&self, codigo: &str) -> Option<&Producto> {
+        let codigo = codigo.trim();
+        self.ids_por_codigo_barras.get(&clave_codigo_barras(codigo))
+            .or_else(|| self.ids_por_sku.get(&clave_sku(codigo)))
+            .and_then(|id| self.productos_por_id.get(id))
+    }
+    
+    // Método privado para comparar un producto con su punto de reorden;
+    // el stock de un producto con variantes está en cada variante
+    fn actualizar_bajo_stock(&mut self, id: u32) {
+        let bajo = !self.variantes_por_padre.contains_key(&id)
+            && self.productos_por_id.get(&id).is_some_and(|p| self.configuracion.es_bajo_stock(p));
+        
+        if bajo {
+            self.productos_bajo_stock.insert(id);
+        } else {
+            self.productos_bajo_stock.remove(&id);
+        }
+    }
+    
+    /// Buscar productos por tag
+    pub fn buscar_por_tag(&self, tag: &str) -> Vec<&Producto> {
+        self.productos_por_tag.get(tag)
+            .map(|ids| ids.iter()
+                .filter_map(|id| self.productos_por_id.get(id))
+                .collect())
+            .unwrap_or_default()
+    }
+    
+    /// Buscar productos con una consulta de tags, por ejemplo
+    /// `(electrónica OR hogar) AND NOT móviles`. Se ordenan por ID.
+    pub fn buscar_por_tags(&self, consulta: &str) -> Result<Vec<&Producto>, InventarioError> {
+        Ok(self.buscar_por_consulta(&ConsultaTags::parsear(consulta)?))
+    }
+    
+    /// Productos que cumplen una consulta de tags ya interpretada, ordenados por ID
+    pub fn buscar_por_consulta(&self, consulta: &ConsultaTags) -> Vec<&Producto> {
+        let ids = consulta.evaluar(
+            &|tag| self.productos_por_tag.get(tag),
+            &|| self.productos_por_id.keys().copied().collect(),
+        );
+        
+        let mut productos: Vec<&Producto> = ids.iter()
+            .filter_map(|id| self.productos_por_id.get(id))
+            .collect();
+        productos.sort_by_key(|p| p.id);
+        productos
+    }
+    
+    /// Buscar productos por nombre sin importar mayúsculas ni acentos, y
+    /// tolerando errores de tipeo. Los más relevantes van primero.
+    pub fn buscar_por_nombre(&self, consulta: &str) -> Vec<&Producto> {
+        self.indice_nombres.buscar(consulta)
+            .into_iter()
+            .filter_map(|(id, _)| self.productos_por_id.get(&id))
+            .collect()
+    }
+    
+    /// Moneda en la que están los precios de la mayoría de los productos;
+    /// es la que se supone cuando no se indica otra
+    pub fn moneda_principal(&self) -> Moneda {
+        self.productos_por_moneda.iter()
+            .max_by_key(|(moneda, cantidad)| (**cantidad, std::cmp::Reverse(**moneda)))
+            .map(|(moneda, _)| *moneda)
+            .unwrap_or_default()
+    }
+    
+    /// Obtener productos más caros. Con varias monedas, los precios de
+    /// cada una se ordenan por separado.
+    pub fn productos_mas_caros(&self, n: usize) -> Vec<&Producto> {
+        self.productos_por_precio.iter()
+            .rev()
+            .take(n)
+            .map(|(_, id)| &self.productos_por_id[id])
+            .collect()
+    }
+    
+    /// Obtener productos más baratos
+    pub fn productos_mas_baratos(&self, n: usize) -> Vec<&Producto> {
+        self.productos_por_precio.iter()
+            .take(n)
+            .map(|(_, id)| &self.productos_por_id[id])
+            .collect()
+    }
+    
+    /// Productos con precio entre `min` y `max` (ambos incluidos),
+    /// del más barato al más caro. Solo entran los de la moneda de `min`
+    /// y `max`; si son distintas no hay ninguno.
+    pub fn productos_en_rango(&self, min: Dinero, max: Dinero) -> Vec<&Producto> {
+        self.rango_de_precios(min, max, None).collect()
+    }
+    
+    /// Una página de `productos_en_rango` con hasta `tamano` productos,
+    /// a partir del que sigue a `despues_de`: el (precio, ID) del último
+    /// producto de la página anterior, o `None` para la primera. Así cada
+    /// página se busca directo en el índice, sin recorrer las anteriores.
+    pub fn pagina_en_rango(&self, min: Dinero, max: Dinero, despues_de: Option<(Dinero, u32)>, tamano: usize) -> Vec<&Producto> {
+        self.rango_de_precios(min, max, despues_de)
+            .take(tamano)
+            .collect()
+    }
+    
+    /// Cantidad de productos con precio entre `min` y `max`, para paginar
+    pub fn cantidad_en_rango(&self, min: Dinero, max: Dinero) -> usize {
+        self.rango_de_precios(min, max, None).count()
+    }
+    
+    // Método privado para recorrer el índice de precios entre dos valores,
+    // empezando después de `despues_de` si se indica
+    fn rango_de_precios(&self, min: Dinero, max: Dinero, despues_de: Option<(Dinero, u32)>) -> impl Iterator<Item = &Producto> + '_ {
+        let (inicio, fin) = ((min, 0), (max, u32::MAX));
+        let desde = match despues_de {
+            Some(clave) if clave >= inicio => Bound::Excluded(clave),
+            _ => Bound::Included(inicio),
+        };
+        
+        // Un rango invertido o ya recorrido no contiene nada (y `range`
+        // entraría en pánico)
+        let vacio = min > max || min.moneda != max.moneda || despues_de.is_some_and(|clave| clave >= fin);
+        let rango = if vacio {
+            self.productos_por_precio.range(inicio..inicio)
+        } else {
+            self.productos_por_precio.range((desde, Bound::Included(fin)))
+        };
+        
+        rango.map(|(_, id)| &self.productos_por_id[id])
+    }
+    
+    // Método privado para agregar un precio al índice y a la cuenta por moneda
+    fn indexar_precio(&mut self, precio: Dinero, id: u32) {
+        self.productos_por_precio.insert((precio, id));
+        *self.productos_por_moneda.entry(precio.moneda).or_default() += 1;
+    }
+    
+    // Método privado para quitar un precio del índice y de la cuenta por moneda
+    fn desindexar_precio(&mut self, precio: Dinero, id: u32) {
+        if self.productos_por_precio.remove(&(precio, id)) {
+            if let Some(cantidad) = self.productos_por_moneda.get_mut(&precio.moneda) {
+                *cantidad -= 1;
+                if *cantidad == 0 {
+                    self.productos_por_moneda.remove(&precio.moneda);
+                }
+            }
+        }
+    }
+    
+    /// Obtener productos con bajo stock
+    pub fn productos_con_bajo_stock(&self) -> Vec<&Producto> {
+        self.productos_bajo_stock.iter()
+            .filter_map(|id| self.productos_por_id.get(id))
+            .collect()
+    }
+    
+    /// Si el producto está en su punto de reorden o por debajo
+    pub fn tiene_bajo_stock(&self, id: u32) -> bool {
+        self.productos_bajo_stock.contains(&id)
+    }
+    
+    /// Punto de reorden efectivo de un producto (propio, de sus tags o global)
+    pub fn punto_reorden_de(&self, id: u32) -> Option<u32> {
+        self.productos_por_id.get(&id).map(|p| self.configuracion.punto_reorden(p))
+    }
+    
+    /// Puntos de reorden global y por tag
+    pub fn configuracion_stock(&self) -> &ConfiguracionStock {
+        &self.configuracion
+    }
+    
+    /// Cambiar el punto de reorden de los productos sin uno propio ni en sus tags
+    pub fn establecer_punto_reorden_global(&mut self, punto_reorden: u32) -> Result<(), InventarioError> {
+        let antes = self.configuracion.clone();
+        let mut configuracion = antes.clone();
+        configuracion.punto_reorden_global = punto_reorden;
+        self.aplicar_configuracion(configuracion)?;
+        
+        self.registrar_configuracion("Punto de reorden global".to_string(), antes);
+        Ok(())
+    }
+    
+    /// Cambiar el punto de reorden heredado por los productos de un tag;
+    /// con `None` el tag deja de tener uno propio
+    pub fn establecer_punto_reorden_tag(&mut self, tag: &str, punto_reorden: Option<u32>) -> Result<(), InventarioError> {
+        let antes = self.configuracion.clone();
+        let mut configuracion = antes.clone();
+        match punto_reorden {
+            Some(punto) => configuracion.punto_reorden_por_tag.insert(tag.to_string(), punto),
+            None => configuracion.punto_reorden_por_tag.remove(tag),
+        };
+        self.aplicar_configuracion(configuracion)?;
+        
+        self.registrar_configuracion(format!("Punto de reorden de «{}»", tag), antes);
+        Ok(())
+    }
+    
+    // Método privado para guardar una configuración nueva y recalcular el bajo stock
+    fn aplicar_configuracion(&mut self, configuracion: ConfiguracionStock) -> Result<(), InventarioError> {
+        self.persistir(|s| s.guardar_configuracion(&configuracion))?;
+        self.configuracion = configuracion;
+        
+        let ids: Vec<u32> = self.productos_por_id.keys().copied().collect();
+        let foto = self.foto(ids.iter().copied());
+        for id in ids {
+            self.actualizar_bajo_stock(id);
+        }
+        self.notificar(foto);
+        
+        Ok(())
+    }
+    
+    /// Copia de todo el inventario, ordenada, lista para guardar
+    pub fn datos(&self) -> DatosInventario {
+        let mut productos: Vec<Producto> = self.productos_por_id.values().cloned().collect();
+        productos.sort_by_key(|p| p.id);
+        
+        // Ordenar por fecha conservando el orden de cada producto
+        let mut movimientos: Vec<Movimiento> = productos.iter()
+            .flat_map(|p| self.movimientos_de(p.id).iter().cloned())
+            .collect();
+        movimientos.sort_by_key(|m| m.fecha);
+        
+        DatosInventario {
+            siguiente_id: self.siguiente_id,
+            productos,
+            movimientos,
+            configuracion: self.configuracion.clone(),
+            ubicaciones: self.ubicaciones.iter().cloned().collect(),
+            proveedores: self.proveedores.values().cloned().collect(),
+            ordenes: self.ordenes.values().cloned().collect(),
+            siguiente_proveedor_id: self.siguiente_proveedor_id,
+            siguiente_orden_id: self.siguiente_orden_id,
+        }
+    }
+    
+    /// Guardar inventario en archivo (CSV o JSON según la extensión)
+    pub fn guardar_inventario(&self, ruta: &str) -> Result<(), InventarioError> {
+        guardar_datos(ruta, &self.datos())
+    }
+    
+    /// Qué cambió en el inventario desde la versión guardada en `ruta`
+    /// (CSV o JSON)
+    pub fn comparar_con_archivo(&self, ruta: &str, modo: ModoCarga) -> Result<Comparacion, InventarioError> {
+        let (guardado, _) = cargar_datos(ruta, modo)?;
+        Ok(comparar(&guardado.productos, &self.datos().productos))
+    }
+    
+    /// Cargar inventario desde archivo (CSV o JSON según la extensión),
+    /// devolviendo las líneas rechazadas o corregidas
+    pub fn cargar_inventario(&mut self, ruta: &str, modo: ModoCarga) -> Result<ReporteCarga, InventarioError> {
+        // Cargar productos desde archivo antes de tocar el estado actual
+        let (mut datos, reporte) = cargar_datos(ruta, modo)?;
+        datos.siguiente_id = datos.productos.iter()
+            .map(|p| p.id + 1)
+            .fold(datos.siguiente_id.max(1), u32::max);
+        
+        // Los movimientos de productos rechazados no tienen a qué asociarse
+        let ids: HashSet<u32> = datos.productos.iter().map(|p| p.id).collect();
+        datos.movimientos.retain(|m| ids.contains(&m.producto_id));
+        
+        // Las ubicaciones con stock quedan dadas de alta aunque falten en la lista
+        let ubicaciones: BTreeSet<String> = datos.ubicaciones.drain(..)
+            .chain(datos.productos.iter().flat_map(|p| p.existencias.keys().cloned()))
+            .collect();
+        datos.ubicaciones = ubicaciones.into_iter().collect();
+
+        // Reemplazar también el contenido del almacenamiento conectado
+        self.persistir(|s| s.guardar_todo(&datos))?;
+
+        let foto = self.foto(self.productos_por_id.keys().copied().chain(ids));
+        self.reconstruir(datos);
+        self.historial.limpiar();
+        self.notificar(foto);
+        
+        Ok(reporte)
+    }
+    
+    /// Aplicar un plan de importación (ver `importacion::planificar`) en un
+    /// solo paso. Devuelve los IDs de los productos nuevos, en el orden del
+    /// plan. Los cambios de stock quedan como ajustes y la importación
+    /// completa se deshace de una vez.
+    pub fn importar(&mut self, plan: &PlanImportacion) -> Result<Vec<u32>, InventarioError> {
+        // El plan se armó con el inventario de ese momento
+        let anteriores = plan.actualizar.iter().map(|a| &a.anterior).chain(&plan.eliminar);
+        for anterior in anteriores {
+            if self.productos_por_id.get(&anterior.id) != Some(anterior) {
+                return Err(InventarioError::Validacion(
+                    "el inventario cambió desde que se revisó la importación".to_string()
+                ));
+            }
+        }
+        
+        let ajuste = |id: u32, delta: i64| (delta != 0).then(|| {
+            Movimiento::nuevo(id, delta, MotivoMovimiento::Ajuste, Some("Importación".to_string()))
+        });
+        let mut despues: Vec<(u32, Option<EstadoProducto>)> = plan.eliminar.iter().map(|p| (p.id, None)).collect();
+        for Actualizacion { anterior, nuevo } in &plan.actualizar {
+            let mut movimientos = self.movimientos_de(anterior.id).to_vec();
+            movimientos.extend(ajuste(anterior.id, nuevo.stock as i64 - anterior.stock as i64));
+            despues.push((anterior.id, Some(EstadoProducto { producto: nuevo.clone(), movimientos })));
+        }
+        
+        let mut ids = Vec::new();
+        let mut siguiente_id = self.siguiente_id;
+        for producto in &plan.agregar {
+            let id = siguiente_id;
+            siguiente_id += 1;
+            let movimientos = ajuste(id, producto.stock as i64).into_iter().collect();
+            despues.push((id, Some(EstadoProducto { producto: Producto { id, ..producto.clone() }, movimientos })));
+            ids.push(id);
+        }
+        
+        let antes = despues.iter().map(|(id, _)| (*id, self.estado(*id))).collect();
+        let siguiente_anterior = std::mem::replace(&mut self.siguiente_id, siguiente_id);
+        self.restaurar(despues, None).inspect_err(|_| self.siguiente_id = siguiente_anterior)?;
+        self.registrar_grupo("Importar".to_string(), antes, None);
+        
+        Ok(ids)
+    }
+    
+    // Método privado para reemplazar todas las estructuras con `datos`
+    fn reconstruir(&mut self, datos: DatosInventario) {
+        // Limpiar estructuras
+        self.productos_por_id.clear();
+        self.productos_por_tag.clear();
+        self.productos_por_precio.clear();
+        self.productos_por_moneda.clear();
+        self.productos_bajo_stock.clear();
+        self.indice_nombres.limpiar();
+        self.ids_por_sku.clear();
+        self.ids_por_codigo_barras.clear();
+        self.variantes_por_padre.clear();
+        self.movimientos.clear();
+        self.configuracion = datos.configuracion;
+        self.ubicaciones = datos.ubicaciones.into_iter().collect();
+        self.proveedores = datos.proveedores.into_iter().map(|p| (p.id, p)).collect();
+        self.ordenes = datos.ordenes.into_iter().map(|o| (o.id, o)).collect();
+        self.siguiente_id = datos.siguiente_id.max(1);
+        // Un archivo sin contadores (o con uno atrasado) no puede repetir IDs
+        self.siguiente_proveedor_id = self.proveedores.keys().next_back()
+            .map_or(1, |id| id + 1)
+            .max(datos.siguiente_proveedor_id);
+        self.siguiente_orden_id = self.ordenes.keys().next_back()
+            .map_or(1, |id| id + 1)
+            .max(datos.siguiente_orden_id);
+        
+        for movimiento in datos.movimientos {
+            self.movimientos.entry(movimiento.producto_id).or_default().push(movimiento);
+        }
+        
+        // Repoblar estructuras
+        for producto in datos.productos {
+            // Actualizar el siguiente ID
+            if producto.id >= self.siguiente_id {
+                self.siguiente_id = producto.id + 1;
+            }
+            self.ubicaciones.extend(producto.existencias.keys().cloned());
+            
+            self.indexar(producto);
+        }
+    }
+    
+    // Método privado para añadir un producto a todas las estructuras
+    fn indexar(&mut self, producto: Producto) {
+        let id = producto.id;
+        
+        // Añadir a todas las estructuras
+        self.indexar_precio(producto.precio, id);
+        self.indice_nombres.insertar(id, &producto.nombre);
+        
+        // Si un archivo trae códigos repetidos se queda el primero
+        if let Some(sku) = &producto.sku {
+            self.ids_por_sku.entry(clave_sku(sku)).or_insert(id);
+        }
+        if let Some(codigo) = &producto.codigo_barras {
+            self.ids_por_codigo_barras.entry(clave_codigo_barras(codigo)).or_insert(id);
+        }
+        
+        // Indexar por tags
+        for tag in &producto.tags {
+            self.productos_por_tag
+                .entry(tag.clone())
+                .or_default()
+                .insert(id);
+        }
+        
+        let padre = producto.variante.as_ref().map(|v| v.padre_id);
+        if let Some(padre) = padre {
+            self.variantes_por_padre.entry(padre).or_default().insert(id);
+        }
+        self.productos_por_id.insert(id, producto);
+        
+        // Verificar stock bajo, también el del padre que ahora tiene variantes
+        self.actualizar_bajo_stock(id);
+        if let Some(padre) = padre {
+            self.actualizar_bajo_stock(padre);
+        }
+    }
+    
+    // Método privado para quitar un producto de todas las estructuras
+    fn desindexar(&mut self, id: u32) -> Option<Producto> {
+        let producto = self.productos_por_id.remove(&id)?;
+            
+        // Eliminar de productos_bajo_stock
+        self.productos_bajo_stock.remove(&id);
+        
+        // Eliminar de productos_por_tag
+        for tag in &producto.tags {
+            if let Some(ids) = self.productos_por_tag.get_mut(tag) {
+                ids.remove(&id);
+                
+                // Eliminar tag si ya no tiene productos
+                if ids.is_empty() {
+                    self.productos_por_tag.remove(tag);
+                }
+            }
+        }
+        
+        // Eliminar de los índices de precio, nombre y códigos
+        self.desindexar_precio(producto.precio, id);
+        self.indice_nombres.quitar(id);
+        if let Some(sku) = &producto.sku {
+            let clave = clave_sku(sku);
+            if self.ids_por_sku.get(&clave) == Some(&id) {
+                self.ids_por_sku.remove(&clave);
+            }
+        }
+        if let Some(codigo) = &producto.codigo_barras {
+            let clave = clave_codigo_barras(codigo);
+            if self.ids_por_codigo_barras.get(&clave) == Some(&id) {
+                self.ids_por_codigo_barras.remove(&clave);
+            }
+        }
+        
+        // Sin su última variante el padre vuelve a tener su propio stock
+        if let Some(padre) = producto.variante.as_ref().map(|v| v.padre_id) {
+            if let Some(ids) = self.variantes_por_padre.get_mut(&padre) {
+                ids.remove(&id);
+                if ids.is_empty() {
+                    self.variantes_por_padre.remove(&padre);
+                }
+            }
+            self.actualizar_bajo_stock(padre);
+        }
+        
+        Some(producto)
+    }
+
+    /// Eliminar un producto del inventario
+    pub fn eliminar_producto(&mut self, id: u32) -> Result<(), InventarioError> {
+        let producto = self.productos_por_id.get(&id)
+            .ok_or(InventarioError::NoEncontrado(id))?;
+        let variantes = self.variantes_de(id).len();
+        if variantes > 0 {
+            return Err(InventarioError::Validacion(format!(
+                "«{}» tiene {} variantes; hay que eliminarlas primero", producto.nombre, variantes
+            )));
+        }
+        
+        let antes = self.estado(id);
+        let padre = producto.variante.as_ref().map(|v| v.padre_id);
+        let foto = self.foto([id].into_iter().chain(padre));
+        self.persistir(|s| s.eliminar_producto(id))?;
+        self.desindexar(id);
+        self.movimientos.remove(&id);
+        
+        self.registrar_comando("Eliminar", id, antes);
+        self.notificar(foto);
+        
+        Ok(())
+    }
+    
+    /// Deshacer el último cambio. Devuelve su descripción, o `None` si no
+    /// había nada que deshacer.
+    pub fn deshacer(&mut self) -> Result<Option<String>, InventarioError> {
+        let Some(comando) = self.historial.siguiente_deshacer().cloned() else {
+            return Ok(None);
+        };
+        
+        match comando.configuracion {
+            Some(cambio) => self.aplicar_configuracion(cambio.antes)?,
+            None => {
+                let productos = comando.productos.into_iter().map(|c| (c.id, c.antes)).collect();
+                self.restaurar(productos, comando.compras.map(CambioCompras::invertido))?;
+            }
+        }
+        self.historial.marcar_deshecho();
+        
+        Ok(Some(comando.descripcion))
+    }
+    
+    /// Volver a aplicar el último cambio deshecho
+    pub fn rehacer(&mut self) -> Result<Option<String>, InventarioError> {
+        let Some(comando) = self.historial.siguiente_rehacer().cloned() else {
+            return Ok(None);
+        };
+        
+        match comando.configuracion {
+            Some(cambio) => self.aplicar_configuracion(cambio.despues)?,
+            None => {
+                let productos = comando.productos.into_iter().map(|c| (c.id, c.despues)).collect();
+                self.restaurar(productos, comando.compras)?;
+            }
+        }
+        self.historial.marcar_rehecho();
+        
+        Ok(Some(comando.descripcion))
+    }
+    
+    /// Descripción del cambio que se desharía, si hay alguno
+    pub fn descripcion_deshacer(&self) -> Option<&str> {
+        self.historial.siguiente_deshacer().map(|c| c.descripcion.as_str())
+    }
+    
+    /// Descripción del cambio que se reharía, si hay alguno
+    pub fn descripcion_rehacer(&self) -> Option<&str> {
+        self.historial.siguiente_rehacer().map(|c| c.descripcion.as_str())
+    }
+    
+    // Método privado para copiar el estado actual de un producto
+    fn estado(&self, id: u32) -> Option<EstadoProducto> {
+        self.productos_por_id.get(&id).map(|producto| EstadoProducto {
+            producto: producto.clone(),
+            movimientos: self.movimientos_de(id).to_vec(),
+        })
+    }
+    
+    // Método privado para apilar un cambio ya aplicado sobre `id`
+    fn registrar_comando(&mut self, accion: &str, id: u32, antes: Option<EstadoProducto>) {
+        let nombre = self.productos_por_id.get(&id).map(|p| p.nombre.as_str())
+            .or(antes.as_ref().map(|e| e.producto.nombre.as_str()))
+            .unwrap_or_default();
+        let descripcion = format!("{} «{}»", accion, nombre);
+        self.registrar_grupo(descripcion, vec![(id, antes)], None);
+    }
+    
+    // Método privado para apilar un cambio ya aplicado sobre varios
+    // productos, y sobre un proveedor o una orden si se pasa `compras`;
+    // se deshace de una vez
+    fn registrar_grupo(&mut self, descripcion: String, antes: Vec<(u32, Option<EstadoProducto>)>, compras: Option<CambioCompras>) {
+        let productos = antes.into_iter()
+            .map(|(id, antes)| CambioProducto { id, antes, despues: self.estado(id) })
+            .collect();
+        
+        self.historial.registrar(Comando { descripcion, productos, compras, configuracion: None });
+    }
+    
+    // Método privado para apilar un cambio ya aplicado en los puntos de
+    // reorden. Arrastrar un valor en la interfaz lo cambia en cada cuadro,
+    // así que los cambios seguidos del mismo punto se juntan en uno solo.
+    fn registrar_configuracion(&mut self, descripcion: String, antes: ConfiguracionStock) {
+        let seguido = self.historial.siguiente_rehacer().is_none();
+        let antes = match self.historial.siguiente_deshacer() {
+            Some(Comando { descripcion: anterior, configuracion: Some(cambio), .. }) if seguido && *anterior == descripcion => {
+                let antes = cambio.antes.clone();
+                self.historial.descartar_ultimo();
+                antes
+            }
+            _ => antes,
+        };
+        let configuracion = CambioConfiguracion { antes, despues: self.configuracion.clone() };
+        
+        self.historial.registrar(Comando { descripcion, productos: Vec::new(), compras: None, configuracion: Some(configuracion) });
+    }
+    
+    // Método privado para dejar productos en un estado dado y aplicar
+    // `compras`, todo en un solo paso; se usa al deshacer, rehacer e importar
+    fn restaurar(&mut self, estados: Vec<(u32, Option<EstadoProducto>)>, compras: Option<CambioCompras>) -> Result<(), InventarioError> {
+        let compras = match compras {
+            Some(cambio) => {
+                let (mut proveedores, mut ordenes) = (self.proveedores.clone(), self.ordenes.clone());
+                aplicar_cambio_compras(cambio, &mut proveedores, &mut ordenes)?;
+                Some((proveedores, ordenes))
+            }
+            None => None,
+        };
+        let foto = self.foto(estados.iter().map(|(id, _)| *id));
+        let siguiente_id = self.siguiente_id;
+        let restauraciones: Vec<Restauracion> = estados.iter()
+            .map(|(id, estado)| Restauracion {
+                id: *id,
+                producto: estado.as_ref().map(|e| &e.producto),
+                movimientos: estado.as_ref().map_or(&[], |e| e.movimientos.as_slice()),
+            })
+            .collect();
+        let listas = compras.as_ref().map(|(proveedores, ordenes)| {
+            let proveedores: Vec<Proveedor> = proveedores.values().cloned().collect();
+            let ordenes: Vec<OrdenCompra> = ordenes.values().cloned().collect();
+            (proveedores, ordenes)
+        });
+        let listas = listas.as_ref().map(|(p, o)| (p.as_slice(), o.as_slice()));
+        
+        // El stock restaurado puede estar en una ubicación que se quitó
+        // después (o que trae una importación): volver a darla de alta
+        let faltantes: Vec<&String> = estados.iter()
+            .flat_map(|(_, estado)| estado.iter().flat_map(|e| e.producto.existencias.keys()))
+            .filter(|nombre| !self.ubicaciones.contains(*nombre))
+            .collect();
+        if !faltantes.is_empty() {
+            let mut ubicaciones = self.ubicaciones.clone();
+            ubicaciones.extend(faltantes.into_iter().cloned());
+            self.aplicar_ubicaciones(ubicaciones)?;
+        }
+        self.persistir(|s| s.restaurar_productos(&restauraciones, listas, siguiente_id))?;
+        
+        // Quitar todos antes de volver a indexar, por si intercambiaron códigos
+        for (id, _) in &estados {
+            self.desindexar(*id);
+            self.movimientos.remove(id);
+        }
+        for (id, estado) in estados {
+            if let Some(estado) = estado {
+                self.movimientos.insert(id, estado.movimientos);
+                self.indexar(estado.producto);
+            }
+        }
+        if let Some((proveedores, ordenes)) = compras {
+            self.proveedores = proveedores;
+            self.ordenes = ordenes;
+        }
+        self.notificar(foto);
+        
+        Ok(())
+    }
+    
+    // Método privado para anotar el stock y el nombre de `ids` antes de un cambio;
+    // sin suscriptores no se anota nada
+    fn foto(&self, ids: impl IntoIterator<Item = u32>) -> Foto {
+        if !self.observadores.activos() {
+            return Foto::new();
+        }
+        ids.into_iter()
+            .map(|id| (id, self.productos_por_id.get(&id).map(|p| (p.stock, self.tiene_bajo_stock(id), p.nombre.clone()))))
+            .collect()
+    }
+    
+    // Método privado para avisar a los suscriptores lo que cambió desde `foto`
+    fn notificar(&mut self, foto: Foto) {
+        if foto.is_empty() {
+            return;
+        }
+        let despues = self.foto(foto.keys().copied());
+        self.observadores.emitir(diferencias(&foto, &despues));
+    }
+    
+    // Método privado para guardar un cambio en el almacenamiento, si hay uno
+    fn persistir<F>(&mut self, guardar: F) -> Result<(), InventarioError>
+    where
+        F: FnOnce(&mut dyn Storage) -> Result<(), InventarioError>,
+    {
+        match self.almacenamiento.as_mut() {
+            Some(almacenamiento) => guardar(almacenamiento.as_mut()),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Aplicar un cambio de compras guardado para deshacer, si los
+/// proveedores o las órdenes todavía están como el cambio espera
+fn aplicar_cambio_compras(
+    cambio: CambioCompras,
+    proveedores: &mut BTreeMap<u32, Proveedor>,
+    ordenes: &mut BTreeMap<u32, OrdenCompra>,
+) -> Result<(), InventarioError> {
+    let coincide = match cambio {
+        CambioCompras::Proveedor { id, antes, despues } => reemplazar(proveedores, id, antes, despues),
+        CambioCompras::Orden { id, antes, despues } => reemplazar(ordenes, id, antes, despues),
+    };
+    if !coincide {
+        return Err(InventarioError::Validacion(
+            "los proveedores o las órdenes cambiaron desde entonces".to_string()
+        ));
+    }
+    Ok(())
+}
+
+/// Poner `despues` en la entrada `id` de `mapa` si ahora tiene `antes`;
+/// devuelve si se pudo
+fn reemplazar<T: Clone + PartialEq>(mapa: &mut BTreeMap<u32, T>, id: u32, antes: Option<T>, despues: Option<T>) -> bool {
+    if mapa.get(&id) != antes.as_ref() {
+        return false;
+    }
+    match despues {
+        Some(valor) => mapa.insert(id, valor),
+        None => mapa.remove(&id),
+    };
+    true
+}
+
+/// Comprobar que el nombre de un producto no esté vacío
+fn validar_nombre(nombre: &str) -> Result<(), InventarioError> {
+    if nombre.trim().is_empty() {
+        return Err(InventarioError::Validacion("el nombre no puede estar vacío".to_string()));
+    }
+
+    Ok(())
+}
+
+/// Comprobar que un costo esté en la moneda del precio del producto
+fn validar_moneda_costo(producto: &Producto, costo: Dinero) -> Result<(), InventarioError> {
+    if costo.moneda != producto.precio.moneda {
+        return Err(InventarioError::Validacion(format!(
+            "el costo debe estar en {}, la moneda del precio", producto.precio.moneda
+        )));
+    }
+    Ok(())
+}
+
+/// Comprobar que el stock total alcance para lo que hay en las ubicaciones
+fn validar_stock_total(producto: &Producto, stock: u32) -> Result<(), InventarioError> {
+    let ubicado: u32 = producto.existencias.values().sum();
+    if stock < ubicado {
+        return Err(InventarioError::Validacion(format!(
+            "el stock total ({}) no puede ser menor que lo asignado a ubicaciones ({})", stock, ubicado
+        )));
+    }
+
+    Ok(())
+}
+
+/// Fijar las unidades de un producto en una ubicación, sin dejar entradas en cero
+fn asignar_existencia(producto: &mut Producto, ubicacion: &str, cantidad: u32) {
+    if cantidad == 0 {
+        producto.existencias.remove(ubicacion);
+    } else {
+        producto.existencias.insert(ubicacion.to_string(), cantidad);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tags(tags: &[&str]) -> Vec<String> {
+        tags.iter().map(|t| t.to_string()).collect()
+    }
+
+    fn usd(unidades: u64) -> Dinero {
+        Dinero::desde_unidades(unidades, Moneda::POR_DEFECTO)
+    }
+
+    #[test]
+    fn deshacer_y_rehacer_restauran_productos_e_indices() {
+        let mut inventario = InventarioManager::new();
+        let id = inventario.agregar_producto("Teclado".to_string(), usd(120), 10, tags(&["periféricos"]), None).unwrap();
+        inventario.registrar_movimiento(id, -8, MotivoMovimiento::Venta, None).unwrap();
+        inventario.eliminar_producto(id).unwrap();
+        assert!(inventario.obtener_todos_productos().is_empty());
+
+        // Deshacer la eliminación devuelve el producto con su historial
+        assert_eq!(inventario.deshacer().unwrap().as_deref(), Some("Eliminar «Teclado»"));
+        assert_eq!(inventario.buscar_por_tag("periféricos").len(), 1);
+        assert_eq!(inventario.movimientos_de(id).len(), 2);
+        assert_eq!(inventario.productos_con_bajo_stock().len(), 1);
+
+        // Deshacer la venta vuelve al stock anterior
+        inventario.deshacer().unwrap();
+        assert_eq!(inventario.obtener_todos_productos()[0].stock, 10);
+        assert_eq!(inventario.movimientos_de(id).len(), 1);
+        assert!(inventario.productos_con_bajo_stock().is_empty());
+
+        // Rehacer ambos cambios
+        inventario.rehacer().unwrap();
+        inventario.rehacer().unwrap();
+        assert!(inventario.obtener_todos_productos().is_empty());
+        assert!(inventario.buscar_por_tag("periféricos").is_empty());
+        assert_eq!(inventario.rehacer().unwrap(), None);
+
+        // Un cambio nuevo descarta lo que se podía rehacer
+        inventario.deshacer().unwrap();
+        inventario.actualizar_producto(id, "Teclado RGB".to_string(), usd(150), 2, tags(&["gaming"]), None).unwrap();
+        assert_eq!(inventario.descripcion_rehacer(), None);
+        assert!(inventario.buscar_por_tag("periféricos").is_empty());
+        assert_eq!(inventario.buscar_por_tag("gaming").len(), 1);
+        assert_eq!(inventario.buscar_por_nombre("rgb").len(), 1);
+    }
+
+    #[test]
+    fn consultas_de_tags_combinan_conjuntos() {
+        let mut inventario = InventarioManager::new();
+        inventario.agregar_producto("Mouse".to_string(), usd(25), 10, tags(&["periféricos", "oferta"]), None).unwrap();
+        inventario.agregar_producto("Teléfono".to_string(), usd(800), 10, tags(&["electrónica", "móviles"]), None).unwrap();
+        inventario.agregar_producto("Cafetera".to_string(), usd(90), 10, tags(&["hogar", "oferta"]), None).unwrap();
+        inventario.agregar_producto("Gamepad".to_string(), usd(60), 10, tags(&["periféricos", "móviles"]), None).unwrap();
+        let nombres = |consulta: &str| -> Vec<String> {
+            inventario.buscar_por_tags(consulta).unwrap().iter().map(|p| p.nombre.clone()).collect()
+        };
+
+        assert_eq!(nombres("periféricos AND NOT móviles"), ["Mouse"]);
+        assert_eq!(nombres("(electrónica OR hogar) AND oferta"), ["Cafetera"]);
+        assert_eq!(nombres("NOT oferta"), ["Teléfono", "Gamepad"]);
+        assert_eq!(nombres("inexistente OR hogar"), ["Cafetera"]);
+        assert!(inventario.buscar_por_tags("hogar AND").is_err());
+    }
+
+    #[test]
+    fn bajo_stock_usa_el_punto_de_reorden_del_producto_tag_o_global() {
+        let mut inventario = InventarioManager::new();
+        let cables = inventario.agregar_producto("Cable".to_string(), usd(5), 20, tags(&["cables"]), None).unwrap();
+        let laptop = inventario.agregar_producto("Laptop".to_string(), usd(900), 3, tags(&["cables"]), Some(2)).unwrap();
+        let mouse = inventario.agregar_producto("Mouse".to_string(), usd(25), 6, tags(&[]), None).unwrap();
+        assert!(!inventario.tiene_bajo_stock(cables));
+        assert!(!inventario.tiene_bajo_stock(laptop));
+        assert!(!inventario.tiene_bajo_stock(mouse));
+
+        // El tag afecta a los productos sin punto propio
+        inventario.establecer_punto_reorden_tag("cables", Some(25)).unwrap();
+        assert!(inventario.tiene_bajo_stock(cables));
+        assert!(!inventario.tiene_bajo_stock(laptop));
+        assert_eq!(inventario.punto_reorden_de(cables), Some(25));
+
+        // El global solo a los que no heredan de nada
+        inventario.establecer_punto_reorden_global(6).unwrap();
+        assert!(inventario.tiene_bajo_stock(mouse));
+        inventario.actualizar_stock(mouse, 7, MotivoMovimiento::Compra, None).unwrap();
+        assert!(!inventario.tiene_bajo_stock(mouse));
+
+        inventario.establecer_punto_reorden_tag("cables", None).unwrap();
+        assert!(!inventario.tiene_bajo_stock(cables));
+        assert_eq!(inventario.productos_con_bajo_stock().len(), 0);
+
+        // Deshacer devuelve el punto del tag; los cambios seguidos del
+        // global se deshacen juntos
+        assert_eq!(inventario.deshacer().unwrap().as_deref(), Some("Punto de reorden de «cables»"));
+        assert!(inventario.tiene_bajo_stock(cables));
+        let global = inventario.configuracion_stock().punto_reorden_global;
+        inventario.establecer_punto_reorden_global(40).unwrap();
+        inventario.establecer_punto_reorden_global(41).unwrap();
+        assert!(inventario.tiene_bajo_stock(mouse));
+        assert_eq!(inventario.deshacer().unwrap().as_deref(), Some("Punto de reorden global"));
+        assert_eq!(inventario.configuracion_stock().punto_reorden_global, global);
+        assert!(!inventario.tiene_bajo_stock(mouse));
+        inventario.rehacer().unwrap();
+        assert_eq!(inventario.configuracion_stock().punto_reorden_global, 41);
+    }
+
+    #[test]
+    fn indice_de_precios_sigue_los_cambios() {
+        let mut inventario = InventarioManager::new();
+        for (nombre, precio) in [("A", 50), ("B", 10), ("C", 30), ("D", 30), ("E", 90)] {
+            inventario.agregar_producto(nombre.to_string(), usd(precio), 10, tags(&[]), None).unwrap();
+        }
+        let nombres = |productos: Vec<&Producto>| -> Vec<String> {
+            productos.iter().map(|p| p.nombre.clone()).collect()
+        };
+
+        assert_eq!(nombres(inventario.productos_en_rango(usd(20), usd(50))), ["C", "D", "A"]);
+        assert_eq!(nombres(inventario.productos_mas_caros(2)), ["E", "A"]);
+        assert_eq!(nombres(inventario.productos_mas_baratos(2)), ["B", "C"]);
+        assert!(inventario.productos_en_rango(usd(60), usd(20)).is_empty());
+        assert_eq!(inventario.moneda_principal(), Moneda::POR_DEFECTO);
+
+        // Los precios en otra moneda no entran en los rangos en dólares
+        let euro = Moneda::parsear("EUR").unwrap();
+        let f = inventario.agregar_producto("F".to_string(), Dinero::parsear("30,50", euro).unwrap(), 10, tags(&[]), None).unwrap();
+        assert_eq!(nombres(inventario.productos_en_rango(usd(20), usd(50))), ["C", "D", "A"]);
+        assert_eq!(nombres(inventario.productos_en_rango(Dinero::new(0, euro), Dinero::new(5_000, euro))), ["F"]);
+        assert!(inventario.productos_en_rango(usd(0), Dinero::new(5_000, euro)).is_empty());
+        inventario.eliminar_producto(f).unwrap();
+
+        // Paginar el rango completo de a dos
+        let primera = inventario.pagina_en_rango(usd(0), usd(u64::MAX), None, 2);
+        assert_eq!(nombres(primera.clone()), ["B", "C"]);
+        let ultimo = primera.last().map(|p| (p.precio, p.id));
+        let segunda = inventario.pagina_en_rango(usd(0), usd(u64::MAX), ultimo, 2);
+        assert_eq!(nombres(segunda.clone()), ["D", "A"]);
+        let ultimo = segunda.last().map(|p| (p.precio, p.id));
+        assert_eq!(nombres(inventario.pagina_en_rango(usd(0), usd(u64::MAX), ultimo, 2)), ["E"]);
+        assert!(inventario.pagina_en_rango(usd(0), usd(20), ultimo, 2).is_empty());
+        assert_eq!(inventario.cantidad_en_rango(usd(0), usd(u64::MAX)), 5);
+
+        // Cambiar el precio reubica el producto; eliminarlo lo quita
+        inventario.actualizar_producto(2, "B".to_string(), usd(100), 10, tags(&[]), None).unwrap();
+        inventario.eliminar_producto(5).unwrap();
+        assert_eq!(nombres(inventario.productos_mas_caros(1)), ["B"]);
+        assert_eq!(nombres(inventario.productos_en_rango(usd(0), usd(99))), ["C", "D", "A"]);
+    }
+
+    #[test]
+    fn transferencias_mueven_stock_entre_ubicaciones() {
+        let mut inventario = InventarioManager::new();
+        let id = inventario.agregar_producto("Silla".to_string(), usd(40), 10, tags(&[]), None).unwrap();
+        inventario.agregar_ubicacion("Bodega").unwrap();
+        inventario.agregar_ubicacion("Tienda").unwrap();
+        assert!(inventario.agregar_ubicacion(" Bodega ").is_err());
+
+        // Del stock sin ubicación a la bodega, y de ahí a la tienda
+        inventario.transferir(id, None, Some("Bodega"), 8, None).unwrap();
+        inventario.transferir(id, Some("Bodega"), Some("Tienda"), 3, None).unwrap();
+        let silla = inventario.obtener_todos_productos()[0].clone();
+        assert_eq!(silla.stock_en(Some("Bodega")), 5);
+        assert_eq!(silla.stock_en(Some("Tienda")), 3);
+        assert_eq!(silla.stock_en(None), 2);
+        assert_eq!(silla.stock, 10);
+        assert!(inventario.transferir(id, Some("Tienda"), None, 4, None).is_err());
+        assert!(inventario.transferir(id, Some("Garage"), None, 1, None).is_err());
+
+        // Una venta en la tienda baja el total y la ubicación
+        assert_eq!(inventario.registrar_movimiento_en(id, Some("Tienda"), -3, MotivoMovimiento::Venta, None).unwrap(), 0);
+        assert_eq!(inventario.obtener_todos_productos()[0].stock, 7);
+        assert_eq!(inventario.totales_por_ubicacion()["Tienda"], 0);
+        assert_eq!(inventario.stock_sin_ubicacion(), 2);
+
+        // El total no puede quedar por debajo de lo ubicado
+        assert!(inventario.actualizar_stock(id, 4, MotivoMovimiento::Ajuste, None).is_err());
+        assert!(inventario.eliminar_ubicacion("Bodega").is_err());
+        inventario.eliminar_ubicacion("Tienda").unwrap();
+
+        // Deshacer la venta devuelve stock a la tienda, que vuelve a estar
+        // dada de alta, y después la última transferencia
+        inventario.deshacer().unwrap();
+        assert_eq!(inventario.ubicaciones(), ["Bodega", "Tienda"]);
+        assert_eq!(inventario.totales_por_ubicacion()["Tienda"], 3);
+        inventario.deshacer().unwrap();
+        assert_eq!(inventario.obtener_todos_productos()[0].stock_en(Some("Bodega")), 8);
+        let transferencias = inventario.movimientos_de(id).iter()
+            .filter(|m| m.motivo == MotivoMovimiento::Transferencia)
+            .count();
+        assert_eq!(transferencias, 2);
+    }
+
+    #[test]
+    fn variantes_siguen_al_padre_y_suman_su_stock() {
+        let mut inventario = InventarioManager::new();
+        let camisa = inventario.agregar_producto("Camisa".to_string(), usd(20), 0, tags(&["ropa"]), None).unwrap();
+        assert!(inventario.tiene_bajo_stock(camisa));
+
+        let talla = |t: &str| BTreeMap::from([("talla".to_string(), t.to_string())]);
+        let s = inventario.agregar_variante(camisa, talla("S"), 2, None).unwrap();
+        let xl = inventario.agregar_variante(camisa, talla("XL"), 10, Some(usd(25))).unwrap();
+        assert!(inventario.agregar_variante(camisa, talla("S"), 1, None).is_err());
+        assert!(inventario.agregar_variante(s, talla("M"), 1, None).is_err());
+        let con_igual = BTreeMap::from([("talla=EU".to_string(), "38".to_string())]);
+        assert!(inventario.agregar_variante(camisa, con_igual, 1, None).is_err());
+        assert!(inventario.eliminar_producto(camisa).is_err());
+
+        // El padre no cuenta como bajo stock; cada variante sí
+        assert_eq!(inventario.stock_agregado(camisa), 12);
+        assert!(!inventario.tiene_bajo_stock(camisa));
+        assert!(inventario.tiene_bajo_stock(s));
+        assert_eq!(inventario.obtener_producto(s).unwrap().nombre_completo(), "Camisa (talla: S)");
+
+        // Al cambiar el padre cambian el nombre, los tags y los precios que no son propios
+        inventario.actualizar_producto(camisa, "Camisa lino".to_string(), usd(22), 0, tags(&["verano"]), None).unwrap();
+        let variante_s = inventario.obtener_producto(s).unwrap();
+        assert_eq!((variante_s.nombre.as_str(), variante_s.precio), ("Camisa lino", usd(22)));
+        assert_eq!(inventario.buscar_por_tag("verano").len(), 3);
+        assert_eq!(inventario.obtener_producto(xl).unwrap().precio, usd(25));
+
+        // Y se deshace de una vez, sin perder lo anterior
+        assert_eq!(inventario.deshacer().unwrap().as_deref(), Some("Editar «Camisa lino»"));
+        assert_eq!(inventario.obtener_producto(s).unwrap().nombre, "Camisa");
+        assert_eq!(inventario.buscar_por_tag("ropa").len(), 3);
+        assert!(inventario.buscar_por_tag("verano").is_empty());
+        assert_eq!(inventario.descripcion_deshacer(), Some("Agregar «Camisa»"));
+        inventario.rehacer().unwrap();
+
+        // Editar la variante no cambia su nombre, y un precio igual al del padre deja de ser propio
+        inventario.actualizar_producto(xl, "Otra".to_string(), usd(22), 10, tags(&[]), None).unwrap();
+        let variante_xl = inventario.obtener_producto(xl).unwrap();
+        assert_eq!(variante_xl.nombre, "Camisa lino");
+        assert!(!variante_xl.variante.as_ref().unwrap().precio_propio);
+        let euros = Dinero::new(2_200, Moneda::parsear("EUR").unwrap());
+        assert!(inventario.actualizar_producto(xl, "Camisa lino".to_string(), euros, 10, tags(&[]), None).is_err());
+
+        // Sin variantes el padre vuelve a tener su propio stock
+        inventario.eliminar_producto(s).unwrap();
+        inventario.eliminar_producto(xl).unwrap();
+        assert!(inventario.variantes_de(camisa).is_empty());
+        assert!(inventario.tiene_bajo_stock(camisa));
+    }
+
+    #[test]
+    fn codigos_son_unicos_y_se_buscan_por_sku_o_barras() {
+        let mut inventario = InventarioManager::new();
+        let teclado = inventario.agregar_producto("Teclado".to_string(), usd(120), 5, tags(&[]), None).unwrap();
+        let mouse = inventario.agregar_producto("Mouse".to_string(), usd(25), 5, tags(&[]), None).unwrap();
+        inventario.establecer_codigos(teclado, Some("TEC-001"), Some("036000291452")).unwrap();
+
+        // Repetidos (sin importar mayúsculas ni UPC-A/EAN-13) o inválidos
+        assert!(inventario.establecer_codigos(mouse, Some("tec-001"), None).is_err());
+        assert!(inventario.establecer_codigos(mouse, None, Some("0036000291452")).is_err());
+        assert!(inventario.establecer_codigos(mouse, None, Some("4006381333932")).is_err());
+        inventario.establecer_codigos(mouse, Some("MOU-001"), Some("4006381333931")).unwrap();
+
+        let id_de = |codigo: &str| inventario.buscar_por_codigo(codigo).map(|p| p.id);
+        assert_eq!(id_de("0036000291452"), Some(teclado));
+        assert_eq!(id_de(" tec-001 "), Some(teclado));
+        assert_eq!(id_de("4006381333931"), Some(mouse));
+        assert_eq!(id_de("XYZ"), None);
+
+        // Editar el producto conserva los códigos; deshacer y eliminar los liberan
+        inventario.actualizar_producto(teclado, "Teclado RGB".to_string(), usd(150), 5, tags(&[]), None).unwrap();
+        assert_eq!(inventario.buscar_por_codigo("TEC-001").map(|p| p.nombre.as_str()), Some("Teclado RGB"));
+        inventario.deshacer().unwrap();
+        inventario.deshacer().unwrap();
+        assert_eq!(inventario.buscar_por_codigo("MOU-001"), None);
+        inventario.eliminar_producto(teclado).unwrap();
+        inventario.establecer_codigos(mouse, Some("TEC-001"), None).unwrap();
+    }
+}
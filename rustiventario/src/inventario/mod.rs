@@ -0,0 +1,18 @@
+// aqui se maneja lo que usaremos para el inventario 
+
+mod busqueda;
+pub mod compras;
+pub mod comparacion;
+pub mod consulta;
+mod deshacer;
+pub mod error;
+pub mod eventos;
+pub mod importacion;
+pub mod manager;
+pub mod reportes;
+pub mod ventas;
+
+pub use consulta::ConsultaTags;
+pub use error::InventarioError;
+pub use eventos::EventoInventario;
+pub use manager::InventarioManager;
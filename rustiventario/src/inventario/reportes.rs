@@ -0,0 +1,266 @@
+// Reportes de valuación: valor del inventario a costo y a precio de venta,
+// y margen bruto por producto y por tag
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::inventario::InventarioManager;
+use crate::models::{Dinero, Moneda, MotivoMovimiento, Producto};
+
+/// Cómo se calcula el costo de las unidades en stock
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetodoValuacion {
+    /// Todas las unidades al costo actual del producto
+    CostoActual,
+    /// PEPS: lo que queda en stock son las compras más recientes
+    Fifo,
+    /// Costo promedio de todas las compras, ponderado por cantidad
+    PromedioPonderado,
+}
+
+impl MetodoValuacion {
+    pub const TODOS: [MetodoValuacion; 3] = [
+        MetodoValuacion::CostoActual,
+        MetodoValuacion::Fifo,
+        MetodoValuacion::PromedioPonderado,
+    ];
+}
+
+impl fmt::Display for MetodoValuacion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let texto = match self {
+            MetodoValuacion::CostoActual => "Costo actual",
+            MetodoValuacion::Fifo => "FIFO (PEPS)",
+            MetodoValuacion::PromedioPonderado => "Promedio ponderado",
+        };
+        write!(f, "{}", texto)
+    }
+}
+
+/// Valuación de un producto
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValuacionProducto {
+    pub id: u32,
+    pub nombre: String,
+    pub stock: u32,
+    pub precio: Dinero,
+    /// Costo de una unidad según el método; `None` si el producto no tiene
+    /// costo ni compras con costo
+    pub costo_unitario: Option<Dinero>,
+    /// Costo de todo el stock
+    pub valor_costo: Option<Dinero>,
+    /// Precio de venta de todo el stock
+    pub valor_venta: Dinero,
+}
+
+impl ValuacionProducto {
+    /// Margen bruto, en porcentaje del precio
+    pub fn margen(&self) -> Option<f64> {
+        margen(self.precio.centavos, self.costo_unitario?.centavos)
+    }
+}
+
+/// Sumas de un grupo de productos (un tag o todo el inventario)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Totales {
+    pub productos: usize,
+    pub unidades: u64,
+    pub valor_costo: Dinero,
+    pub valor_venta: Dinero,
+    /// Valor de venta de las unidades con costo conocido, la base del margen
+    pub valor_venta_con_costo: Dinero,
+    pub unidades_sin_costo: u64,
+}
+
+impl Totales {
+    fn new(moneda: Moneda) -> Self {
+        Self {
+            productos: 0,
+            unidades: 0,
+            valor_costo: Dinero::new(0, moneda),
+            valor_venta: Dinero::new(0, moneda),
+            valor_venta_con_costo: Dinero::new(0, moneda),
+            unidades_sin_costo: 0,
+        }
+    }
+
+    /// Margen bruto de las unidades con costo conocido, en porcentaje
+    pub fn margen(&self) -> Option<f64> {
+        margen(self.valor_venta_con_costo.centavos, self.valor_costo.centavos)
+    }
+
+    // Método privado para sumar un producto al grupo
+    fn agregar(&mut self, producto: &ValuacionProducto) {
+        self.productos += 1;
+        self.unidades += u64::from(producto.stock);
+        sumar(&mut self.valor_venta, producto.valor_venta);
+        match producto.valor_costo {
+            Some(valor_costo) => {
+                sumar(&mut self.valor_costo, valor_costo);
+                sumar(&mut self.valor_venta_con_costo, producto.valor_venta);
+            }
+            None => self.unidades_sin_costo += u64::from(producto.stock),
+        }
+    }
+}
+
+/// Valuación del inventario en una moneda
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReporteValuacion {
+    pub moneda: Moneda,
+    pub metodo: MetodoValuacion,
+    /// Ordenados por ID
+    pub productos: Vec<ValuacionProducto>,
+    pub tags: BTreeMap<String, Totales>,
+    pub total: Totales,
+    /// Productos con precio en otra moneda, que no entran en el reporte
+    pub omitidos: usize,
+}
+
+/// Valuar los productos con precio en `moneda`. Las compras que no
+/// indicaron su costo no cuentan.
+pub fn valuar(inventario: &InventarioManager, moneda: Moneda, metodo: MetodoValuacion) -> ReporteValuacion {
+    let mut productos: Vec<&Producto> = inventario.obtener_todos_productos();
+    productos.sort_by_key(|p| p.id);
+
+    let mut reporte = ReporteValuacion {
+        moneda,
+        metodo,
+        productos: Vec::new(),
+        tags: BTreeMap::new(),
+        total: Totales::new(moneda),
+        omitidos: 0,
+    };
+
+    for producto in productos {
+        if producto.precio.moneda != moneda {
+            reporte.omitidos += 1;
+            continue;
+        }
+
+        // Compras con costo, de la más antigua a la más reciente
+        let compras: Vec<(u64, u64)> = inventario.movimientos_de(producto.id).iter()
+            .filter(|m| m.motivo == MotivoMovimiento::Compra && m.delta > 0)
+            .filter_map(|m| m.costo_unitario.filter(|c| c.moneda == moneda).map(|c| (m.delta as u64, c.centavos)))
+            .collect();
+        let costo_actual = producto.costo.filter(|c| c.moneda == moneda).map(|c| c.centavos);
+
+        // Con stock en cero el costo unitario es el de la próxima unidad
+        let unidades = u64::from(producto.stock.max(1));
+        let costo_unitario = costo_de(metodo, &compras, costo_actual, unidades)
+            .map(|total| Dinero::new(dividir(u128::from(total), u128::from(unidades)), moneda));
+        let valor_costo = costo_de(metodo, &compras, costo_actual, u64::from(producto.stock))
+            .map(|total| Dinero::new(total, moneda));
+
+        let valuacion = ValuacionProducto {
+            id: producto.id,
+            nombre: producto.nombre_completo(),
+            stock: producto.stock,
+            precio: producto.precio,
+            costo_unitario,
+            valor_costo,
+            valor_venta: Dinero::new(producto.precio.centavos.saturating_mul(u64::from(producto.stock)), moneda),
+        };
+
+        reporte.total.agregar(&valuacion);
+        for tag in &producto.tags {
+            reporte.tags.entry(tag.clone()).or_insert_with(|| Totales::new(moneda)).agregar(&valuacion);
+        }
+        reporte.productos.push(valuacion);
+    }
+
+    reporte
+}
+
+// Costo total de `unidades` según el método, en centavos. Lo que no
+// alcanzan a cubrir las compras se valúa al costo actual o, si no hay,
+// al de la compra más antigua.
+fn costo_de(metodo: MetodoValuacion, compras: &[(u64, u64)], costo_actual: Option<u64>, unidades: u64) -> Option<u64> {
+    let ultima = compras.last().map(|(_, costo)| *costo);
+    let primera = compras.first().map(|(_, costo)| *costo);
+
+    match metodo {
+        MetodoValuacion::CostoActual => costo_actual.or(ultima).map(|costo| costo.saturating_mul(unidades)),
+        MetodoValuacion::Fifo => {
+            let mut restantes = unidades;
+            let mut total: u64 = 0;
+            for (cantidad, costo) in compras.iter().rev() {
+                let tomadas = restantes.min(*cantidad);
+                total = total.saturating_add(tomadas.saturating_mul(*costo));
+                restantes -= tomadas;
+            }
+            if restantes == 0 {
+                return Some(total);
+            }
+            costo_actual.or(primera).map(|costo| total.saturating_add(restantes.saturating_mul(costo)))
+        }
+        MetodoValuacion::PromedioPonderado => {
+            let cantidad: u128 = compras.iter().map(|(cantidad, _)| u128::from(*cantidad)).sum();
+            if cantidad == 0 {
+                return costo_actual.map(|costo| costo.saturating_mul(unidades));
+            }
+            let costo: u128 = compras.iter().map(|(cantidad, costo)| u128::from(*cantidad) * u128::from(*costo)).sum();
+            Some(dividir(costo * u128::from(unidades), cantidad))
+        }
+    }
+}
+
+// División redondeada al centavo más cercano
+fn dividir(dividendo: u128, divisor: u128) -> u64 {
+    u64::try_from((dividendo + divisor / 2) / divisor).unwrap_or(u64::MAX)
+}
+
+fn sumar(total: &mut Dinero, monto: Dinero) {
+    total.centavos = total.centavos.saturating_add(monto.centavos);
+}
+
+fn margen(venta: u64, costo: u64) -> Option<f64> {
+    (venta > 0).then(|| (venta as f64 - costo as f64) / venta as f64 * 100.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn usd(texto: &str) -> Dinero {
+        Dinero::parsear(texto, Moneda::POR_DEFECTO).unwrap()
+    }
+
+    #[test]
+    fn valua_con_costo_actual_fifo_y_promedio() {
+        let mut inventario = InventarioManager::new();
+        let tags = |tags: &[&str]| tags.iter().map(|t| t.to_string()).collect::<Vec<_>>();
+        let cafe = inventario.agregar_producto("Café".to_string(), usd("10"), 0, tags(&["bebidas"]), None).unwrap();
+        let te = inventario.agregar_producto("Té".to_string(), usd("5"), 4, tags(&["bebidas"]), None).unwrap();
+        let euro = Moneda::parsear("EUR").unwrap();
+        inventario.agregar_producto("Galletas".to_string(), Dinero::new(300, euro), 1, tags(&[]), None).unwrap();
+
+        // 10 a $4, 10 a $6; se venden 12, así que quedan 8
+        inventario.registrar_compra(cafe, None, 10, usd("4"), None).unwrap();
+        inventario.registrar_compra(cafe, None, 10, usd("6"), None).unwrap();
+        inventario.registrar_movimiento(cafe, -12, MotivoMovimiento::Venta, None).unwrap();
+        assert!(inventario.registrar_compra(cafe, None, 1, Dinero::new(100, euro), None).is_err());
+
+        let valor_cafe = |metodo| valuar(&inventario, Moneda::POR_DEFECTO, metodo).productos[0].valor_costo;
+        assert_eq!(valor_cafe(MetodoValuacion::CostoActual), Some(usd("48")));
+        assert_eq!(valor_cafe(MetodoValuacion::Fifo), Some(usd("48")));
+        assert_eq!(valor_cafe(MetodoValuacion::PromedioPonderado), Some(usd("40")));
+
+        // Con más stock que compras, FIFO completa con el costo actual
+        inventario.registrar_movimiento(cafe, 14, MotivoMovimiento::Devolucion, None).unwrap();
+        inventario.establecer_costo(cafe, Some(usd("5"))).unwrap();
+        let reporte = valuar(&inventario, Moneda::POR_DEFECTO, MetodoValuacion::Fifo);
+        assert_eq!(reporte.productos[0].valor_costo, Some(usd("110")));
+        assert_eq!(reporte.omitidos, 1);
+
+        // El té no tiene costo: cuenta para la venta pero no para el margen
+        assert_eq!(reporte.productos[1].id, te);
+        assert_eq!(reporte.productos[1].margen(), None);
+        let bebidas = reporte.tags["bebidas"];
+        assert_eq!((bebidas.productos, bebidas.unidades_sin_costo), (2, 4));
+        assert_eq!(bebidas.valor_venta, usd("240"));
+        assert_eq!(bebidas.valor_costo, usd("110"));
+        assert_eq!(bebidas.margen(), Some(50.0));
+        assert_eq!(reporte.total, bebidas);
+    }
+}
@@ -0,0 +1,248 @@
+// Ventas en caja: carrito, impuestos y recibo
+
+use std::fmt;
+use chrono::{Local, TimeZone};
+
+use crate::inventario::{InventarioError, InventarioManager};
+use crate::models::{Dinero, FormatoMoneda, Moneda, Producto};
+
+/// Tasa de impuesto sobre el subtotal, en centésimas de punto porcentual
+/// (1600 = 16 %). Los precios se guardan sin impuestos.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TasaImpuesto(u32);
+
+impl TasaImpuesto {
+    /// Interpretar un porcentaje como `16`, `10.5` o `10,5`; vacío es cero
+    pub fn parsear(texto: &str) -> Result<Self, String> {
+        let texto = texto.trim().trim_end_matches('%').trim();
+        if texto.is_empty() {
+            return Ok(Self(0));
+        }
+
+        let invalida = || format!("tasa inválida: \"{}\"", texto);
+        let (enteros, decimales) = texto.split_once(['.', ',']).unwrap_or((texto, ""));
+        if decimales.len() > 2 || !decimales.chars().all(|c| c.is_ascii_digit()) {
+            return Err(invalida());
+        }
+        let enteros: u32 = enteros.parse().map_err(|_| invalida())?;
+        let decimales: u32 = format!("{:0<2}", decimales).parse().map_err(|_| invalida())?;
+
+        let tasa = enteros.checked_mul(100).and_then(|t| t.checked_add(decimales)).ok_or_else(invalida)?;
+        if tasa > 10_000 {
+            return Err("la tasa no puede pasar del 100 %".to_string());
+        }
+        Ok(Self(tasa))
+    }
+
+    /// Impuesto sobre `centavos`, redondeado al centavo más cercano
+    pub fn aplicar(&self, centavos: u64) -> u64 {
+        let impuesto = (u128::from(centavos) * u128::from(self.0) + 5_000) / 10_000;
+        u64::try_from(impuesto).unwrap_or(u64::MAX)
+    }
+}
+
+impl fmt::Display for TasaImpuesto {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (enteros, decimales) = (self.0 / 100, self.0 % 100);
+        if decimales == 0 {
+            write!(f, "{} %", enteros)
+        } else {
+            let decimales = format!("{:02}", decimales);
+            write!(f, "{}.{} %", enteros, decimales.trim_end_matches('0'))
+        }
+    }
+}
+
+/// Productos elegidos para vender, en el orden en que se agregaron
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Carrito {
+    /// Pares (producto, unidades)
+    pub lineas: Vec<(u32, u32)>,
+}
+
+impl Carrito {
+    /// Sumar unidades de un producto; si ya estaba se agregan a su línea
+    pub fn agregar(&mut self, producto_id: u32, cantidad: u32) {
+        match self.lineas.iter_mut().find(|(id, _)| *id == producto_id) {
+            Some((_, actual)) => *actual = actual.saturating_add(cantidad),
+            None => self.lineas.push((producto_id, cantidad)),
+        }
+    }
+
+    pub fn quitar(&mut self, producto_id: u32) {
+        self.lineas.retain(|(id, _)| *id != producto_id);
+    }
+
+    pub fn vaciar(&mut self) {
+        self.lineas.clear();
+    }
+
+    pub fn esta_vacio(&self) -> bool {
+        self.lineas.is_empty()
+    }
+}
+
+/// Un producto vendido, con el precio al momento de la venta
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineaVenta {
+    pub producto_id: u32,
+    pub nombre: String,
+    pub cantidad: u32,
+    pub precio_unitario: Dinero,
+    pub importe: Dinero,
+}
+
+/// Detalle y totales de una venta (o de un carrito antes de cobrarlo)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Venta {
+    /// Segundos desde la época Unix
+    pub fecha: i64,
+    pub moneda: Moneda,
+    pub lineas: Vec<LineaVenta>,
+    pub subtotal: Dinero,
+    pub tasa: TasaImpuesto,
+    pub impuesto: Dinero,
+    pub total: Dinero,
+}
+
+impl Venta {
+    /// Calcular los importes del carrito con los precios actuales, sin tocar
+    /// el stock. Todos los productos tienen que tener precio en la misma moneda.
+    pub fn calcular(inventario: &InventarioManager, carrito: &Carrito, tasa: TasaImpuesto) -> Result<Self, InventarioError> {
+        let fuera_de_rango = || InventarioError::Validacion("el importe de la venta queda fuera de rango".to_string());
+        let mut moneda = None;
+        let mut lineas = Vec::new();
+        let mut subtotal: u64 = 0;
+
+        for &(id, cantidad) in &carrito.lineas {
+            let producto = inventario.obtener_producto(id).ok_or(InventarioError::NoEncontrado(id))?;
+            let precio = producto.precio;
+            if *moneda.get_or_insert(precio.moneda) != precio.moneda {
+                return Err(InventarioError::Validacion(format!(
+                    "«{}» tiene precio en {} y el resto de la venta no", producto.nombre_completo(), precio.moneda
+                )));
+            }
+
+            let importe = precio.centavos.checked_mul(u64::from(cantidad)).ok_or_else(fuera_de_rango)?;
+            subtotal = subtotal.checked_add(importe).ok_or_else(fuera_de_rango)?;
+            lineas.push(LineaVenta {
+                producto_id: id,
+                nombre: producto.nombre_completo(),
+                cantidad,
+                precio_unitario: precio,
+                importe: Dinero::new(importe, precio.moneda),
+            });
+        }
+
+        let moneda = moneda.unwrap_or_else(|| inventario.moneda_principal());
+        let impuesto = tasa.aplicar(subtotal);
+        let total = subtotal.checked_add(impuesto).ok_or_else(fuera_de_rango)?;
+        Ok(Self {
+            fecha: Local::now().timestamp(),
+            moneda,
+            lineas,
+            subtotal: Dinero::new(subtotal, moneda),
+            tasa,
+            impuesto: Dinero::new(impuesto, moneda),
+            total: Dinero::new(total, moneda),
+        })
+    }
+
+    /// Texto del recibo, listo para imprimir
+    pub fn recibo(&self, formato: &FormatoMoneda) -> String {
+        const ANCHO: usize = 40;
+        let fecha = Local.timestamp_opt(self.fecha, 0)
+            .single()
+            .map(|f| f.format("%Y-%m-%d %H:%M").to_string())
+            .unwrap_or_default();
+        let renglon = |izquierda: &str, derecha: &str| {
+            let espacio = ANCHO.saturating_sub(izquierda.chars().count() + derecha.chars().count()).max(1);
+            format!("{}{}{}", izquierda, " ".repeat(espacio), derecha)
+        };
+
+        let mut texto = vec![format!("{:^ANCHO$}", "RECIBO DE VENTA"), format!("{:^ANCHO$}", fecha), "-".repeat(ANCHO)];
+        for linea in &self.lineas {
+            texto.push(renglon(&format!("{} × {}", linea.cantidad, linea.nombre), &linea.importe.formatear(formato)));
+            if linea.cantidad > 1 {
+                texto.push(format!("    a {} c/u", linea.precio_unitario.formatear(formato)));
+            }
+        }
+        texto.push("-".repeat(ANCHO));
+        texto.push(renglon("Subtotal", &self.subtotal.formatear(formato)));
+        texto.push(renglon(&format!("Impuestos ({})", self.tasa), &self.impuesto.formatear(formato)));
+        texto.push(renglon("TOTAL", &self.total.formatear(formato)));
+
+        texto.join("\n") + "\n"
+    }
+}
+
+/// Cobrar el carrito: descontar el stock de `ubicacion` de una sola vez y
+/// devolver la venta. Si falta stock de algún producto no se vende nada.
+pub fn cobrar(
+    inventario: &mut InventarioManager,
+    carrito: &Carrito,
+    tasa: TasaImpuesto,
+    ubicacion: Option<&str>,
+) -> Result<Venta, InventarioError> {
+    let venta = Venta::calcular(inventario, carrito, tasa)?;
+    inventario.registrar_venta(&carrito.lineas, ubicacion, Some("Venta en caja".to_string()))?;
+    Ok(venta)
+}
+
+/// Productos para agregar al carrito: el que tiene ese código de barras o
+/// SKU, o si no los que coinciden por nombre
+pub fn buscar<'a>(inventario: &'a InventarioManager, texto: &str) -> Vec<&'a Producto> {
+    match inventario.buscar_por_codigo(texto) {
+        Some(producto) => vec![producto],
+        None => inventario.buscar_por_nombre(texto),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cobrar_descuenta_todo_o_nada() {
+        let mut inventario = InventarioManager::new();
+        let usd = |texto| Dinero::parsear(texto, Moneda::POR_DEFECTO).unwrap();
+        let cafe = inventario.agregar_producto("Café".to_string(), usd("10.50"), 5, Vec::new(), None).unwrap();
+        let te = inventario.agregar_producto("Té".to_string(), usd("3"), 1, Vec::new(), None).unwrap();
+
+        let mut carrito = Carrito::default();
+        carrito.agregar(cafe, 1);
+        carrito.agregar(te, 2);
+        carrito.agregar(cafe, 1);
+        assert_eq!(carrito.lineas, [(cafe, 2), (te, 2)]);
+
+        // Solo hay un té: no se vende nada
+        let tasa = TasaImpuesto::parsear("10,5").unwrap();
+        assert!(cobrar(&mut inventario, &carrito, tasa, None).is_err());
+        assert_eq!(inventario.obtener_producto(cafe).unwrap().stock, 5);
+
+        // Un importe que no entra en el tipo es un error, no un total recortado
+        let caro = inventario.agregar_producto("Caro".to_string(), Dinero::new(u64::MAX / 2 + 1, Moneda::POR_DEFECTO), 2, Vec::new(), None).unwrap();
+        let mut excesivo = Carrito::default();
+        excesivo.agregar(caro, 2);
+        assert!(matches!(Venta::calcular(&inventario, &excesivo, tasa), Err(InventarioError::Validacion(_))));
+        inventario.deshacer().unwrap();
+
+        carrito.quitar(te);
+        carrito.agregar(te, 1);
+        let venta = cobrar(&mut inventario, &carrito, tasa, None).unwrap();
+        assert_eq!((venta.subtotal, venta.impuesto, venta.total), (usd("24"), usd("2.52"), usd("26.52")));
+        assert_eq!(inventario.obtener_producto(cafe).unwrap().stock, 3);
+        assert_eq!(inventario.obtener_producto(te).unwrap().stock, 0);
+
+        let recibo = venta.recibo(&FormatoMoneda::default());
+        assert!(recibo.contains("2 × Café"), "{}", recibo);
+        assert!(recibo.contains("Impuestos (10.5 %)"), "{}", recibo);
+        assert!(recibo.lines().last().unwrap().ends_with("$26.52"), "{}", recibo);
+
+        // La venta se deshace de una vez y lo anterior sigue en el historial
+        assert_eq!(inventario.deshacer().unwrap().as_deref(), Some("Registrar venta"));
+        assert_eq!(inventario.obtener_producto(cafe).unwrap().stock, 5);
+        assert_eq!(inventario.obtener_producto(te).unwrap().stock, 1);
+        assert_eq!(inventario.descripcion_deshacer(), Some("Agregar «Té»"));
+    }
+}
@@ -0,0 +1,41 @@
+//! Gestión de inventario: productos con precio, stock por ubicación y
+//! etiquetas, historial de movimientos, proveedores y órdenes de compra,
+//! ventas y reportes de valuación.
+//!
+//! Todo pasa por [`InventarioManager`]. Sin almacenamiento el inventario
+//! vive en memoria y se guarda con [`InventarioManager::guardar_inventario`];
+//! con uno ([`CsvStorage`], [`SqliteStorage`] o [`DiarioStorage`]) cada
+//! cambio queda guardado antes de aplicarse.
+//!
+//! ```
+//! use rustiventario::{Dinero, InventarioManager, Moneda, MotivoMovimiento};
+//!
+//! let mut inventario = InventarioManager::new();
+//! let precio = Dinero::parsear("19.99", Moneda::POR_DEFECTO).unwrap();
+//! let id = inventario.agregar_producto("Teclado".to_string(), precio, 3, vec!["periféricos".to_string()], None)?;
+//!
+//! inventario.registrar_movimiento(id, -1, MotivoMovimiento::Venta, None)?;
+//! assert_eq!(inventario.obtener_producto(id).unwrap().stock, 2);
+//! assert_eq!(inventario.buscar_por_tags("periféricos")?.len(), 1);
+//! # Ok::<(), rustiventario::InventarioError>(())
+//! ```
+//!
+//! Módulos:
+//! - [`models`]: los datos (productos, dinero, movimientos, compras)
+//! - [`inventario`]: el gestor y lo que se construye sobre él (búsqueda
+//!   por tags, eventos, importación, comparación, compras, ventas, reportes)
+//! - [`storage`]: backends de persistencia
+//! - [`utils::file_io`]: lectura y escritura de CSV y JSON
+
+pub mod inventario;
+pub mod models;
+pub mod storage;
+pub mod utils;
+
+pub use inventario::{ConsultaTags, EventoInventario, InventarioError, InventarioManager};
+pub use models::{
+    ConfiguracionStock, Dinero, EstadoOrden, FormatoMoneda, LineaOrden, Moneda, Movimiento, MotivoMovimiento,
+    OrdenCompra, Producto, Proveedor, Variante,
+};
+pub use storage::{CsvStorage, DiarioStorage, SqliteStorage, Storage};
+pub use utils::file_io::{DatosInventario, ModoCarga, ReporteCarga};
@@ -0,0 +1,89 @@
+// Códigos que identifican un producto además de su ID: el SKU interno y
+// el código de barras (EAN-13 o UPC-A)
+
+/// Comprobar que un SKU sea utilizable: sin espacios y con solo letras,
+/// números, guiones, guiones bajos o puntos
+pub fn validar_sku(sku: &str) -> Result<(), String> {
+    if sku.is_empty() {
+        return Err("el SKU no puede estar vacío".to_string());
+    }
+    if let Some(c) = sku.chars().find(|c| !c.is_alphanumeric() && !"-_.".contains(*c)) {
+        return Err(format!("el SKU \"{}\" tiene un carácter no permitido: '{}'", sku, c));
+    }
+
+    Ok(())
+}
+
+/// Forma del SKU con la que se compara: sin distinguir mayúsculas
+pub fn clave_sku(sku: &str) -> String {
+    sku.trim().to_uppercase()
+}
+
+/// Comprobar un código EAN-13 (13 dígitos) o UPC-A (12 dígitos),
+/// incluido su dígito verificador
+pub fn validar_codigo_barras(codigo: &str) -> Result<(), String> {
+    if !codigo.chars().all(|c| c.is_ascii_digit()) {
+        return Err(format!("el código de barras \"{}\" solo puede tener dígitos", codigo));
+    }
+    if codigo.len() != 12 && codigo.len() != 13 {
+        return Err(format!(
+            "el código de barras \"{}\" tiene {} dígitos; EAN-13 lleva 13 y UPC-A 12",
+            codigo, codigo.len()
+        ));
+    }
+
+    let digitos: Vec<u32> = codigo.bytes().map(|b| u32::from(b - b'0')).collect();
+    let (verificador, datos) = digitos.split_last().expect("el código tiene 12 o 13 dígitos");
+    if digito_verificador(datos) != *verificador {
+        return Err(format!("el dígito verificador del código de barras \"{}\" no es válido", codigo));
+    }
+
+    Ok(())
+}
+
+/// Forma del código de barras con la que se compara: un UPC-A es el
+/// EAN-13 que empieza con 0, así que se guardan todos con 13 dígitos
+pub fn clave_codigo_barras(codigo: &str) -> String {
+    let codigo = codigo.trim();
+    if codigo.len() == 12 {
+        format!("0{}", codigo)
+    } else {
+        codigo.to_string()
+    }
+}
+
+// Dígito verificador de EAN/UPC: desde la derecha los dígitos pesan 3 y 1
+// alternadamente, y el verificador completa la suma hasta un múltiplo de 10
+fn digito_verificador(datos: &[u32]) -> u32 {
+    let suma: u32 = datos.iter()
+        .rev()
+        .enumerate()
+        .map(|(i, d)| if i % 2 == 0 { d * 3 } else { *d })
+        .sum();
+    (10 - suma % 10) % 10
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn valida_ean_13_y_upc_a() {
+        assert!(validar_codigo_barras("4006381333931").is_ok());
+        assert!(validar_codigo_barras("036000291452").is_ok());
+        assert!(validar_codigo_barras("4006381333932").unwrap_err().contains("verificador"));
+        assert!(validar_codigo_barras("12345").unwrap_err().contains("5 dígitos"));
+        assert!(validar_codigo_barras("40063813339A1").is_err());
+
+        // Un UPC-A y su EAN-13 equivalente son el mismo código
+        assert_eq!(clave_codigo_barras("036000291452"), clave_codigo_barras("0036000291452"));
+    }
+
+    #[test]
+    fn valida_sku() {
+        assert!(validar_sku("TEC-001_b.2").is_ok());
+        assert!(validar_sku("TEC 001").is_err());
+        assert!(validar_sku("").is_err());
+        assert_eq!(clave_sku(" tec-001 "), "TEC-001");
+    }
+}
@@ -0,0 +1,43 @@
+use std::collections::BTreeMap;
+use serde::{Deserialize, Serialize};
+
+use super::Producto;
+
+/// Puntos de reorden por defecto, para productos que no tienen uno propio
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConfiguracionStock {
+    /// Punto de reorden para productos sin uno propio ni en sus tags
+    pub punto_reorden_global: u32,
+    /// Punto de reorden heredado por los productos de cada tag
+    #[serde(default)]
+    pub punto_reorden_por_tag: BTreeMap<String, u32>,
+}
+
+impl Default for ConfiguracionStock {
+    fn default() -> Self {
+        Self {
+            // Equivale al antiguo límite fijo de "menos de 5 unidades"
+            punto_reorden_global: 4,
+            punto_reorden_por_tag: BTreeMap::new(),
+        }
+    }
+}
+
+impl ConfiguracionStock {
+    /// Punto de reorden efectivo: el del producto, si no el mayor de sus
+    /// tags y si no el global
+    pub fn punto_reorden(&self, producto: &Producto) -> u32 {
+        producto.punto_reorden.unwrap_or_else(|| {
+            producto.tags.iter()
+                .filter_map(|tag| self.punto_reorden_por_tag.get(tag))
+                .copied()
+                .max()
+                .unwrap_or(self.punto_reorden_global)
+        })
+    }
+
+    /// Si el producto está en su punto de reorden o por debajo
+    pub fn es_bajo_stock(&self, producto: &Producto) -> bool {
+        producto.stock <= self.punto_reorden(producto)
+    }
+}
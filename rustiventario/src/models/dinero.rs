@@ -0,0 +1,332 @@
+// Montos de dinero en punto fijo: unidades menores (centavos) y la moneda
+// ISO 4217, para no perder precisión con decimales de punto flotante
+
+use std::cmp::Ordering;
+use std::fmt;
+use serde::{Deserialize, Serialize};
+
+/// Código de moneda ISO 4217, por ejemplo `USD` o `EUR`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Moneda([u8; 3]);
+
+impl Moneda {
+    /// Moneda de los precios guardados antes de que existieran las monedas
+    pub const POR_DEFECTO: Moneda = Moneda(*b"USD");
+
+    /// Interpretar un código de tres letras, sin distinguir mayúsculas
+    pub fn parsear(codigo: &str) -> Result<Self, String> {
+        let codigo = codigo.trim().to_ascii_uppercase();
+        match <[u8; 3]>::try_from(codigo.as_bytes()) {
+            Ok(letras) if letras.iter().all(u8::is_ascii_uppercase) => Ok(Moneda(letras)),
+            _ => Err(format!("moneda inválida: \"{}\" (se espera un código ISO como USD)", codigo)),
+        }
+    }
+
+    pub fn codigo(&self) -> &str {
+        // Siempre son letras ASCII, ver `parsear`
+        std::str::from_utf8(&self.0).unwrap_or_default()
+    }
+
+    /// Cantidad de decimales de la moneda (2 para casi todas)
+    pub fn decimales(&self) -> u32 {
+        match self.codigo() {
+            "CLP" | "ISK" | "JPY" | "KRW" | "PYG" | "VND" => 0,
+            "BHD" | "JOD" | "KWD" | "OMR" | "TND" => 3,
+            _ => 2,
+        }
+    }
+
+    /// Símbolo para mostrar; las monedas sin uno conocido usan su código
+    pub fn simbolo(&self) -> &str {
+        match self.codigo() {
+            "USD" | "MXN" | "ARS" | "CLP" | "COP" | "UYU" | "CAD" | "AUD" => "$",
+            "EUR" => "€",
+            "GBP" => "£",
+            "JPY" => "¥",
+            "BRL" => "R$",
+            "PEN" => "S/",
+            otro => otro,
+        }
+    }
+
+    // Unidades menores por unidad: 100 para dos decimales
+    fn factor(&self) -> u64 {
+        10u64.pow(self.decimales())
+    }
+}
+
+impl Default for Moneda {
+    fn default() -> Self {
+        Moneda::POR_DEFECTO
+    }
+}
+
+impl fmt::Display for Moneda {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.codigo())
+    }
+}
+
+impl TryFrom<String> for Moneda {
+    type Error = String;
+
+    fn try_from(codigo: String) -> Result<Self, Self::Error> {
+        Moneda::parsear(&codigo)
+    }
+}
+
+impl From<Moneda> for String {
+    fn from(moneda: Moneda) -> Self {
+        moneda.codigo().to_string()
+    }
+}
+
+/// Un monto de dinero: `centavos` son las unidades menores de `moneda`
+/// (centavos para la mayoría de las monedas)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(from = "DineroGuardado")]
+pub struct Dinero {
+    pub centavos: u64,
+    pub moneda: Moneda,
+}
+
+impl Dinero {
+    pub fn new(centavos: u64, moneda: Moneda) -> Self {
+        Self { centavos, moneda }
+    }
+
+    /// Monto sin decimales, como se guardaban los precios antiguos
+    pub fn desde_unidades(unidades: u64, moneda: Moneda) -> Self {
+        Self::new(unidades.saturating_mul(moneda.factor()), moneda)
+    }
+
+    /// Parte entera del monto, sin los centavos
+    pub fn unidades(&self) -> u64 {
+        self.centavos / self.moneda.factor()
+    }
+
+    /// Interpretar un monto escrito por una persona: `19.99`, `19,99`,
+    /// `1,234.50` o `1.234,50`. El último separador es el decimal si lo
+    /// siguen como mucho tantos dígitos como decimales tiene la moneda;
+    /// los demás agrupan miles y los siguen exactamente tres dígitos.
+    /// Un único separador seguido de tres dígitos (`1.234`) es ambiguo en
+    /// una moneda con decimales y no se acepta.
+    pub fn parsear(texto: &str, moneda: Moneda) -> Result<Self, String> {
+        let texto = texto.trim();
+        let invalido = || format!("monto inválido: \"{}\"", texto);
+        if texto.is_empty() || !texto.chars().all(|c| c.is_ascii_digit() || c == '.' || c == ',') {
+            return Err(invalido());
+        }
+
+        let decimales = moneda.decimales() as usize;
+        let (entera, fraccion) = match texto.rfind(['.', ',']) {
+            Some(pos) if texto.len() - pos - 1 <= decimales => (&texto[..pos], &texto[pos + 1..]),
+            _ => (texto, ""),
+        };
+
+        // Los demás separadores agrupan miles; un error de tipeo no puede
+        // convertirse en un monto mil veces mayor
+        let grupos: Vec<&str> = entera.split(['.', ',']).collect();
+        if grupos[1..].iter().any(|grupo| grupo.len() != 3) {
+            return Err(invalido());
+        }
+        if decimales > 0 && fraccion.is_empty() && grupos.len() == 2 {
+            return Err(format!("monto ambiguo: \"{}\"; escribirlo con decimales", texto));
+        }
+        let entera: String = grupos.concat();
+        if entera.is_empty() && fraccion.is_empty() {
+            return Err(invalido());
+        }
+        let unidades: u64 = if entera.is_empty() { 0 } else { entera.parse().map_err(|_| invalido())? };
+        let fraccion: u64 = format!("{:0<ancho$}", fraccion, ancho = decimales).parse().unwrap_or(0);
+
+        unidades.checked_mul(moneda.factor())
+            .and_then(|c| c.checked_add(fraccion))
+            .map(|centavos| Self::new(centavos, moneda))
+            .ok_or_else(|| format!("monto demasiado grande: \"{}\"", texto))
+    }
+
+    /// Monto con punto decimal y sin separador de miles, por ejemplo
+    /// `1234.50`; es como se guarda en los archivos
+    pub fn monto(&self) -> String {
+        let factor = self.moneda.factor();
+        match self.moneda.decimales() as usize {
+            0 => self.centavos.to_string(),
+            decimales => format!("{}.{:0ancho$}", self.centavos / factor, self.centavos % factor, ancho = decimales),
+        }
+    }
+
+    /// Monto para mostrar según las convenciones regionales de `formato`
+    pub fn formatear(&self, formato: &FormatoMoneda) -> String {
+        let monto = self.monto();
+        let (entera, fraccion) = monto.split_once('.').unwrap_or((&monto, ""));
+
+        // Agrupar la parte entera de a tres dígitos
+        let mut agrupada = String::new();
+        for (i, c) in entera.chars().enumerate() {
+            if i > 0 && (entera.len() - i) % 3 == 0 {
+                agrupada.push(formato.separador_miles);
+            }
+            agrupada.push(c);
+        }
+        if !fraccion.is_empty() {
+            agrupada.push(formato.separador_decimal);
+            agrupada.push_str(fraccion);
+        }
+
+        let simbolo = self.moneda.simbolo();
+        match (formato.simbolo_antes, simbolo.chars().count() > 1) {
+            (true, false) => format!("{}{}", simbolo, agrupada),
+            (true, true) => format!("{} {}", simbolo, agrupada),
+            (false, _) => format!("{} {}", agrupada, simbolo),
+        }
+    }
+}
+
+// Los montos de una misma moneda quedan juntos, ordenados por valor
+impl Ord for Dinero {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.moneda.cmp(&other.moneda).then(self.centavos.cmp(&other.centavos))
+    }
+}
+
+impl PartialOrd for Dinero {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for Dinero {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.monto(), self.moneda)
+    }
+}
+
+/// Formas en que se puede leer un precio de JSON: los archivos antiguos
+/// tienen un entero de unidades sin moneda
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum DineroGuardado {
+    Unidades(u64),
+    Completo { centavos: u64, moneda: Moneda },
+}
+
+impl From<DineroGuardado> for Dinero {
+    fn from(guardado: DineroGuardado) -> Self {
+        match guardado {
+            DineroGuardado::Unidades(unidades) => Dinero::desde_unidades(unidades, Moneda::POR_DEFECTO),
+            DineroGuardado::Completo { centavos, moneda } => Dinero::new(centavos, moneda),
+        }
+    }
+}
+
+/// Convenciones regionales para mostrar montos
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FormatoMoneda {
+    pub separador_miles: char,
+    pub separador_decimal: char,
+    /// `$1,234.50` en vez de `1.234,50 €`
+    pub simbolo_antes: bool,
+}
+
+impl FormatoMoneda {
+    /// Formato del idioma y país de un locale como `es_AR.UTF-8`
+    pub fn desde_locale(locale: &str) -> Self {
+        let nombre = locale.split(['.', '@']).next().unwrap_or_default();
+        let (idioma, pais) = nombre.split_once(['_', '-']).unwrap_or((nombre, ""));
+
+        // Países de habla hispana que escriben los decimales con punto
+        let punto_decimal = match idioma {
+            "es" => matches!(pais, "MX" | "US" | "PR" | "GT" | "DO" | "HN" | "NI" | "PA" | "SV" | "PE"),
+            "de" | "fr" | "it" | "pt" | "nl" | "ru" | "pl" | "tr" | "sv" | "da" | "nb" | "fi" | "cs" => false,
+            _ => true,
+        };
+        let separador_miles = match idioma {
+            _ if punto_decimal => ',',
+            "fr" | "ru" | "pl" | "sv" | "nb" | "fi" | "cs" => ' ',
+            _ => '.',
+        };
+        // En Europa el símbolo suele ir después; en América, antes
+        let simbolo_antes = match idioma {
+            "es" => pais != "ES",
+            "pt" => pais == "BR",
+            "de" | "fr" | "it" | "ru" | "pl" | "sv" | "da" | "nb" | "fi" | "cs" => false,
+            _ => true,
+        };
+
+        Self {
+            separador_miles,
+            separador_decimal: if punto_decimal { '.' } else { ',' },
+            simbolo_antes,
+        }
+    }
+
+    /// Formato del locale del sistema (`LC_ALL`, `LC_MONETARY` o `LANG`)
+    pub fn desde_entorno() -> Self {
+        let locale = ["LC_ALL", "LC_MONETARY", "LANG"].iter()
+            .filter_map(|variable| std::env::var(variable).ok())
+            .find(|valor| !valor.is_empty())
+            .unwrap_or_default();
+        Self::desde_locale(&locale)
+    }
+}
+
+impl Default for FormatoMoneda {
+    fn default() -> Self {
+        Self::desde_locale("en_US")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn usd(centavos: u64) -> Dinero {
+        Dinero::new(centavos, Moneda::POR_DEFECTO)
+    }
+
+    #[test]
+    fn interpreta_montos_con_coma_o_punto() {
+        assert_eq!(Dinero::parsear("19.99", Moneda::POR_DEFECTO), Ok(usd(1999)));
+        assert_eq!(Dinero::parsear("19,9", Moneda::POR_DEFECTO), Ok(usd(1990)));
+        assert_eq!(Dinero::parsear("1,234.50", Moneda::POR_DEFECTO), Ok(usd(123_450)));
+        assert_eq!(Dinero::parsear("1.234,50", Moneda::POR_DEFECTO), Ok(usd(123_450)));
+        assert_eq!(Dinero::parsear("1.234.567", Moneda::POR_DEFECTO), Ok(usd(123_456_700)));
+        assert_eq!(Dinero::parsear("1200", Moneda::POR_DEFECTO), Ok(usd(120_000)));
+
+        // Un separador que no es decimal ni agrupa tres dígitos es un error
+        assert!(Dinero::parsear("19.999", Moneda::POR_DEFECTO).is_err());
+        assert!(Dinero::parsear("1.234", Moneda::POR_DEFECTO).is_err());
+        assert!(Dinero::parsear("1,23,456.00", Moneda::POR_DEFECTO).is_err());
+
+        let yen = Moneda::parsear("jpy").unwrap();
+        assert_eq!(Dinero::parsear("1,500", yen), Ok(Dinero::new(1500, yen)));
+        assert!(Dinero::parsear("19.99", yen).is_err());
+        assert!(Dinero::parsear("12.3.4x", Moneda::POR_DEFECTO).is_err());
+        assert!(Dinero::parsear("", Moneda::POR_DEFECTO).is_err());
+        assert!(Moneda::parsear("US").is_err());
+    }
+
+    #[test]
+    fn formatea_segun_el_locale() {
+        let euro = Moneda::parsear("EUR").unwrap();
+        let precio = Dinero::new(123_456_789, euro);
+
+        assert_eq!(usd(123_450).formatear(&FormatoMoneda::desde_locale("en_US.UTF-8")), "$1,234.50");
+        assert_eq!(usd(5).formatear(&FormatoMoneda::desde_locale("es_MX")), "$0.05");
+        assert_eq!(precio.formatear(&FormatoMoneda::desde_locale("es_ES.UTF-8")), "1.234.567,89 €");
+        assert_eq!(precio.formatear(&FormatoMoneda::desde_locale("fr_FR")), "1 234 567,89 €");
+        assert_eq!(usd(99).to_string(), "0.99 USD");
+        assert_eq!(Dinero::new(42, Moneda::parsear("KWD").unwrap()).monto(), "0.042");
+    }
+
+    #[test]
+    fn lee_precios_antiguos_de_json() {
+        let antiguo: Dinero = serde_json::from_str("1200").unwrap();
+        assert_eq!(antiguo, usd(120_000));
+
+        let nuevo: Dinero = serde_json::from_str(&serde_json::to_string(&usd(1999)).unwrap()).unwrap();
+        assert_eq!(nuevo, usd(1999));
+    }
+}
@@ -0,0 +1,14 @@
+pub mod codigo;
+pub mod configuracion;
+pub mod dinero;
+pub mod movimiento;
+pub mod orden_compra;
+pub mod producto;
+pub mod proveedor;
+
+pub use configuracion::ConfiguracionStock;
+pub use dinero::{Dinero, FormatoMoneda, Moneda};
+pub use movimiento::{Movimiento, MotivoMovimiento};
+pub use orden_compra::{EstadoOrden, LineaOrden, OrdenCompra};
+pub use producto::{Producto, Variante};
+pub use proveedor::Proveedor;
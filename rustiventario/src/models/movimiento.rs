@@ -0,0 +1,93 @@
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+use serde::{Deserialize, Serialize};
+
+use super::Dinero;
+
+/// Motivo de un cambio de stock
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MotivoMovimiento {
+    Venta,
+    Compra,
+    Ajuste,
+    Devolucion,
+    /// Paso de unidades entre ubicaciones; se registra como un par de
+    /// movimientos (salida y entrada) con la misma fecha
+    Transferencia,
+}
+
+impl MotivoMovimiento {
+    pub const TODOS: [MotivoMovimiento; 5] = [
+        MotivoMovimiento::Venta,
+        MotivoMovimiento::Compra,
+        MotivoMovimiento::Ajuste,
+        MotivoMovimiento::Devolucion,
+        MotivoMovimiento::Transferencia,
+    ];
+
+    /// Nombre usado al guardar en archivos
+    pub fn clave(&self) -> &'static str {
+        match self {
+            MotivoMovimiento::Venta => "venta",
+            MotivoMovimiento::Compra => "compra",
+            MotivoMovimiento::Ajuste => "ajuste",
+            MotivoMovimiento::Devolucion => "devolucion",
+            MotivoMovimiento::Transferencia => "transferencia",
+        }
+    }
+
+    pub fn desde_clave(clave: &str) -> Option<Self> {
+        Self::TODOS.into_iter().find(|m| m.clave() == clave)
+    }
+}
+
+impl fmt::Display for MotivoMovimiento {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let texto = match self {
+            MotivoMovimiento::Venta => "Venta",
+            MotivoMovimiento::Compra => "Compra",
+            MotivoMovimiento::Ajuste => "Ajuste",
+            MotivoMovimiento::Devolucion => "Devolución",
+            MotivoMovimiento::Transferencia => "Transferencia",
+        };
+        write!(f, "{}", texto)
+    }
+}
+
+/// Un cambio de stock de un producto
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Movimiento {
+    pub producto_id: u32,
+    /// Segundos desde la época Unix
+    pub fecha: i64,
+    /// Unidades que entran (positivo) o salen (negativo)
+    pub delta: i64,
+    pub motivo: MotivoMovimiento,
+    pub nota: Option<String>,
+    /// Ubicación afectada; `None` es el stock sin ubicación asignada
+    #[serde(default)]
+    pub ubicacion: Option<String>,
+    /// Lo que costó cada unidad, en las compras que lo indican; sirve
+    /// para valuar el inventario (ver `inventario::reportes`)
+    #[serde(default)]
+    pub costo_unitario: Option<Dinero>,
+}
+
+impl Movimiento {
+    /// Crear un movimiento con la fecha actual
+    pub fn nuevo(producto_id: u32, delta: i64, motivo: MotivoMovimiento, nota: Option<String>) -> Self {
+        let fecha = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or(0);
+
+        Self { producto_id, fecha, delta, motivo, nota, ubicacion: None, costo_unitario: None }
+    }
+
+    /// El mismo movimiento, en la ubicación indicada
+    pub fn en_ubicacion(mut self, ubicacion: Option<&str>) -> Self {
+        self.ubicacion = ubicacion.map(String::from);
+        self
+    }
+}
@@ -0,0 +1,115 @@
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+use serde::{Deserialize, Serialize};
+
+use super::Dinero;
+
+/// Etapa de una orden de compra
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EstadoOrden {
+    /// Todavía se puede modificar o eliminar
+    Borrador,
+    /// Pedida al proveedor, esperando la mercadería
+    Enviada,
+    /// Llegó parte de lo pedido
+    RecibidaParcial,
+    Recibida,
+}
+
+impl EstadoOrden {
+    pub const TODOS: [EstadoOrden; 4] = [
+        EstadoOrden::Borrador,
+        EstadoOrden::Enviada,
+        EstadoOrden::RecibidaParcial,
+        EstadoOrden::Recibida,
+    ];
+
+    /// Nombre usado al guardar en archivos
+    pub fn clave(&self) -> &'static str {
+        match self {
+            EstadoOrden::Borrador => "borrador",
+            EstadoOrden::Enviada => "enviada",
+            EstadoOrden::RecibidaParcial => "recibida_parcial",
+            EstadoOrden::Recibida => "recibida",
+        }
+    }
+
+    pub fn desde_clave(clave: &str) -> Option<Self> {
+        Self::TODOS.into_iter().find(|e| e.clave() == clave)
+    }
+
+    /// Si todavía falta recibir mercadería
+    pub fn abierta(&self) -> bool {
+        matches!(self, EstadoOrden::Enviada | EstadoOrden::RecibidaParcial)
+    }
+}
+
+impl fmt::Display for EstadoOrden {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let texto = match self {
+            EstadoOrden::Borrador => "Borrador",
+            EstadoOrden::Enviada => "Enviada",
+            EstadoOrden::RecibidaParcial => "Recibida en parte",
+            EstadoOrden::Recibida => "Recibida",
+        };
+        write!(f, "{}", texto)
+    }
+}
+
+/// Un producto pedido en una orden
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LineaOrden {
+    pub producto_id: u32,
+    pub cantidad: u32,
+    /// Unidades que ya llegaron
+    #[serde(default)]
+    pub recibida: u32,
+    /// Costo acordado por unidad, en la moneda del precio del producto
+    #[serde(default)]
+    pub costo_unitario: Option<Dinero>,
+}
+
+impl LineaOrden {
+    pub fn new(producto_id: u32, cantidad: u32, costo_unitario: Option<Dinero>) -> Self {
+        Self { producto_id, cantidad, recibida: 0, costo_unitario }
+    }
+
+    /// Unidades que faltan recibir
+    pub fn pendiente(&self) -> u32 {
+        self.cantidad.saturating_sub(self.recibida)
+    }
+}
+
+/// Pedido de mercadería a un proveedor
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OrdenCompra {
+    pub id: u32,
+    pub proveedor_id: u32,
+    /// Segundos desde la época Unix
+    pub fecha: i64,
+    pub estado: EstadoOrden,
+    pub lineas: Vec<LineaOrden>,
+    #[serde(default)]
+    pub nota: Option<String>,
+}
+
+impl OrdenCompra {
+    /// Crear una orden en borrador con la fecha actual
+    pub fn nueva(id: u32, proveedor_id: u32, lineas: Vec<LineaOrden>, nota: Option<String>) -> Self {
+        let fecha = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or(0);
+
+        Self { id, proveedor_id, fecha, estado: EstadoOrden::Borrador, lineas, nota }
+    }
+
+    /// Unidades que faltan recibir de un producto
+    pub fn pendiente_de(&self, producto_id: u32) -> u32 {
+        self.lineas.iter()
+            .filter(|l| l.producto_id == producto_id)
+            .map(LineaOrden::pendiente)
+            .sum()
+    }
+}
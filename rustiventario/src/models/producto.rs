@@ -0,0 +1,94 @@
+use std::collections::{BTreeMap, HashSet};
+use std::cmp::Ordering;
+use serde::{Deserialize, Serialize};
+
+use super::Dinero;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Producto {
+    pub id: u32,
+    pub nombre: String,
+    /// Los archivos antiguos guardaban unidades enteras, ver `Dinero`
+    pub precio: Dinero,
+    /// Stock total, sumando todas las ubicaciones
+    pub stock: u32,
+    pub tags: HashSet<String>,
+    /// Con este stock o menos hay que reponer; si es `None` se hereda
+    /// de las tags o de la configuración global
+    #[serde(default)]
+    pub punto_reorden: Option<u32>,
+    /// Unidades en cada ubicación (sin entradas en cero). Lo que falta
+    /// para llegar a `stock` es stock sin ubicación asignada.
+    #[serde(default)]
+    pub existencias: BTreeMap<String, u32>,
+    /// Código interno del producto, único sin distinguir mayúsculas
+    #[serde(default)]
+    pub sku: Option<String>,
+    /// Código de barras EAN-13 o UPC-A, único
+    #[serde(default)]
+    pub codigo_barras: Option<String>,
+    /// Costo de una unidad, en la moneda del precio; es el de la última
+    /// compra si se registró con costo
+    #[serde(default)]
+    pub costo: Option<Dinero>,
+    /// Proveedor al que se le piden reposiciones
+    #[serde(default)]
+    pub proveedor_id: Option<u32>,
+    /// Si el producto es una variante (talla, color...) de otro
+    #[serde(default)]
+    pub variante: Option<Variante>,
+}
+
+/// Lo que hace de un producto una variante de otro. El nombre, los tags
+/// y el precio base son los del padre; el stock, los códigos y el costo
+/// son de cada variante.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Variante {
+    pub padre_id: u32,
+    /// Lo que la distingue de las otras variantes, por ejemplo `talla = M`
+    pub atributos: BTreeMap<String, String>,
+    /// Si el precio es propio; si no, sigue al del padre
+    #[serde(default)]
+    pub precio_propio: bool,
+}
+
+impl Variante {
+    /// Los atributos en una línea, por ejemplo `color: rojo, talla: M`
+    pub fn descripcion(&self) -> String {
+        self.atributos.iter()
+            .map(|(atributo, valor)| format!("{}: {}", atributo, valor))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+impl Producto {
+    /// Unidades en una ubicación; con `None`, las que no tienen ubicación
+    pub fn stock_en(&self, ubicacion: Option<&str>) -> u32 {
+        match ubicacion {
+            Some(nombre) => self.existencias.get(nombre).copied().unwrap_or(0),
+            None => self.stock.saturating_sub(self.existencias.values().sum()),
+        }
+    }
+
+    /// Nombre con los atributos de la variante, si lo es
+    pub fn nombre_completo(&self) -> String {
+        match &self.variante {
+            Some(variante) => format!("{} ({})", self.nombre, variante.descripcion()),
+            None => self.nombre.clone(),
+        }
+    }
+}
+
+impl Ord for Producto {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Ordenamos por precio (mayor a menor)
+        self.precio.cmp(&other.precio)
+    }
+}
+
+impl PartialOrd for Producto {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
@@ -0,0 +1,11 @@
+use serde::{Deserialize, Serialize};
+
+/// Empresa a la que se le compran productos
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Proveedor {
+    pub id: u32,
+    pub nombre: String,
+    /// Teléfono, correo o lo que sirva para hacer pedidos
+    #[serde(default)]
+    pub contacto: Option<String>,
+}
@@ -0,0 +1,228 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::inventario::InventarioError;
+use crate::models::{ConfiguracionStock, Movimiento, OrdenCompra, Producto, Proveedor};
+use crate::utils::file_io::{guardar_datos, cargar_datos, DatosInventario, ModoCarga};
+use super::{Restauracion, Storage};
+
+/// Almacenamiento en un archivo CSV o JSON (según la extensión).
+///
+/// Cada cambio reescribe el archivo completo, así que conviene para
+/// inventarios pequeños; para muchos productos usar `SqliteStorage`.
+pub struct CsvStorage {
+    ruta: String,
+    productos: BTreeMap<u32, Producto>,
+    movimientos: Vec<Movimiento>,
+    configuracion: ConfiguracionStock,
+    ubicaciones: Vec<String>,
+    proveedores: Vec<Proveedor>,
+    ordenes: Vec<OrdenCompra>,
+    siguiente_id: u32,
+    siguiente_proveedor_id: u32,
+    siguiente_orden_id: u32,
+}
+
+impl CsvStorage {
+    pub fn new(ruta: &str) -> Self {
+        Self {
+            ruta: ruta.to_string(),
+            productos: BTreeMap::new(),
+            movimientos: Vec::new(),
+            configuracion: ConfiguracionStock::default(),
+            ubicaciones: Vec::new(),
+            proveedores: Vec::new(),
+            ordenes: Vec::new(),
+            siguiente_id: 1,
+            siguiente_proveedor_id: 1,
+            siguiente_orden_id: 1,
+        }
+    }
+
+    // Escribir la copia en memoria al archivo
+    fn escribir(&self) -> Result<(), InventarioError> {
+        let datos = DatosInventario {
+            siguiente_id: self.siguiente_id,
+            productos: self.productos.values().cloned().collect(),
+            movimientos: self.movimientos.clone(),
+            configuracion: self.configuracion.clone(),
+            ubicaciones: self.ubicaciones.clone(),
+            proveedores: self.proveedores.clone(),
+            ordenes: self.ordenes.clone(),
+            siguiente_proveedor_id: self.siguiente_proveedor_id,
+            siguiente_orden_id: self.siguiente_orden_id,
+        };
+        guardar_datos(&self.ruta, &datos)
+    }
+}
+
+impl Storage for CsvStorage {
+    fn descripcion(&self) -> String {
+        format!("Archivo: {}", self.ruta)
+    }
+
+    fn cargar(&mut self) -> Result<DatosInventario, InventarioError> {
+        // Un archivo que todavía no existe es un inventario vacío
+        if !Path::new(&self.ruta).exists() {
+            return Ok(DatosInventario { siguiente_id: 1, ..Default::default() });
+        }
+
+        let (datos, _) = cargar_datos(&self.ruta, ModoCarga::Estricto)?;
+
+        self.productos = datos.productos.iter().map(|p| (p.id, p.clone())).collect();
+        self.movimientos = datos.movimientos.clone();
+        self.configuracion = datos.configuracion.clone();
+        self.ubicaciones = datos.ubicaciones.clone();
+        self.proveedores = datos.proveedores.clone();
+        self.ordenes = datos.ordenes.clone();
+        self.siguiente_id = datos.siguiente_id;
+        self.siguiente_proveedor_id = datos.siguiente_proveedor_id;
+        self.siguiente_orden_id = datos.siguiente_orden_id;
+
+        Ok(datos)
+    }
+
+    fn guardar_todo(&mut self, datos: &DatosInventario) -> Result<(), InventarioError> {
+        self.productos = datos.productos.iter().map(|p| (p.id, p.clone())).collect();
+        self.movimientos = datos.movimientos.clone();
+        self.configuracion = datos.configuracion.clone();
+        self.ubicaciones = datos.ubicaciones.clone();
+        self.proveedores = datos.proveedores.clone();
+        self.ordenes = datos.ordenes.clone();
+        self.siguiente_id = datos.siguiente_id;
+        self.siguiente_proveedor_id = datos.siguiente_proveedor_id;
+        self.siguiente_orden_id = datos.siguiente_orden_id;
+        self.escribir()
+    }
+
+    fn guardar_producto(
+        &mut self,
+        producto: &Producto,
+        siguiente_id: u32,
+        movimientos: &[Movimiento],
+    ) -> Result<(), InventarioError> {
+        let anterior = self.productos.insert(producto.id, producto.clone());
+        let siguiente_anterior = std::mem::replace(&mut self.siguiente_id, siguiente_id);
+        self.movimientos.extend(movimientos.iter().cloned());
+
+        // Si no se pudo escribir, deshacer el cambio en la copia
+        self.escribir().inspect_err(|_| {
+            match anterior {
+                Some(p) => self.productos.insert(p.id, p),
+                None => self.productos.remove(&producto.id),
+            };
+            self.siguiente_id = siguiente_anterior;
+            self.movimientos.truncate(self.movimientos.len() - movimientos.len());
+        })
+    }
+
+    fn guardar_productos(
+        &mut self,
+        productos: &[Producto],
+        siguiente_id: u32,
+        movimientos: &[Movimiento],
+    ) -> Result<(), InventarioError> {
+        let productos_anteriores = self.productos.clone();
+        let siguiente_anterior = std::mem::replace(&mut self.siguiente_id, siguiente_id);
+        for producto in productos {
+            self.productos.insert(producto.id, producto.clone());
+        }
+        self.movimientos.extend(movimientos.iter().cloned());
+
+        self.escribir().inspect_err(|_| {
+            self.productos = productos_anteriores;
+            self.siguiente_id = siguiente_anterior;
+            self.movimientos.truncate(self.movimientos.len() - movimientos.len());
+        })
+    }
+
+    fn restaurar_productos(
+        &mut self,
+        productos: &[Restauracion<'_>],
+        compras: Option<(&[Proveedor], &[OrdenCompra])>,
+        siguiente_id: u32,
+    ) -> Result<(), InventarioError> {
+        let productos_anteriores = self.productos.clone();
+        let movimientos_anteriores = self.movimientos.clone();
+        let proveedores_anteriores = self.proveedores.clone();
+        let ordenes_anteriores = self.ordenes.clone();
+        let siguiente_anterior = self.siguiente_id;
+
+        for restauracion in productos {
+            let id = restauracion.id;
+            self.productos.remove(&id);
+            self.movimientos.retain(|m| m.producto_id != id);
+            if let Some(producto) = restauracion.producto {
+                self.productos.insert(id, producto.clone());
+                self.movimientos.extend(restauracion.movimientos.iter().cloned());
+            }
+        }
+        if let Some((proveedores, ordenes)) = compras {
+            self.proveedores = proveedores.to_vec();
+            self.ordenes = ordenes.to_vec();
+        }
+        self.siguiente_id = siguiente_id;
+
+        self.escribir().inspect_err(|_| {
+            self.productos = productos_anteriores;
+            self.movimientos = movimientos_anteriores;
+            self.proveedores = proveedores_anteriores;
+            self.ordenes = ordenes_anteriores;
+            self.siguiente_id = siguiente_anterior;
+        })
+    }
+
+    fn guardar_configuracion(&mut self, configuracion: &ConfiguracionStock) -> Result<(), InventarioError> {
+        let anterior = std::mem::replace(&mut self.configuracion, configuracion.clone());
+        self.escribir().inspect_err(|_| self.configuracion = anterior)
+    }
+
+    fn guardar_ubicaciones(&mut self, ubicaciones: &[String]) -> Result<(), InventarioError> {
+        let anteriores = std::mem::replace(&mut self.ubicaciones, ubicaciones.to_vec());
+        self.escribir().inspect_err(|_| self.ubicaciones = anteriores)
+    }
+
+    fn guardar_compras(
+        &mut self,
+        proveedores: &[Proveedor],
+        ordenes: &[OrdenCompra],
+        siguiente_proveedor_id: u32,
+        siguiente_orden_id: u32,
+        productos: &[Producto],
+        movimientos: &[Movimiento],
+    ) -> Result<(), InventarioError> {
+        let proveedores_anteriores = std::mem::replace(&mut self.proveedores, proveedores.to_vec());
+        let ordenes_anteriores = std::mem::replace(&mut self.ordenes, ordenes.to_vec());
+        let siguientes_anteriores = (
+            std::mem::replace(&mut self.siguiente_proveedor_id, siguiente_proveedor_id),
+            std::mem::replace(&mut self.siguiente_orden_id, siguiente_orden_id),
+        );
+        let productos_anteriores: Vec<Producto> = productos.iter()
+            .filter_map(|p| self.productos.insert(p.id, p.clone()))
+            .collect();
+        self.movimientos.extend(movimientos.iter().cloned());
+
+        self.escribir().inspect_err(|_| {
+            self.proveedores = proveedores_anteriores;
+            self.ordenes = ordenes_anteriores;
+            (self.siguiente_proveedor_id, self.siguiente_orden_id) = siguientes_anteriores;
+            for producto in productos_anteriores {
+                self.productos.insert(producto.id, producto);
+            }
+            self.movimientos.truncate(self.movimientos.len() - movimientos.len());
+        })
+    }
+
+    fn eliminar_producto(&mut self, id: u32) -> Result<(), InventarioError> {
+        let anterior = self.productos.remove(&id);
+        let movimientos_anteriores = self.movimientos.clone();
+        self.movimientos.retain(|m| m.producto_id != id);
+
+        self.escribir().inspect_err(|_| {
+            if let Some(p) = anterior {
+                self.productos.insert(id, p);
+            }
+            self.movimientos = movimientos_anteriores;
+        })
+    }
+}
@@ -0,0 +1,421 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+
+use crate::inventario::InventarioError;
+use crate::models::{ConfiguracionStock, Movimiento, OrdenCompra, Producto, Proveedor};
+use crate::utils::file_io::{
+    cargar_datos, escribir_provisionales, reemplazar_con_provisionales, DatosInventario, ModoCarga,
+};
+use super::{Restauracion, Storage};
+
+/// Almacenamiento con diario de cambios, para la interfaz gráfica.
+///
+/// Cada cambio se agrega como una línea JSON al final del diario, sin
+/// reescribir el archivo del inventario; `sincronizar` vuelca todo al
+/// archivo y vacía el diario. Si el programa se cierra sin sincronizar,
+/// `cargar` aplica el diario sobre el archivo y no se pierde nada.
+/// Sin archivo (inventario todavía sin guardar) solo se usa el diario.
+///
+/// Antes de reemplazar el archivo se anota una marca de volcado: lo que
+/// está antes de la marca ya quedó en el archivo y no se vuelve a aplicar,
+/// y un volcado cortado a medias se termina al abrir.
+pub struct DiarioStorage {
+    archivo: Option<String>,
+    ruta_diario: String,
+    diario: File,
+    // Cambios escritos en el diario desde la última sincronización
+    pendientes: usize,
+}
+
+/// Un cambio tal como queda en el diario
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "operacion", rename_all = "snake_case")]
+enum EntradaDiario {
+    GuardarTodo { datos: DatosInventario },
+    GuardarProductos { productos: Vec<Producto>, siguiente_id: u32, movimientos: Vec<Movimiento> },
+    GuardarConfiguracion { configuracion: ConfiguracionStock },
+    GuardarUbicaciones { ubicaciones: Vec<String> },
+    GuardarCompras {
+        proveedores: Vec<Proveedor>,
+        ordenes: Vec<OrdenCompra>,
+        #[serde(default)]
+        siguiente_proveedor_id: u32,
+        #[serde(default)]
+        siguiente_orden_id: u32,
+        productos: Vec<Producto>,
+        movimientos: Vec<Movimiento>,
+    },
+    EliminarProducto { id: u32 },
+    /// El archivo ya tiene todos los cambios anteriores a esta entrada
+    Volcado,
+    RestaurarProductos {
+        productos: Vec<ProductoRestaurado>,
+        compras: Option<(Vec<Proveedor>, Vec<OrdenCompra>)>,
+        siguiente_id: u32,
+    },
+}
+
+/// Un producto dentro de una entrada `RestaurarProductos`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct ProductoRestaurado {
+    id: u32,
+    producto: Option<Producto>,
+    movimientos: Vec<Movimiento>,
+}
+
+impl DiarioStorage {
+    /// Abrir el diario en `ruta_diario` conservando lo que tenga, para que
+    /// `cargar` lo aplique sobre `archivo`
+    pub fn abrir(archivo: Option<&str>, ruta_diario: &str) -> Result<Self, InventarioError> {
+        if let Some(carpeta) = Path::new(ruta_diario).parent() {
+            fs::create_dir_all(carpeta)?;
+        }
+        let diario = OpenOptions::new().create(true).append(true).open(ruta_diario)?;
+
+        // Quitar una última línea incompleta, para no pegarle la siguiente
+        let contenido = fs::read(ruta_diario)?;
+        if !contenido.is_empty() && !contenido.ends_with(b"\n") {
+            let largo = contenido.iter().rposition(|&b| b == b'\n').map_or(0, |i| i + 1);
+            diario.set_len(largo as u64)?;
+        }
+
+        // Si el programa se cerró justo después de anotar un volcado, los
+        // archivos provisionales ya están completos: terminar de reemplazar
+        let mut entradas = leer_entradas(ruta_diario)?;
+        if let (Some(ruta), Some(EntradaDiario::Volcado)) = (archivo, entradas.last()) {
+            reemplazar_con_provisionales(ruta)?;
+            diario.set_len(0)?;
+            entradas.clear();
+        }
+        let pendientes = sin_volcar(entradas).len();
+
+        Ok(Self {
+            archivo: archivo.map(str::to_string),
+            ruta_diario: ruta_diario.to_string(),
+            diario,
+            pendientes,
+        })
+    }
+
+    /// Empezar un diario vacío, para un inventario que ya está en memoria
+    /// igual que en `archivo` (recién cargado o recién guardado)
+    pub fn nuevo(archivo: Option<&str>, ruta_diario: &str) -> Result<Self, InventarioError> {
+        let storage = Self::abrir(archivo, ruta_diario)?;
+        storage.diario.set_len(0)?;
+        Ok(Self { pendientes: 0, ..storage })
+    }
+
+    /// Cambios en el diario que todavía no están en el archivo
+    pub fn pendientes(&self) -> usize {
+        self.pendientes
+    }
+
+    // Método privado para agregar un cambio al final del diario
+    fn anotar(&mut self, entrada: EntradaDiario) -> Result<(), InventarioError> {
+        let linea = serde_json::to_string(&entrada)
+            .map_err(|e| InventarioError::Validacion(format!("no se pudo anotar el cambio: {}", e)))?;
+        writeln!(self.diario, "{}", linea)?;
+        self.diario.sync_data()?;
+        self.pendientes += 1;
+        Ok(())
+    }
+}
+
+impl Storage for DiarioStorage {
+    fn descripcion(&self) -> String {
+        match &self.archivo {
+            Some(ruta) => format!("Archivo: {} (con diario de cambios)", ruta),
+            None => format!("Diario: {} (inventario sin guardar)", self.ruta_diario),
+        }
+    }
+
+    fn cargar(&mut self) -> Result<DatosInventario, InventarioError> {
+        let mut datos = match &self.archivo {
+            // Igual que al abrirlo desde la interfaz, para que el diario se
+            // aplique sobre los mismos datos
+            Some(ruta) if Path::new(ruta).exists() => cargar_datos(ruta, ModoCarga::Tolerante)?.0,
+            _ => DatosInventario { siguiente_id: 1, ..Default::default() },
+        };
+
+        let entradas = sin_volcar(leer_entradas(&self.ruta_diario)?);
+        self.pendientes = entradas.len();
+        for entrada in entradas {
+            aplicar(&mut datos, entrada);
+        }
+
+        Ok(datos)
+    }
+
+    fn guardar_todo(&mut self, datos: &DatosInventario) -> Result<(), InventarioError> {
+        match self.archivo.clone() {
+            // El diario se vacía solo cuando todos los archivos quedaron reemplazados
+            Some(ruta) => {
+                escribir_provisionales(&ruta, datos)?;
+                self.anotar(EntradaDiario::Volcado)?;
+                reemplazar_con_provisionales(&ruta)?;
+                self.diario.set_len(0)?;
+                self.pendientes = 0;
+                Ok(())
+            }
+            // Sin archivo, el diario empieza con una copia completa
+            None => {
+                self.diario.set_len(0)?;
+                self.pendientes = 0;
+                self.anotar(EntradaDiario::GuardarTodo { datos: datos.clone() })
+            }
+        }
+    }
+
+    fn sincronizar(&mut self, datos: &DatosInventario) -> Result<(), InventarioError> {
+        if self.archivo.is_some() && self.pendientes > 0 {
+            self.guardar_todo(datos)?;
+        }
+        Ok(())
+    }
+
+    fn guardar_producto(
+        &mut self,
+        producto: &Producto,
+        siguiente_id: u32,
+        movimientos: &[Movimiento],
+    ) -> Result<(), InventarioError> {
+        self.guardar_productos(std::slice::from_ref(producto), siguiente_id, movimientos)
+    }
+
+    fn guardar_productos(
+        &mut self,
+        productos: &[Producto],
+        siguiente_id: u32,
+        movimientos: &[Movimiento],
+    ) -> Result<(), InventarioError> {
+        self.anotar(EntradaDiario::GuardarProductos {
+            productos: productos.to_vec(),
+            siguiente_id,
+            movimientos: movimientos.to_vec(),
+        })
+    }
+
+    fn guardar_configuracion(&mut self, configuracion: &ConfiguracionStock) -> Result<(), InventarioError> {
+        self.anotar(EntradaDiario::GuardarConfiguracion { configuracion: configuracion.clone() })
+    }
+
+    fn guardar_ubicaciones(&mut self, ubicaciones: &[String]) -> Result<(), InventarioError> {
+        self.anotar(EntradaDiario::GuardarUbicaciones { ubicaciones: ubicaciones.to_vec() })
+    }
+
+    fn guardar_compras(
+        &mut self,
+        proveedores: &[Proveedor],
+        ordenes: &[OrdenCompra],
+        siguiente_proveedor_id: u32,
+        siguiente_orden_id: u32,
+        productos: &[Producto],
+        movimientos: &[Movimiento],
+    ) -> Result<(), InventarioError> {
+        self.anotar(EntradaDiario::GuardarCompras {
+            proveedores: proveedores.to_vec(),
+            ordenes: ordenes.to_vec(),
+            siguiente_proveedor_id,
+            siguiente_orden_id,
+            productos: productos.to_vec(),
+            movimientos: movimientos.to_vec(),
+        })
+    }
+
+    fn eliminar_producto(&mut self, id: u32) -> Result<(), InventarioError> {
+        self.anotar(EntradaDiario::EliminarProducto { id })
+    }
+
+    fn restaurar_productos(
+        &mut self,
+        productos: &[Restauracion<'_>],
+        compras: Option<(&[Proveedor], &[OrdenCompra])>,
+        siguiente_id: u32,
+    ) -> Result<(), InventarioError> {
+        self.anotar(EntradaDiario::RestaurarProductos {
+            productos: productos.iter()
+                .map(|r| ProductoRestaurado {
+                    id: r.id,
+                    producto: r.producto.cloned(),
+                    movimientos: r.movimientos.to_vec(),
+                })
+                .collect(),
+            compras: compras.map(|(proveedores, ordenes)| (proveedores.to_vec(), ordenes.to_vec())),
+            siguiente_id,
+        })
+    }
+}
+
+/// Leer las entradas del diario. Una última línea incompleta (el programa
+/// se cerró mientras la escribía) se descarta.
+fn leer_entradas(ruta: &str) -> Result<Vec<EntradaDiario>, InventarioError> {
+    if !Path::new(ruta).exists() {
+        return Ok(Vec::new());
+    }
+
+    let contenido = fs::read_to_string(ruta)?;
+    let lineas: Vec<&str> = contenido.lines().filter(|l| !l.trim().is_empty()).collect();
+    let mut entradas = Vec::new();
+
+    for (i, linea) in lineas.iter().enumerate() {
+        match serde_json::from_str(linea) {
+            Ok(entrada) => entradas.push(entrada),
+            Err(_) if i + 1 == lineas.len() => break,
+            Err(e) => {
+                return Err(InventarioError::Parseo {
+                    linea: i + 1,
+                    columna: e.column(),
+                    mensaje: format!("entrada del diario inválida: {}", e),
+                });
+            }
+        }
+    }
+
+    Ok(entradas)
+}
+
+/// Las entradas posteriores a la última marca de volcado; las anteriores
+/// ya están en el archivo
+fn sin_volcar(mut entradas: Vec<EntradaDiario>) -> Vec<EntradaDiario> {
+    match entradas.iter().rposition(|e| *e == EntradaDiario::Volcado) {
+        Some(posicion) => entradas.split_off(posicion + 1),
+        None => entradas,
+    }
+}
+
+/// Aplicar un cambio del diario sobre los datos cargados
+fn aplicar(datos: &mut DatosInventario, entrada: EntradaDiario) {
+    let reemplazar = |datos: &mut DatosInventario, producto: Producto| {
+        match datos.productos.iter_mut().find(|p| p.id == producto.id) {
+            Some(actual) => *actual = producto,
+            None => datos.productos.push(producto),
+        }
+    };
+
+    match entrada {
+        EntradaDiario::GuardarTodo { datos: nuevos } => *datos = nuevos,
+        EntradaDiario::GuardarProductos { productos, siguiente_id, movimientos } => {
+            for producto in productos {
+                reemplazar(datos, producto);
+            }
+            datos.siguiente_id = siguiente_id;
+            datos.movimientos.extend(movimientos);
+        }
+        EntradaDiario::GuardarConfiguracion { configuracion } => datos.configuracion = configuracion,
+        EntradaDiario::Volcado => {}
+        EntradaDiario::GuardarUbicaciones { ubicaciones } => datos.ubicaciones = ubicaciones,
+        EntradaDiario::GuardarCompras { proveedores, ordenes, siguiente_proveedor_id, siguiente_orden_id, productos, movimientos } => {
+            datos.proveedores = proveedores;
+            datos.ordenes = ordenes;
+            // Los diarios anteriores a los contadores no los traen
+            datos.siguiente_proveedor_id = datos.siguiente_proveedor_id.max(siguiente_proveedor_id);
+            datos.siguiente_orden_id = datos.siguiente_orden_id.max(siguiente_orden_id);
+            for producto in productos {
+                reemplazar(datos, producto);
+            }
+            datos.movimientos.extend(movimientos);
+        }
+        EntradaDiario::EliminarProducto { id } => {
+            datos.productos.retain(|p| p.id != id);
+            datos.movimientos.retain(|m| m.producto_id != id);
+        }
+        EntradaDiario::RestaurarProductos { productos, compras, siguiente_id } => {
+            for ProductoRestaurado { id, producto, movimientos } in productos {
+                datos.productos.retain(|p| p.id != id);
+                datos.movimientos.retain(|m| m.producto_id != id);
+                if let Some(producto) = producto {
+                    datos.productos.push(producto);
+                    datos.movimientos.extend(movimientos);
+                }
+            }
+            if let Some((proveedores, ordenes)) = compras {
+                datos.proveedores = proveedores;
+                datos.ordenes = ordenes;
+            }
+            datos.siguiente_id = siguiente_id;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::inventario::InventarioManager;
+    use crate::models::{Dinero, Moneda, MotivoMovimiento};
+    use crate::utils::file_io::{archivos_del_inventario, ruta_diario, ruta_provisional};
+
+    #[test]
+    fn el_diario_recupera_los_cambios_sin_sincronizar() {
+        let archivo = std::env::temp_dir().join("rustiventario_diario.csv").to_string_lossy().to_string();
+        let ruta_diario = ruta_diario(&archivo);
+        let _ = fs::remove_file(&archivo);
+        let precio = Dinero::desde_unidades(5, Moneda::POR_DEFECTO);
+
+        let mut inventario = InventarioManager::con_almacenamiento(Box::new(
+            DiarioStorage::nuevo(Some(&archivo), &ruta_diario).unwrap(),
+        )).unwrap();
+        let id = inventario.agregar_producto("Lápiz".to_string(), precio, 10, Vec::new(), None).unwrap();
+        inventario.sincronizar().unwrap();
+        assert!(fs::read_to_string(&ruta_diario).unwrap().is_empty());
+
+        // Cambios que solo quedan en el diario, más una línea cortada a medias
+        inventario.registrar_movimiento(id, -3, MotivoMovimiento::Venta, None).unwrap();
+        let borrado = inventario.agregar_producto("Goma".to_string(), precio, 1, Vec::new(), None).unwrap();
+        inventario.eliminar_producto(borrado).unwrap();
+        inventario.agregar_ubicacion("Bodega").unwrap();
+        drop(inventario);
+        let mut diario = OpenOptions::new().append(true).open(&ruta_diario).unwrap();
+        write!(diario, "{{\"operacion\":\"guardar_pro").unwrap();
+
+        let storage = DiarioStorage::abrir(Some(&archivo), &ruta_diario).unwrap();
+        assert_eq!(storage.pendientes(), 4);
+        let recuperado = InventarioManager::con_almacenamiento(Box::new(storage)).unwrap();
+        let productos = recuperado.obtener_todos_productos();
+        assert_eq!(productos.len(), 1);
+        assert_eq!(productos[0].stock, 7);
+        assert_eq!(recuperado.movimientos_de(id).len(), 2);
+        assert_eq!(recuperado.ubicaciones(), ["Bodega"]);
+
+        for ruta in [archivo.clone(), ruta_diario] {
+            fs::remove_file(ruta).unwrap();
+        }
+        for sufijo in ["movimientos", "umbrales", "ubicaciones", "proveedores", "ordenes"] {
+            let _ = fs::remove_file(Path::new(&archivo).with_extension(format!("{}.csv", sufijo)));
+        }
+    }
+
+    #[test]
+    fn un_volcado_cortado_se_termina_sin_repetir_movimientos() {
+        let archivo = std::env::temp_dir().join("rustiventario_volcado.csv").to_string_lossy().to_string();
+        let ruta_diario = ruta_diario(&archivo);
+        let _ = fs::remove_file(&archivo);
+        let precio = Dinero::desde_unidades(5, Moneda::POR_DEFECTO);
+
+        let mut inventario = InventarioManager::con_almacenamiento(Box::new(
+            DiarioStorage::nuevo(Some(&archivo), &ruta_diario).unwrap(),
+        )).unwrap();
+        let id = inventario.agregar_producto("Lápiz".to_string(), precio, 10, Vec::new(), None).unwrap();
+        inventario.sincronizar().unwrap();
+        inventario.registrar_movimiento(id, -3, MotivoMovimiento::Venta, None).unwrap();
+
+        // Un volcado que se cortó con la marca anotada y solo el CSV de
+        // productos reemplazado
+        escribir_provisionales(&archivo, &inventario.datos()).unwrap();
+        drop(inventario);
+        let mut diario = OpenOptions::new().append(true).open(&ruta_diario).unwrap();
+        writeln!(diario, "{}", serde_json::to_string(&EntradaDiario::Volcado).unwrap()).unwrap();
+        fs::rename(ruta_provisional(&archivo), &archivo).unwrap();
+
+        let storage = DiarioStorage::abrir(Some(&archivo), &ruta_diario).unwrap();
+        assert_eq!(storage.pendientes(), 0);
+        let recuperado = InventarioManager::con_almacenamiento(Box::new(storage)).unwrap();
+        assert_eq!(recuperado.obtener_producto(id).unwrap().stock, 7);
+        assert_eq!(recuperado.movimientos_de(id).len(), 2);
+
+        for ruta in archivos_del_inventario(&archivo).into_iter().chain([ruta_diario]) {
+            fs::remove_file(ruta).unwrap();
+        }
+    }
+}
@@ -0,0 +1,95 @@
+// Backends de persistencia que el gestor de inventario puede usar
+
+pub mod csv;
+pub mod diario;
+pub mod sqlite;
+
+pub use self::csv::CsvStorage;
+pub use self::diario::DiarioStorage;
+pub use self::sqlite::SqliteStorage;
+
+use crate::inventario::InventarioError;
+use crate::models::{ConfiguracionStock, Movimiento, OrdenCompra, Producto, Proveedor};
+use crate::utils::file_io::DatosInventario;
+
+/// Persistencia del inventario usada por `InventarioManager`.
+///
+/// El gestor llama a `guardar_producto` y `eliminar_producto` en cada cambio,
+/// antes de aplicarlo en memoria, así un error deja ambos lados iguales.
+pub trait Storage: Send {
+    /// Descripción corta para mostrar en la interfaz
+    fn descripcion(&self) -> String;
+
+    /// Leer todo el inventario guardado
+    fn cargar(&mut self) -> Result<DatosInventario, InventarioError>;
+
+    /// Reemplazar todo el contenido guardado
+    fn guardar_todo(&mut self, datos: &DatosInventario) -> Result<(), InventarioError>;
+
+    /// Volcar los cambios acumulados, para los almacenamientos que no
+    /// escriben todo en cada cambio. `datos` es el inventario completo.
+    fn sincronizar(&mut self, _datos: &DatosInventario) -> Result<(), InventarioError> {
+        Ok(())
+    }
+
+    /// Insertar o actualizar un producto, junto con los movimientos de stock
+    /// que lo causaron (dos en una transferencia, o ninguno)
+    fn guardar_producto(
+        &mut self,
+        producto: &Producto,
+        siguiente_id: u32,
+        movimientos: &[Movimiento],
+    ) -> Result<(), InventarioError>;
+
+    /// Guardar varios productos con sus movimientos en un solo paso: o se
+    /// guardan todos o ninguno. Se usa en las ventas de varios productos.
+    fn guardar_productos(
+        &mut self,
+        productos: &[Producto],
+        siguiente_id: u32,
+        movimientos: &[Movimiento],
+    ) -> Result<(), InventarioError>;
+
+    /// Guardar los puntos de reorden global y por tag
+    fn guardar_configuracion(&mut self, configuracion: &ConfiguracionStock) -> Result<(), InventarioError>;
+
+    /// Reemplazar la lista de ubicaciones
+    fn guardar_ubicaciones(&mut self, ubicaciones: &[String]) -> Result<(), InventarioError>;
+
+    /// Reemplazar los proveedores y las órdenes de compra, con los
+    /// próximos IDs de cada uno. Al recibir una orden también se guardan
+    /// los productos que cambiaron de stock, con sus movimientos, en el
+    /// mismo paso.
+    fn guardar_compras(
+        &mut self,
+        proveedores: &[Proveedor],
+        ordenes: &[OrdenCompra],
+        siguiente_proveedor_id: u32,
+        siguiente_orden_id: u32,
+        productos: &[Producto],
+        movimientos: &[Movimiento],
+    ) -> Result<(), InventarioError>;
+
+    /// Eliminar un producto y su historial de movimientos
+    fn eliminar_producto(&mut self, id: u32) -> Result<(), InventarioError>;
+
+    /// Dejar varios productos exactamente como se indica, con todo su
+    /// historial, en un solo paso: o se restauran todos o ninguno. Con
+    /// `compras` también se reemplazan los proveedores y las órdenes.
+    /// Se usa al deshacer.
+    fn restaurar_productos(
+        &mut self,
+        productos: &[Restauracion<'_>],
+        compras: Option<(&[Proveedor], &[OrdenCompra])>,
+        siguiente_id: u32,
+    ) -> Result<(), InventarioError>;
+}
+
+/// Estado en que queda un producto al deshacer o rehacer un cambio
+pub struct Restauracion<'a> {
+    pub id: u32,
+    /// `None` deja el producto eliminado
+    pub producto: Option<&'a Producto>,
+    /// Historial completo del producto
+    pub movimientos: &'a [Movimiento],
+}
@@ -0,0 +1,783 @@
+use std::collections::{BTreeMap, HashMap};
+use rusqlite::{params, Connection, OptionalExtension};
+
+use crate::inventario::InventarioError;
+use crate::models::{
+    ConfiguracionStock, Dinero, EstadoOrden, LineaOrden, Moneda, Movimiento, MotivoMovimiento, OrdenCompra, Producto,
+    Proveedor, Variante,
+};
+use crate::utils::file_io::DatosInventario;
+use super::{Restauracion, Storage};
+
+/// Almacenamiento en una base SQLite embebida.
+///
+/// Cada cambio se confirma en su propia transacción, así que un cierre
+/// inesperado no pierde nada de lo ya aplicado.
+pub struct SqliteStorage {
+    ruta: String,
+    conexion: Connection,
+}
+
+impl SqliteStorage {
+    /// Abrir (o crear) la base en `ruta`
+    pub fn abrir(ruta: &str) -> Result<Self, InventarioError> {
+        let conexion = Connection::open(ruta).map_err(error_sqlite)?;
+        Self::inicializar(ruta, conexion)
+    }
+
+    /// Base en memoria, útil para pruebas
+    #[cfg(test)]
+    pub fn en_memoria() -> Result<Self, InventarioError> {
+        let conexion = Connection::open_in_memory().map_err(error_sqlite)?;
+        Self::inicializar(":memory:", conexion)
+    }
+
+    fn inicializar(ruta: &str, conexion: Connection) -> Result<Self, InventarioError> {
+        conexion.execute_batch(
+            "PRAGMA foreign_keys = ON;
+             CREATE TABLE IF NOT EXISTS productos (
+                 id     INTEGER PRIMARY KEY,
+                 nombre TEXT NOT NULL,
+                 precio INTEGER NOT NULL,
+                 stock  INTEGER NOT NULL,
+                 punto_reorden INTEGER,
+                 sku           TEXT,
+                 codigo_barras TEXT,
+                 precio_centavos INTEGER,
+                 moneda          TEXT,
+                 costo_centavos  INTEGER,
+                 proveedor_id    INTEGER,
+                 variante_de     INTEGER,
+                 precio_propio   INTEGER NOT NULL DEFAULT 0
+             );
+             CREATE TABLE IF NOT EXISTS producto_tags (
+                 producto_id INTEGER NOT NULL REFERENCES productos(id) ON DELETE CASCADE,
+                 tag         TEXT NOT NULL,
+                 PRIMARY KEY (producto_id, tag)
+             );
+             CREATE TABLE IF NOT EXISTS movimientos (
+                 id          INTEGER PRIMARY KEY AUTOINCREMENT,
+                 producto_id INTEGER NOT NULL REFERENCES productos(id) ON DELETE CASCADE,
+                 fecha       INTEGER NOT NULL,
+                 delta       INTEGER NOT NULL,
+                 motivo      TEXT NOT NULL,
+                 nota        TEXT,
+                 ubicacion   TEXT,
+                 costo_centavos INTEGER,
+                 costo_moneda   TEXT
+             );
+             CREATE TABLE IF NOT EXISTS ubicaciones (
+                 nombre TEXT PRIMARY KEY
+             );
+             CREATE TABLE IF NOT EXISTS existencias (
+                 producto_id INTEGER NOT NULL REFERENCES productos(id) ON DELETE CASCADE,
+                 ubicacion   TEXT NOT NULL,
+                 cantidad    INTEGER NOT NULL,
+                 PRIMARY KEY (producto_id, ubicacion)
+             );
+             CREATE TABLE IF NOT EXISTS atributos_variante (
+                 producto_id INTEGER NOT NULL REFERENCES productos(id) ON DELETE CASCADE,
+                 atributo    TEXT NOT NULL,
+                 valor       TEXT NOT NULL,
+                 PRIMARY KEY (producto_id, atributo)
+             );
+             CREATE TABLE IF NOT EXISTS umbrales_tag (
+                 tag           TEXT PRIMARY KEY,
+                 punto_reorden INTEGER NOT NULL
+             );
+             CREATE TABLE IF NOT EXISTS proveedores (
+                 id       INTEGER PRIMARY KEY,
+                 nombre   TEXT NOT NULL,
+                 contacto TEXT
+             );
+             CREATE TABLE IF NOT EXISTS ordenes (
+                 id           INTEGER PRIMARY KEY,
+                 proveedor_id INTEGER NOT NULL,
+                 fecha        INTEGER NOT NULL,
+                 estado       TEXT NOT NULL,
+                 nota         TEXT
+             );
+             CREATE TABLE IF NOT EXISTS lineas_orden (
+                 orden_id    INTEGER NOT NULL REFERENCES ordenes(id) ON DELETE CASCADE,
+                 posicion    INTEGER NOT NULL,
+                 producto_id INTEGER NOT NULL,
+                 cantidad    INTEGER NOT NULL,
+                 recibida    INTEGER NOT NULL,
+                 costo_centavos INTEGER,
+                 costo_moneda   TEXT,
+                 PRIMARY KEY (orden_id, posicion)
+             );
+             CREATE TABLE IF NOT EXISTS meta (
+                 clave TEXT PRIMARY KEY,
+                 valor INTEGER NOT NULL
+             );",
+        ).map_err(error_sqlite)?;
+
+        // Las bases creadas con versiones anteriores no tienen estas columnas
+        agregar_columna_si_falta(&conexion, "productos", "punto_reorden", "INTEGER")?;
+        agregar_columna_si_falta(&conexion, "movimientos", "ubicacion", "TEXT")?;
+        agregar_columna_si_falta(&conexion, "productos", "sku", "TEXT")?;
+        agregar_columna_si_falta(&conexion, "productos", "codigo_barras", "TEXT")?;
+        agregar_columna_si_falta(&conexion, "productos", "precio_centavos", "INTEGER")?;
+        agregar_columna_si_falta(&conexion, "productos", "moneda", "TEXT")?;
+        agregar_columna_si_falta(&conexion, "productos", "costo_centavos", "INTEGER")?;
+        agregar_columna_si_falta(&conexion, "movimientos", "costo_centavos", "INTEGER")?;
+        agregar_columna_si_falta(&conexion, "movimientos", "costo_moneda", "TEXT")?;
+        agregar_columna_si_falta(&conexion, "productos", "proveedor_id", "INTEGER")?;
+        agregar_columna_si_falta(&conexion, "productos", "variante_de", "INTEGER")?;
+        agregar_columna_si_falta(&conexion, "productos", "precio_propio", "INTEGER NOT NULL DEFAULT 0")?;
+
+        Ok(Self { ruta: ruta.to_string(), conexion })
+    }
+}
+
+impl Storage for SqliteStorage {
+    fn descripcion(&self) -> String {
+        format!("SQLite: {}", self.ruta)
+    }
+
+    fn cargar(&mut self) -> Result<DatosInventario, InventarioError> {
+        let mut productos: HashMap<u32, Producto> = HashMap::new();
+
+        let mut consulta = self.conexion
+            .prepare(
+                "SELECT id, nombre, precio, stock, punto_reorden, sku, codigo_barras, precio_centavos, moneda, costo_centavos,
+                     proveedor_id, variante_de, precio_propio
+                 FROM productos",
+            )
+            .map_err(error_sqlite)?;
+        let filas = consulta.query_map([], |fila| {
+            let moneda = match fila.get::<_, Option<String>>(8)? {
+                Some(codigo) => Moneda::parsear(&codigo).map_err(|e| {
+                    rusqlite::Error::FromSqlConversionFailure(8, rusqlite::types::Type::Text, e.into())
+                })?,
+                None => Moneda::POR_DEFECTO,
+            };
+            // Las filas de versiones anteriores solo tienen el precio en unidades
+            let precio = match fila.get::<_, Option<u64>>(7)? {
+                Some(centavos) => Dinero::new(centavos, moneda),
+                None => Dinero::desde_unidades(fila.get(2)?, moneda),
+            };
+
+            Ok(Producto {
+                id: fila.get(0)?,
+                nombre: fila.get(1)?,
+                precio,
+                stock: fila.get(3)?,
+                tags: Default::default(),
+                punto_reorden: fila.get(4)?,
+                existencias: Default::default(),
+                sku: fila.get(5)?,
+                codigo_barras: fila.get(6)?,
+                // El costo está en la moneda del precio
+                costo: fila.get::<_, Option<u64>>(9)?.map(|centavos| Dinero::new(centavos, moneda)),
+                proveedor_id: fila.get(10)?,
+                // Los atributos se leen después, de su propia tabla
+                variante: match fila.get::<_, Option<u32>>(11)? {
+                    Some(padre_id) => Some(Variante { padre_id, atributos: Default::default(), precio_propio: fila.get(12)? }),
+                    None => None,
+                },
+            })
+        }).map_err(error_sqlite)?;
+        for producto in filas {
+            let producto = producto.map_err(error_sqlite)?;
+            productos.insert(producto.id, producto);
+        }
+
+        let mut consulta = self.conexion
+            .prepare("SELECT producto_id, tag FROM producto_tags")
+            .map_err(error_sqlite)?;
+        let filas = consulta.query_map([], |fila| Ok((fila.get::<_, u32>(0)?, fila.get::<_, String>(1)?)))
+            .map_err(error_sqlite)?;
+        for fila in filas {
+            let (id, tag) = fila.map_err(error_sqlite)?;
+            if let Some(producto) = productos.get_mut(&id) {
+                producto.tags.insert(tag);
+            }
+        }
+
+        let mut consulta = self.conexion
+            .prepare("SELECT producto_id, ubicacion, cantidad FROM existencias")
+            .map_err(error_sqlite)?;
+        let filas = consulta
+            .query_map([], |fila| Ok((fila.get::<_, u32>(0)?, fila.get::<_, String>(1)?, fila.get::<_, u32>(2)?)))
+            .map_err(error_sqlite)?;
+        for fila in filas {
+            let (id, ubicacion, cantidad) = fila.map_err(error_sqlite)?;
+            if let Some(producto) = productos.get_mut(&id) {
+                producto.existencias.insert(ubicacion, cantidad);
+            }
+        }
+
+        let mut consulta = self.conexion
+            .prepare("SELECT producto_id, atributo, valor FROM atributos_variante")
+            .map_err(error_sqlite)?;
+        let filas = consulta
+            .query_map([], |fila| Ok((fila.get::<_, u32>(0)?, fila.get::<_, String>(1)?, fila.get::<_, String>(2)?)))
+            .map_err(error_sqlite)?;
+        for fila in filas {
+            let (id, atributo, valor) = fila.map_err(error_sqlite)?;
+            if let Some(variante) = productos.get_mut(&id).and_then(|p| p.variante.as_mut()) {
+                variante.atributos.insert(atributo, valor);
+            }
+        }
+
+        let mut consulta = self.conexion
+            .prepare(
+                "SELECT producto_id, fecha, delta, motivo, nota, ubicacion, costo_centavos, costo_moneda
+                 FROM movimientos ORDER BY id",
+            )
+            .map_err(error_sqlite)?;
+        let filas = consulta.query_map([], |fila| {
+            let costo = (fila.get::<_, Option<u64>>(6)?, fila.get::<_, Option<String>>(7)?);
+            Ok((fila.get(0)?, fila.get(1)?, fila.get(2)?, fila.get::<_, String>(3)?, fila.get(4)?, fila.get(5)?, costo))
+        }).map_err(error_sqlite)?;
+        let mut movimientos = Vec::new();
+        for fila in filas {
+            let (producto_id, fecha, delta, motivo, nota, ubicacion, costo) = fila.map_err(error_sqlite)?;
+            let motivo = MotivoMovimiento::desde_clave(&motivo).ok_or_else(|| {
+                InventarioError::Validacion(format!("motivo desconocido en la base: \"{}\"", motivo))
+            })?;
+            let costo_unitario = match costo {
+                (Some(centavos), moneda) => {
+                    let moneda = match moneda {
+                        Some(codigo) => Moneda::parsear(&codigo).map_err(InventarioError::Validacion)?,
+                        None => Moneda::POR_DEFECTO,
+                    };
+                    Some(Dinero::new(centavos, moneda))
+                }
+                (None, _) => None,
+            };
+            movimientos.push(Movimiento { producto_id, fecha, delta, motivo, nota, ubicacion, costo_unitario });
+        }
+
+        let mut consulta = self.conexion
+            .prepare("SELECT nombre FROM ubicaciones ORDER BY nombre")
+            .map_err(error_sqlite)?;
+        let ubicaciones = consulta
+            .query_map([], |fila| fila.get(0))
+            .map_err(error_sqlite)?
+            .collect::<Result<Vec<String>, _>>()
+            .map_err(error_sqlite)?;
+
+        let mut consulta = self.conexion
+            .prepare("SELECT id, nombre, contacto FROM proveedores ORDER BY id")
+            .map_err(error_sqlite)?;
+        let proveedores = consulta
+            .query_map([], |fila| Ok(Proveedor { id: fila.get(0)?, nombre: fila.get(1)?, contacto: fila.get(2)? }))
+            .map_err(error_sqlite)?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(error_sqlite)?;
+
+        let mut ordenes: BTreeMap<u32, OrdenCompra> = BTreeMap::new();
+        let mut consulta = self.conexion
+            .prepare("SELECT id, proveedor_id, fecha, estado, nota FROM ordenes")
+            .map_err(error_sqlite)?;
+        let filas = consulta
+            .query_map([], |fila| {
+                Ok((fila.get(0)?, fila.get(1)?, fila.get(2)?, fila.get::<_, String>(3)?, fila.get(4)?))
+            })
+            .map_err(error_sqlite)?;
+        for fila in filas {
+            let (id, proveedor_id, fecha, estado, nota) = fila.map_err(error_sqlite)?;
+            let estado = EstadoOrden::desde_clave(&estado).ok_or_else(|| {
+                InventarioError::Validacion(format!("estado de orden desconocido en la base: \"{}\"", estado))
+            })?;
+            ordenes.insert(id, OrdenCompra { id, proveedor_id, fecha, estado, lineas: Vec::new(), nota });
+        }
+
+        let mut consulta = self.conexion
+            .prepare(
+                "SELECT orden_id, producto_id, cantidad, recibida, costo_centavos, costo_moneda
+                 FROM lineas_orden ORDER BY orden_id, posicion",
+            )
+            .map_err(error_sqlite)?;
+        let filas = consulta
+            .query_map([], |fila| {
+                let costo = (fila.get::<_, Option<u64>>(4)?, fila.get::<_, Option<String>>(5)?);
+                Ok((fila.get::<_, u32>(0)?, fila.get(1)?, fila.get(2)?, fila.get(3)?, costo))
+            })
+            .map_err(error_sqlite)?;
+        for fila in filas {
+            let (orden_id, producto_id, cantidad, recibida, costo) = fila.map_err(error_sqlite)?;
+            let costo_unitario = match costo {
+                (Some(centavos), moneda) => {
+                    let moneda = match moneda {
+                        Some(codigo) => Moneda::parsear(&codigo).map_err(InventarioError::Validacion)?,
+                        None => Moneda::POR_DEFECTO,
+                    };
+                    Some(Dinero::new(centavos, moneda))
+                }
+                (None, _) => None,
+            };
+            if let Some(orden) = ordenes.get_mut(&orden_id) {
+                orden.lineas.push(LineaOrden { producto_id, cantidad, recibida, costo_unitario });
+            }
+        }
+
+        let siguiente_id: Option<u32> = self.conexion
+            .query_row("SELECT valor FROM meta WHERE clave = 'siguiente_id'", [], |fila| fila.get(0))
+            .optional()
+            .map_err(error_sqlite)?;
+        let maximo = productos.keys().map(|id| id + 1).max().unwrap_or(1);
+        let siguiente_proveedor_id: Option<u32> = self.conexion
+            .query_row("SELECT valor FROM meta WHERE clave = 'siguiente_proveedor_id'", [], |fila| fila.get(0))
+            .optional()
+            .map_err(error_sqlite)?;
+        let siguiente_orden_id: Option<u32> = self.conexion
+            .query_row("SELECT valor FROM meta WHERE clave = 'siguiente_orden_id'", [], |fila| fila.get(0))
+            .optional()
+            .map_err(error_sqlite)?;
+
+        let mut configuracion = ConfiguracionStock::default();
+        let global: Option<u32> = self.conexion
+            .query_row("SELECT valor FROM meta WHERE clave = 'punto_reorden_global'", [], |fila| fila.get(0))
+            .optional()
+            .map_err(error_sqlite)?;
+        if let Some(global) = global {
+            configuracion.punto_reorden_global = global;
+        }
+        let mut consulta = self.conexion
+            .prepare("SELECT tag, punto_reorden FROM umbrales_tag")
+            .map_err(error_sqlite)?;
+        let filas = consulta.query_map([], |fila| Ok((fila.get(0)?, fila.get(1)?)))
+            .map_err(error_sqlite)?;
+        configuracion.punto_reorden_por_tag = filas
+            .collect::<Result<BTreeMap<String, u32>, _>>()
+            .map_err(error_sqlite)?;
+
+        let mut productos: Vec<Producto> = productos.into_values().collect();
+        productos.sort_by_key(|p| p.id);
+
+        Ok(DatosInventario {
+            siguiente_id: siguiente_id.unwrap_or(1).max(maximo),
+            productos,
+            movimientos,
+            configuracion,
+            ubicaciones,
+            siguiente_proveedor_id: siguiente_proveedor_id.unwrap_or(1).max(proveedores.iter().map(|p| p.id + 1).max().unwrap_or(1)),
+            siguiente_orden_id: siguiente_orden_id.unwrap_or(1).max(ordenes.keys().map(|id| id + 1).max().unwrap_or(1)),
+            proveedores,
+            ordenes: ordenes.into_values().collect(),
+        })
+    }
+
+    fn guardar_todo(&mut self, datos: &DatosInventario) -> Result<(), InventarioError> {
+        let tx = self.conexion.transaction().map_err(error_sqlite)?;
+
+        tx.execute("DELETE FROM productos", []).map_err(error_sqlite)?;
+        for producto in &datos.productos {
+            insertar_producto(&tx, producto)?;
+        }
+        for movimiento in &datos.movimientos {
+            insertar_movimiento(&tx, movimiento)?;
+        }
+        guardar_siguiente_id(&tx, datos.siguiente_id)?;
+        escribir_configuracion(&tx, &datos.configuracion)?;
+        escribir_ubicaciones(&tx, &datos.ubicaciones)?;
+        escribir_compras(&tx, &datos.proveedores, &datos.ordenes)?;
+        guardar_contador(&tx, "siguiente_proveedor_id", datos.siguiente_proveedor_id)?;
+        guardar_contador(&tx, "siguiente_orden_id", datos.siguiente_orden_id)?;
+
+        tx.commit().map_err(error_sqlite)
+    }
+
+    fn guardar_producto(
+        &mut self,
+        producto: &Producto,
+        siguiente_id: u32,
+        movimientos: &[Movimiento],
+    ) -> Result<(), InventarioError> {
+        let tx = self.conexion.transaction().map_err(error_sqlite)?;
+
+        escribir_producto(&tx, producto)?;
+        for movimiento in movimientos {
+            insertar_movimiento(&tx, movimiento)?;
+        }
+        guardar_siguiente_id(&tx, siguiente_id)?;
+
+        tx.commit().map_err(error_sqlite)
+    }
+
+    fn guardar_productos(
+        &mut self,
+        productos: &[Producto],
+        siguiente_id: u32,
+        movimientos: &[Movimiento],
+    ) -> Result<(), InventarioError> {
+        let tx = self.conexion.transaction().map_err(error_sqlite)?;
+
+        for producto in productos {
+            escribir_producto(&tx, producto)?;
+        }
+        for movimiento in movimientos {
+            insertar_movimiento(&tx, movimiento)?;
+        }
+        guardar_siguiente_id(&tx, siguiente_id)?;
+
+        tx.commit().map_err(error_sqlite)
+    }
+
+    fn guardar_configuracion(&mut self, configuracion: &ConfiguracionStock) -> Result<(), InventarioError> {
+        let tx = self.conexion.transaction().map_err(error_sqlite)?;
+        escribir_configuracion(&tx, configuracion)?;
+        tx.commit().map_err(error_sqlite)
+    }
+
+    fn guardar_ubicaciones(&mut self, ubicaciones: &[String]) -> Result<(), InventarioError> {
+        let tx = self.conexion.transaction().map_err(error_sqlite)?;
+        escribir_ubicaciones(&tx, ubicaciones)?;
+        tx.commit().map_err(error_sqlite)
+    }
+
+    fn guardar_compras(
+        &mut self,
+        proveedores: &[Proveedor],
+        ordenes: &[OrdenCompra],
+        siguiente_proveedor_id: u32,
+        siguiente_orden_id: u32,
+        productos: &[Producto],
+        movimientos: &[Movimiento],
+    ) -> Result<(), InventarioError> {
+        let tx = self.conexion.transaction().map_err(error_sqlite)?;
+
+        escribir_compras(&tx, proveedores, ordenes)?;
+        guardar_contador(&tx, "siguiente_proveedor_id", siguiente_proveedor_id)?;
+        guardar_contador(&tx, "siguiente_orden_id", siguiente_orden_id)?;
+        for producto in productos {
+            // Al recibir solo cambian el stock, las existencias y el costo
+            tx.execute(
+                "UPDATE productos SET stock = ?2, costo_centavos = ?3 WHERE id = ?1",
+                params![producto.id, producto.stock, producto.costo.map(|c| c.centavos)],
+            ).map_err(error_sqlite)?;
+            tx.execute("DELETE FROM existencias WHERE producto_id = ?1", params![producto.id])
+                .map_err(error_sqlite)?;
+            for (ubicacion, cantidad) in &producto.existencias {
+                tx.execute(
+                    "INSERT INTO existencias (producto_id, ubicacion, cantidad) VALUES (?1, ?2, ?3)",
+                    params![producto.id, ubicacion, cantidad],
+                ).map_err(error_sqlite)?;
+            }
+        }
+        for movimiento in movimientos {
+            insertar_movimiento(&tx, movimiento)?;
+        }
+
+        tx.commit().map_err(error_sqlite)
+    }
+
+    fn eliminar_producto(&mut self, id: u32) -> Result<(), InventarioError> {
+        self.conexion
+            .execute("DELETE FROM productos WHERE id = ?1", params![id])
+            .map_err(error_sqlite)?;
+        Ok(())
+    }
+
+    fn restaurar_productos(
+        &mut self,
+        productos: &[Restauracion<'_>],
+        compras: Option<(&[Proveedor], &[OrdenCompra])>,
+        siguiente_id: u32,
+    ) -> Result<(), InventarioError> {
+        let tx = self.conexion.transaction().map_err(error_sqlite)?;
+
+        // Las tags y movimientos anteriores se borran en cascada
+        for restauracion in productos {
+            tx.execute("DELETE FROM productos WHERE id = ?1", params![restauracion.id])
+                .map_err(error_sqlite)?;
+        }
+        for restauracion in productos {
+            if let Some(producto) = restauracion.producto {
+                insertar_producto(&tx, producto)?;
+                for movimiento in restauracion.movimientos {
+                    insertar_movimiento(&tx, movimiento)?;
+                }
+            }
+        }
+        if let Some((proveedores, ordenes)) = compras {
+            escribir_compras(&tx, proveedores, ordenes)?;
+        }
+        guardar_siguiente_id(&tx, siguiente_id)?;
+
+        tx.commit().map_err(error_sqlite)
+    }
+}
+
+// Insertar un producto o actualizarlo en el lugar, con sus tags y existencias
+fn escribir_producto(conexion: &Connection, producto: &Producto) -> Result<(), InventarioError> {
+    // Actualizar en el lugar para no perder los movimientos en cascada
+    let actualizados = conexion.execute(
+        "UPDATE productos SET nombre = ?2, precio = ?3, stock = ?4, punto_reorden = ?5, sku = ?6, codigo_barras = ?7,
+             precio_centavos = ?8, moneda = ?9, costo_centavos = ?10, proveedor_id = ?11, variante_de = ?12,
+             precio_propio = ?13
+         WHERE id = ?1",
+        params![
+            producto.id,
+            producto.nombre,
+            producto.precio.unidades(),
+            producto.stock,
+            producto.punto_reorden,
+            producto.sku,
+            producto.codigo_barras,
+            producto.precio.centavos,
+            producto.precio.moneda.codigo(),
+            producto.costo.map(|c| c.centavos),
+            producto.proveedor_id,
+            producto.variante.as_ref().map(|v| v.padre_id),
+            producto.variante.as_ref().is_some_and(|v| v.precio_propio),
+        ],
+    ).map_err(error_sqlite)?;
+    if actualizados == 0 {
+        insertar_producto(conexion, producto)?;
+    } else {
+        conexion.execute("DELETE FROM producto_tags WHERE producto_id = ?1", params![producto.id])
+            .map_err(error_sqlite)?;
+        conexion.execute("DELETE FROM existencias WHERE producto_id = ?1", params![producto.id])
+            .map_err(error_sqlite)?;
+        conexion.execute("DELETE FROM atributos_variante WHERE producto_id = ?1", params![producto.id])
+            .map_err(error_sqlite)?;
+        insertar_tags(conexion, producto)?;
+    }
+
+    Ok(())
+}
+
+fn insertar_producto(conexion: &Connection, producto: &Producto) -> Result<(), InventarioError> {
+    conexion.execute(
+        "INSERT INTO productos (id, nombre, precio, stock, punto_reorden, sku, codigo_barras, precio_centavos, moneda, costo_centavos,
+             proveedor_id, variante_de, precio_propio)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+        params![
+            producto.id,
+            producto.nombre,
+            producto.precio.unidades(),
+            producto.stock,
+            producto.punto_reorden,
+            producto.sku,
+            producto.codigo_barras,
+            producto.precio.centavos,
+            producto.precio.moneda.codigo(),
+            producto.costo.map(|c| c.centavos),
+            producto.proveedor_id,
+            producto.variante.as_ref().map(|v| v.padre_id),
+            producto.variante.as_ref().is_some_and(|v| v.precio_propio),
+        ],
+    ).map_err(error_sqlite)?;
+
+    insertar_tags(conexion, producto)
+}
+
+// Tags, existencias por ubicación y atributos de variante, que viven en
+// sus propias tablas
+fn insertar_tags(conexion: &Connection, producto: &Producto) -> Result<(), InventarioError> {
+    for tag in &producto.tags {
+        conexion.execute(
+            "INSERT INTO producto_tags (producto_id, tag) VALUES (?1, ?2)",
+            params![producto.id, tag],
+        ).map_err(error_sqlite)?;
+    }
+    for (ubicacion, cantidad) in &producto.existencias {
+        conexion.execute(
+            "INSERT INTO existencias (producto_id, ubicacion, cantidad) VALUES (?1, ?2, ?3)",
+            params![producto.id, ubicacion, cantidad],
+        ).map_err(error_sqlite)?;
+    }
+    for (atributo, valor) in producto.variante.iter().flat_map(|v| &v.atributos) {
+        conexion.execute(
+            "INSERT INTO atributos_variante (producto_id, atributo, valor) VALUES (?1, ?2, ?3)",
+            params![producto.id, atributo, valor],
+        ).map_err(error_sqlite)?;
+    }
+
+    Ok(())
+}
+
+fn insertar_movimiento(conexion: &Connection, movimiento: &Movimiento) -> Result<(), InventarioError> {
+    conexion.execute(
+        "INSERT INTO movimientos (producto_id, fecha, delta, motivo, nota, ubicacion, costo_centavos, costo_moneda)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![
+            movimiento.producto_id,
+            movimiento.fecha,
+            movimiento.delta,
+            movimiento.motivo.clave(),
+            movimiento.nota,
+            movimiento.ubicacion,
+            movimiento.costo_unitario.map(|c| c.centavos),
+            movimiento.costo_unitario.map(|c| c.moneda.codigo().to_string()),
+        ],
+    ).map_err(error_sqlite)?;
+    Ok(())
+}
+
+fn guardar_siguiente_id(conexion: &Connection, siguiente_id: u32) -> Result<(), InventarioError> {
+    guardar_contador(conexion, "siguiente_id", siguiente_id)
+}
+
+// Guardar en `meta` el próximo ID libre de algo
+fn guardar_contador(conexion: &Connection, clave: &str, valor: u32) -> Result<(), InventarioError> {
+    conexion.execute(
+        "INSERT INTO meta (clave, valor) VALUES (?1, ?2)
+         ON CONFLICT(clave) DO UPDATE SET valor = excluded.valor",
+        params![clave, valor],
+    ).map_err(error_sqlite)?;
+    Ok(())
+}
+
+fn escribir_configuracion(conexion: &Connection, configuracion: &ConfiguracionStock) -> Result<(), InventarioError> {
+    conexion.execute(
+        "INSERT INTO meta (clave, valor) VALUES ('punto_reorden_global', ?1)
+         ON CONFLICT(clave) DO UPDATE SET valor = excluded.valor",
+        params![configuracion.punto_reorden_global],
+    ).map_err(error_sqlite)?;
+
+    conexion.execute("DELETE FROM umbrales_tag", []).map_err(error_sqlite)?;
+    for (tag, punto) in &configuracion.punto_reorden_por_tag {
+        conexion.execute(
+            "INSERT INTO umbrales_tag (tag, punto_reorden) VALUES (?1, ?2)",
+            params![tag, punto],
+        ).map_err(error_sqlite)?;
+    }
+
+    Ok(())
+}
+
+fn escribir_ubicaciones(conexion: &Connection, ubicaciones: &[String]) -> Result<(), InventarioError> {
+    conexion.execute("DELETE FROM ubicaciones", []).map_err(error_sqlite)?;
+    for nombre in ubicaciones {
+        conexion.execute("INSERT OR IGNORE INTO ubicaciones (nombre) VALUES (?1)", params![nombre])
+            .map_err(error_sqlite)?;
+    }
+    Ok(())
+}
+
+fn escribir_compras(
+    conexion: &Connection,
+    proveedores: &[Proveedor],
+    ordenes: &[OrdenCompra],
+) -> Result<(), InventarioError> {
+    conexion.execute("DELETE FROM proveedores", []).map_err(error_sqlite)?;
+    for proveedor in proveedores {
+        conexion.execute(
+            "INSERT INTO proveedores (id, nombre, contacto) VALUES (?1, ?2, ?3)",
+            params![proveedor.id, proveedor.nombre, proveedor.contacto],
+        ).map_err(error_sqlite)?;
+    }
+
+    // Las líneas se borran en cascada
+    conexion.execute("DELETE FROM ordenes", []).map_err(error_sqlite)?;
+    for orden in ordenes {
+        conexion.execute(
+            "INSERT INTO ordenes (id, proveedor_id, fecha, estado, nota) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![orden.id, orden.proveedor_id, orden.fecha, orden.estado.clave(), orden.nota],
+        ).map_err(error_sqlite)?;
+        for (posicion, linea) in orden.lineas.iter().enumerate() {
+            conexion.execute(
+                "INSERT INTO lineas_orden (orden_id, posicion, producto_id, cantidad, recibida, costo_centavos, costo_moneda)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![
+                    orden.id,
+                    posicion,
+                    linea.producto_id,
+                    linea.cantidad,
+                    linea.recibida,
+                    linea.costo_unitario.map(|c| c.centavos),
+                    linea.costo_unitario.map(|c| c.moneda.codigo().to_string()),
+                ],
+            ).map_err(error_sqlite)?;
+        }
+    }
+
+    Ok(())
+}
+
+// Método privado para migrar bases viejas agregando una columna nueva
+fn agregar_columna_si_falta(
+    conexion: &Connection,
+    tabla: &str,
+    columna: &str,
+    tipo: &str,
+) -> Result<(), InventarioError> {
+    let existe: bool = conexion
+        .query_row(
+            "SELECT COUNT(*) > 0 FROM pragma_table_info(?1) WHERE name = ?2",
+            params![tabla, columna],
+            |fila| fila.get(0),
+        )
+        .map_err(error_sqlite)?;
+    if !existe {
+        conexion.execute(&format!("ALTER TABLE {} ADD COLUMN {} {}", tabla, columna, tipo), [])
+            .map_err(error_sqlite)?;
+    }
+    Ok(())
+}
+
+// Los errores de SQLite se tratan como errores de entrada/salida
+fn error_sqlite(e: rusqlite::Error) -> InventarioError {
+    InventarioError::Io(std::io::Error::other(e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn producto(id: u32, nombre: &str, stock: u32, tags: &[&str]) -> Producto {
+        Producto {
+            id,
+            nombre: nombre.to_string(),
+            precio: Dinero::new(10_050, Moneda::POR_DEFECTO),
+            stock,
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn cada_cambio_queda_guardado() {
+        let mut storage = SqliteStorage::en_memoria().unwrap();
+
+        let venta = Movimiento {
+            producto_id: 1,
+            fecha: 1_700_000_000,
+            delta: -6,
+            motivo: MotivoMovimiento::Venta,
+            nota: Some("mostrador".to_string()),
+            ubicacion: Some("Tienda".to_string()),
+            costo_unitario: None,
+        };
+
+        storage.guardar_producto(&producto(1, "Laptop", 10, &["a", "b"]), 2, &[]).unwrap();
+        storage.guardar_producto(&producto(2, "Mouse", 3, &["b"]), 3, &[]).unwrap();
+        let mut laptop = producto(1, "Laptop", 4, &["c"]);
+        laptop.existencias.insert("Tienda".to_string(), 3);
+        laptop.sku = Some("LAP-1".to_string());
+        laptop.costo = Some(Dinero::new(7_000, Moneda::POR_DEFECTO));
+        laptop.variante = Some(Variante {
+            padre_id: 2,
+            atributos: [("color".to_string(), "gris".to_string())].into(),
+            precio_propio: true,
+        });
+        storage.guardar_producto(&laptop, 3, std::slice::from_ref(&venta)).unwrap();
+        storage.guardar_ubicaciones(&["Bodega".to_string(), "Tienda".to_string()]).unwrap();
+        storage.eliminar_producto(2).unwrap();
+
+        let proveedores = [Proveedor { id: 1, nombre: "Mayorista".to_string(), contacto: None }];
+        let mut orden = OrdenCompra::nueva(1, 1, vec![LineaOrden::new(1, 5, Some(Dinero::new(6_500, Moneda::POR_DEFECTO)))], None);
+        orden.estado = EstadoOrden::RecibidaParcial;
+        orden.lineas[0].recibida = 2;
+        let compra = Movimiento { delta: 2, motivo: MotivoMovimiento::Compra, nota: None, ubicacion: None, ..venta.clone() };
+        laptop.stock += 2;
+        laptop.proveedor_id = Some(1);
+        storage.guardar_producto(&laptop, 3, &[]).unwrap();
+        storage.guardar_compras(&proveedores, std::slice::from_ref(&orden), 4, 2, std::slice::from_ref(&laptop), std::slice::from_ref(&compra)).unwrap();
+
+        let datos = storage.cargar().unwrap();
+        assert_eq!(datos.productos, vec![laptop]);
+        assert_eq!(datos.movimientos, vec![venta, compra]);
+        assert_eq!(datos.proveedores, proveedores);
+        assert_eq!(datos.ordenes, vec![orden]);
+        assert_eq!(datos.ubicaciones, ["Bodega", "Tienda"]);
+        assert_eq!(datos.siguiente_id, 3);
+        assert_eq!((datos.siguiente_proveedor_id, datos.siguiente_orden_id), (4, 2));
+    }
+}
@@ -0,0 +1,1555 @@
+use std::fs::{self, File};
+use std::io::Write;
+use std::collections::{BTreeMap, HashSet};
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+
+use crate::inventario::InventarioError;
+use crate::inventario::reportes::{ReporteValuacion, Totales};
+use crate::inventario::ventas::Venta;
+use crate::models::codigo::{clave_codigo_barras, clave_sku, validar_codigo_barras, validar_sku};
+use crate::models::{
+    ConfiguracionStock, Dinero, EstadoOrden, FormatoMoneda, LineaOrden, Moneda, Movimiento, MotivoMovimiento, OrdenCompra,
+    Producto, Proveedor, Variante,
+};
+
+/// Encabezado que se escribe al inicio de cada archivo CSV
+const ENCABEZADO: [&str; 15] = [
+    "id", "nombre", "precio", "stock", "tags", "punto_reorden", "existencias", "sku", "codigo_barras", "moneda", "costo",
+    "proveedor_id", "variante_de", "atributos", "precio_propio",
+];
+
+/// Encabezado del archivo CSV de puntos de reorden por defecto
+const ENCABEZADO_UMBRALES: [&str; 3] = ["ambito", "tag", "punto_reorden"];
+
+/// Encabezado del archivo CSV de movimientos de stock
+const ENCABEZADO_MOVIMIENTOS: [&str; 8] = [
+    "producto_id", "fecha", "delta", "motivo", "nota", "ubicacion", "costo_unitario", "moneda",
+];
+
+/// Encabezado del CSV de un reporte de valuación
+const ENCABEZADO_REPORTE: [&str; 10] = [
+    "tipo", "id", "nombre", "stock", "precio", "costo_unitario", "valor_costo", "valor_venta", "margen", "moneda",
+];
+
+/// Encabezado del archivo CSV de ubicaciones
+const ENCABEZADO_UBICACIONES: [&str; 1] = ["nombre"];
+
+/// Encabezado del archivo CSV de proveedores
+const ENCABEZADO_PROVEEDORES: [&str; 3] = ["id", "nombre", "contacto"];
+
+/// Encabezado del archivo CSV de órdenes de compra, con una línea por
+/// producto pedido
+const ENCABEZADO_ORDENES: [&str; 10] = [
+    "orden_id", "proveedor_id", "fecha", "estado", "nota", "producto_id", "cantidad", "recibida", "costo_unitario", "moneda",
+];
+
+/// Formatos de archivo soportados para guardar y cargar el inventario
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FormatoArchivo {
+    Csv,
+    Json,
+}
+
+impl FormatoArchivo {
+    /// Elegir el formato según la extensión; si no es `.json` se usa CSV
+    pub fn desde_ruta(ruta: &str) -> Self {
+        match Path::new(ruta).extension().and_then(|e| e.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("json") => FormatoArchivo::Json,
+            _ => FormatoArchivo::Csv,
+        }
+    }
+}
+
+/// Todo lo que se guarda de un inventario
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DatosInventario {
+    pub siguiente_id: u32,
+    pub productos: Vec<Producto>,
+    #[serde(default)]
+    pub movimientos: Vec<Movimiento>,
+    #[serde(default)]
+    pub configuracion: ConfiguracionStock,
+    /// Ubicaciones registradas (tiendas, bodegas...), aunque no tengan stock
+    #[serde(default)]
+    pub ubicaciones: Vec<String>,
+    #[serde(default)]
+    pub proveedores: Vec<Proveedor>,
+    #[serde(default)]
+    pub ordenes: Vec<OrdenCompra>,
+    /// Próximos IDs de proveedor y de orden. No bajan al eliminar, así un
+    /// ID no se vuelve a usar; en CSV se toma el mayor guardado más uno.
+    #[serde(default)]
+    pub siguiente_proveedor_id: u32,
+    #[serde(default)]
+    pub siguiente_orden_id: u32,
+}
+
+/// Guardar el inventario completo (CSV o JSON según la extensión).
+/// En CSV los movimientos, los puntos de reorden, las ubicaciones y las
+/// compras van en archivos aparte, ver `ruta_movimientos`, `ruta_umbrales`,
+/// `ruta_ubicaciones`, `ruta_proveedores` y `ruta_ordenes`.
+///
+/// Todo se escribe primero en archivos provisionales (ver
+/// `ruta_provisional`) que después reemplazan a los anteriores, así un
+/// error a mitad de camino no deja ningún archivo cortado.
+pub fn guardar_datos(ruta: &str, datos: &DatosInventario) -> Result<(), InventarioError> {
+    escribir_provisionales(ruta, datos)?;
+    reemplazar_con_provisionales(ruta)
+}
+
+/// Archivos que forman un inventario guardado: el de `ruta` y, en CSV,
+/// los que lo acompañan
+pub fn archivos_del_inventario(ruta: &str) -> Vec<String> {
+    match FormatoArchivo::desde_ruta(ruta) {
+        FormatoArchivo::Csv => vec![
+            ruta.to_string(),
+            ruta_movimientos(ruta),
+            ruta_umbrales(ruta),
+            ruta_ubicaciones(ruta),
+            ruta_proveedores(ruta),
+            ruta_ordenes(ruta),
+        ],
+        FormatoArchivo::Json => vec![ruta.to_string()],
+    }
+}
+
+/// Escribir el inventario completo en los archivos provisionales, ya
+/// volcados al disco, sin tocar los actuales
+pub fn escribir_provisionales(ruta: &str, datos: &DatosInventario) -> Result<(), InventarioError> {
+    match FormatoArchivo::desde_ruta(ruta) {
+        FormatoArchivo::Csv => {
+            let productos: Vec<&Producto> = datos.productos.iter().collect();
+            guardar_en_archivo(&ruta_provisional(ruta), &productos)?;
+            guardar_movimientos(&ruta_provisional(&ruta_movimientos(ruta)), &datos.movimientos)?;
+            guardar_umbrales(&ruta_provisional(&ruta_umbrales(ruta)), &datos.configuracion)?;
+            guardar_ubicaciones(&ruta_provisional(&ruta_ubicaciones(ruta)), &datos.ubicaciones)?;
+            guardar_proveedores(&ruta_provisional(&ruta_proveedores(ruta)), &datos.proveedores)?;
+            guardar_ordenes(&ruta_provisional(&ruta_ordenes(ruta)), &datos.ordenes)?;
+        }
+        FormatoArchivo::Json => guardar_en_json(&ruta_provisional(ruta), datos)?,
+    }
+
+    for archivo in archivos_del_inventario(ruta) {
+        File::open(ruta_provisional(&archivo))?.sync_all()?;
+    }
+    Ok(())
+}
+
+/// Reemplazar cada archivo del inventario por su provisional, si lo tiene
+pub fn reemplazar_con_provisionales(ruta: &str) -> Result<(), InventarioError> {
+    for archivo in archivos_del_inventario(ruta) {
+        let provisional = ruta_provisional(&archivo);
+        if Path::new(&provisional).exists() {
+            fs::rename(&provisional, &archivo)?;
+        }
+    }
+    Ok(())
+}
+
+/// Cargar el inventario completo (CSV o JSON según la extensión)
+pub fn cargar_datos(ruta: &str, modo: ModoCarga) -> Result<(DatosInventario, ReporteCarga), InventarioError> {
+    match FormatoArchivo::desde_ruta(ruta) {
+        FormatoArchivo::Csv => {
+            let (productos, reporte) = cargar_desde_archivo(ruta, modo)?;
+
+            // Los inventarios anteriores al historial no tienen archivo de movimientos
+            let ruta_mov = ruta_movimientos(ruta);
+            let movimientos = if Path::new(&ruta_mov).exists() {
+                cargar_movimientos(&ruta_mov)?
+            } else {
+                Vec::new()
+            };
+
+            let ruta_umb = ruta_umbrales(ruta);
+            let configuracion = if Path::new(&ruta_umb).exists() {
+                cargar_umbrales(&ruta_umb)?
+            } else {
+                ConfiguracionStock::default()
+            };
+
+            let ruta_ubi = ruta_ubicaciones(ruta);
+            let ubicaciones = if Path::new(&ruta_ubi).exists() {
+                cargar_ubicaciones(&ruta_ubi)?
+            } else {
+                Vec::new()
+            };
+
+            let ruta_prov = ruta_proveedores(ruta);
+            let proveedores = if Path::new(&ruta_prov).exists() {
+                cargar_proveedores(&ruta_prov)?
+            } else {
+                Vec::new()
+            };
+
+            let ruta_ord = ruta_ordenes(ruta);
+            let ordenes = if Path::new(&ruta_ord).exists() {
+                cargar_ordenes(&ruta_ord)?
+            } else {
+                Vec::new()
+            };
+
+            let siguiente_id = productos.iter().map(|p| p.id + 1).max().unwrap_or(1);
+            let siguiente_proveedor_id = proveedores.iter().map(|p| p.id + 1).max().unwrap_or(1);
+            let siguiente_orden_id = ordenes.iter().map(|o| o.id + 1).max().unwrap_or(1);
+            let datos = DatosInventario {
+                siguiente_id,
+                productos,
+                movimientos,
+                configuracion,
+                ubicaciones,
+                proveedores,
+                ordenes,
+                siguiente_proveedor_id,
+                siguiente_orden_id,
+            };
+            Ok((datos, reporte))
+        }
+        FormatoArchivo::Json => {
+            let datos = cargar_desde_json(ruta)?;
+            let reporte = ReporteCarga { productos_cargados: datos.productos.len(), ..Default::default() };
+            Ok((datos, reporte))
+        }
+    }
+}
+
+/// Archivo de movimientos que acompaña a un CSV de productos:
+/// `inventario.csv` guarda su historial en `inventario.movimientos.csv`
+pub fn ruta_movimientos(ruta: &str) -> String {
+    Path::new(ruta)
+        .with_extension("movimientos.csv")
+        .to_string_lossy()
+        .to_string()
+}
+
+/// Archivo de puntos de reorden que acompaña a un CSV de productos:
+/// `inventario.csv` los guarda en `inventario.umbrales.csv`
+pub fn ruta_umbrales(ruta: &str) -> String {
+    Path::new(ruta)
+        .with_extension("umbrales.csv")
+        .to_string_lossy()
+        .to_string()
+}
+
+/// Archivo de ubicaciones que acompaña a un CSV de productos:
+/// `inventario.csv` las guarda en `inventario.ubicaciones.csv`
+pub fn ruta_ubicaciones(ruta: &str) -> String {
+    Path::new(ruta)
+        .with_extension("ubicaciones.csv")
+        .to_string_lossy()
+        .to_string()
+}
+
+/// Archivo de proveedores que acompaña a un CSV de productos:
+/// `inventario.csv` los guarda en `inventario.proveedores.csv`
+pub fn ruta_proveedores(ruta: &str) -> String {
+    Path::new(ruta)
+        .with_extension("proveedores.csv")
+        .to_string_lossy()
+        .to_string()
+}
+
+/// Archivo de órdenes de compra que acompaña a un CSV de productos:
+/// `inventario.csv` las guarda en `inventario.ordenes.csv`
+pub fn ruta_ordenes(ruta: &str) -> String {
+    Path::new(ruta)
+        .with_extension("ordenes.csv")
+        .to_string_lossy()
+        .to_string()
+}
+
+/// Archivo donde se escribe `ruta` antes de reemplazarlo:
+/// `inventario.csv` se escribe primero en `inventario.csv.tmp`
+pub fn ruta_provisional(ruta: &str) -> String {
+    format!("{}.tmp", ruta)
+}
+
+/// Diario de cambios sin volcar de un inventario abierto en la interfaz:
+/// `inventario.csv` (o `.json`) lo lleva en `inventario.diario.jsonl`
+pub fn ruta_diario(ruta: &str) -> String {
+    Path::new(ruta)
+        .with_extension("diario.jsonl")
+        .to_string_lossy()
+        .to_string()
+}
+
+/// Guardar productos en un archivo CSV (RFC 4180)
+pub fn guardar_en_archivo(ruta: &str, productos: &[&Producto]) -> Result<(), InventarioError> {
+    let mut archivo = File::create(ruta)?;
+
+    archivo.write_all(escribir_registro(&ENCABEZADO).as_bytes())?;
+    archivo.write_all(b"\r\n")?;
+
+    for producto in productos {
+        // Las tags se codifican como un registro CSV dentro de un único campo,
+        // así una tag con comas o comillas sobrevive la ida y vuelta
+        let mut tags: Vec<&str> = producto.tags.iter().map(String::as_str).collect();
+        tags.sort_unstable();
+        let tags_str = escribir_tags(&tags);
+
+        let punto_reorden = producto.punto_reorden.map(|p| p.to_string()).unwrap_or_default();
+
+        // Las existencias van igual que las tags, como `ubicación=cantidad`
+        let existencias: Vec<String> = producto.existencias.iter()
+            .map(|(ubicacion, cantidad)| format!("{}={}", ubicacion, cantidad))
+            .collect();
+        let existencias: Vec<&str> = existencias.iter().map(String::as_str).collect();
+        let existencias_str = escribir_tags(&existencias);
+
+        // El costo va en la moneda del precio
+        let costo = producto.costo.map(|c| c.monto()).unwrap_or_default();
+        let proveedor_id = producto.proveedor_id.map(|p| p.to_string()).unwrap_or_default();
+
+        // Los atributos de una variante van como `atributo=valor`
+        let (variante_de, atributos, precio_propio) = match &producto.variante {
+            Some(variante) => {
+                let atributos: Vec<String> = variante.atributos.iter()
+                    .map(|(atributo, valor)| format!("{}={}", atributo, valor))
+                    .collect();
+                let atributos: Vec<&str> = atributos.iter().map(String::as_str).collect();
+                let precio_propio = if variante.precio_propio { "si" } else { "" };
+                (variante.padre_id.to_string(), escribir_tags(&atributos), precio_propio)
+            }
+            None => Default::default(),
+        };
+
+        let linea = escribir_registro(&[
+            producto.id.to_string().as_str(),
+            producto.nombre.as_str(),
+            producto.precio.monto().as_str(),
+            producto.stock.to_string().as_str(),
+            tags_str.as_str(),
+            punto_reorden.as_str(),
+            existencias_str.as_str(),
+            producto.sku.as_deref().unwrap_or(""),
+            producto.codigo_barras.as_deref().unwrap_or(""),
+            producto.precio.moneda.codigo(),
+            costo.as_str(),
+            proveedor_id.as_str(),
+            variante_de.as_str(),
+            atributos.as_str(),
+            precio_propio,
+        ]);
+        archivo.write_all(linea.as_bytes())?;
+        archivo.write_all(b"\r\n")?;
+    }
+
+    Ok(())
+}
+
+/// Guardar movimientos de stock en un archivo CSV
+pub fn guardar_movimientos(ruta: &str, movimientos: &[Movimiento]) -> Result<(), InventarioError> {
+    let mut archivo = File::create(ruta)?;
+
+    archivo.write_all(escribir_registro(&ENCABEZADO_MOVIMIENTOS).as_bytes())?;
+    archivo.write_all(b"\r\n")?;
+
+    for movimiento in movimientos {
+        let costo_unitario = movimiento.costo_unitario.map(|c| c.monto()).unwrap_or_default();
+        let linea = escribir_registro(&[
+            movimiento.producto_id.to_string().as_str(),
+            movimiento.fecha.to_string().as_str(),
+            movimiento.delta.to_string().as_str(),
+            movimiento.motivo.clave(),
+            movimiento.nota.as_deref().unwrap_or(""),
+            movimiento.ubicacion.as_deref().unwrap_or(""),
+            costo_unitario.as_str(),
+            movimiento.costo_unitario.as_ref().map(|c| c.moneda.codigo()).unwrap_or(""),
+        ]);
+        archivo.write_all(linea.as_bytes())?;
+        archivo.write_all(b"\r\n")?;
+    }
+
+    Ok(())
+}
+
+/// Cargar movimientos de stock desde un archivo CSV
+pub fn cargar_movimientos(ruta: &str) -> Result<Vec<Movimiento>, InventarioError> {
+    let contenido = fs::read_to_string(ruta)?;
+    let mut movimientos = Vec::new();
+
+    for (i, registro) in leer_registros(&contenido)?.into_iter().enumerate() {
+        let partes = &registro.campos;
+        let error = |columna: usize, mensaje: String| InventarioError::Parseo {
+            linea: registro.linea,
+            columna,
+            mensaje,
+        };
+
+        if i == 0 && partes.first().map(String::as_str) == Some(ENCABEZADO_MOVIMIENTOS[0]) {
+            continue;
+        }
+
+        if partes.len() < 5 {
+            return Err(error(partes.len() + 1, format!("se esperaban 5 campos y hay {}", partes.len())));
+        }
+
+        let producto_id = partes[0].trim().parse()
+            .map_err(|_| error(1, format!("producto_id inválido: \"{}\"", partes[0])))?;
+        let fecha = partes[1].trim().parse()
+            .map_err(|_| error(2, format!("fecha inválida: \"{}\"", partes[1])))?;
+        let delta = partes[2].trim().parse()
+            .map_err(|_| error(3, format!("delta inválido: \"{}\"", partes[2])))?;
+        let motivo = MotivoMovimiento::desde_clave(partes[3].trim())
+            .ok_or_else(|| error(4, format!("motivo desconocido: \"{}\"", partes[3])))?;
+        let nota = Some(partes[4].clone()).filter(|n| !n.is_empty());
+        // Los archivos anteriores a las ubicaciones no tienen esta columna
+        let ubicacion = partes.get(5).cloned().filter(|u| !u.is_empty());
+        // Ni las anteriores a los costos estas dos
+        let costo_unitario = match partes.get(6).map(|c| c.trim()).filter(|c| !c.is_empty()) {
+            Some(campo) => {
+                let moneda = match partes.get(7).map(|m| m.trim()).filter(|m| !m.is_empty()) {
+                    Some(codigo) => Moneda::parsear(codigo).map_err(|motivo| error(8, motivo))?,
+                    None => Moneda::POR_DEFECTO,
+                };
+                Some(Dinero::parsear(campo, moneda).map_err(|motivo| error(7, format!("costo_unitario: {}", motivo)))?)
+            }
+            None => None,
+        };
+
+        movimientos.push(Movimiento { producto_id, fecha, delta, motivo, nota, ubicacion, costo_unitario });
+    }
+
+    Ok(movimientos)
+}
+
+/// Guardar los puntos de reorden global y por tag en un archivo CSV
+pub fn guardar_umbrales(ruta: &str, configuracion: &ConfiguracionStock) -> Result<(), InventarioError> {
+    let mut archivo = File::create(ruta)?;
+
+    archivo.write_all(escribir_registro(&ENCABEZADO_UMBRALES).as_bytes())?;
+    archivo.write_all(b"\r\n")?;
+
+    let global = configuracion.punto_reorden_global.to_string();
+    archivo.write_all(escribir_registro(&["global", "", global.as_str()]).as_bytes())?;
+    archivo.write_all(b"\r\n")?;
+
+    for (tag, punto) in &configuracion.punto_reorden_por_tag {
+        let linea = escribir_registro(&["tag", tag.as_str(), punto.to_string().as_str()]);
+        archivo.write_all(linea.as_bytes())?;
+        archivo.write_all(b"\r\n")?;
+    }
+
+    Ok(())
+}
+
+/// Cargar los puntos de reorden global y por tag desde un archivo CSV
+pub fn cargar_umbrales(ruta: &str) -> Result<ConfiguracionStock, InventarioError> {
+    let contenido = fs::read_to_string(ruta)?;
+    let mut configuracion = ConfiguracionStock::default();
+
+    for (i, registro) in leer_registros(&contenido)?.into_iter().enumerate() {
+        let partes = &registro.campos;
+        let error = |columna: usize, mensaje: String| InventarioError::Parseo {
+            linea: registro.linea,
+            columna,
+            mensaje,
+        };
+
+        if i == 0 && partes.first().map(String::as_str) == Some(ENCABEZADO_UMBRALES[0]) {
+            continue;
+        }
+
+        if partes.len() < 3 {
+            return Err(error(partes.len() + 1, format!("se esperaban 3 campos y hay {}", partes.len())));
+        }
+
+        let punto = partes[2].trim().parse()
+            .map_err(|_| error(3, format!("punto_reorden inválido: \"{}\"", partes[2])))?;
+
+        match partes[0].trim() {
+            "global" => configuracion.punto_reorden_global = punto,
+            "tag" => {
+                configuracion.punto_reorden_por_tag.insert(partes[1].clone(), punto);
+            }
+            otro => return Err(error(1, format!("ámbito desconocido: \"{}\"", otro))),
+        }
+    }
+
+    Ok(configuracion)
+}
+
+/// Guardar los nombres de las ubicaciones en un archivo CSV
+pub fn guardar_ubicaciones(ruta: &str, ubicaciones: &[String]) -> Result<(), InventarioError> {
+    let mut archivo = File::create(ruta)?;
+
+    archivo.write_all(escribir_registro(&ENCABEZADO_UBICACIONES).as_bytes())?;
+    archivo.write_all(b"\r\n")?;
+
+    for ubicacion in ubicaciones {
+        archivo.write_all(escribir_registro(&[ubicacion.as_str()]).as_bytes())?;
+        archivo.write_all(b"\r\n")?;
+    }
+
+    Ok(())
+}
+
+/// Guardar los proveedores en un archivo CSV
+pub fn guardar_proveedores(ruta: &str, proveedores: &[Proveedor]) -> Result<(), InventarioError> {
+    let mut archivo = File::create(ruta)?;
+
+    archivo.write_all(escribir_registro(&ENCABEZADO_PROVEEDORES).as_bytes())?;
+    archivo.write_all(b"\r\n")?;
+
+    for proveedor in proveedores {
+        let linea = escribir_registro(&[
+            proveedor.id.to_string().as_str(),
+            proveedor.nombre.as_str(),
+            proveedor.contacto.as_deref().unwrap_or(""),
+        ]);
+        archivo.write_all(linea.as_bytes())?;
+        archivo.write_all(b"\r\n")?;
+    }
+
+    Ok(())
+}
+
+/// Cargar los proveedores desde un archivo CSV
+pub fn cargar_proveedores(ruta: &str) -> Result<Vec<Proveedor>, InventarioError> {
+    let contenido = fs::read_to_string(ruta)?;
+    let mut proveedores = Vec::new();
+
+    for registro in leer_registros(&contenido)?.into_iter().skip(1) {
+        let partes = &registro.campos;
+        if partes.len() < 2 {
+            return Err(InventarioError::Parseo {
+                linea: registro.linea,
+                columna: partes.len() + 1,
+                mensaje: format!("se esperaban 2 campos y hay {}", partes.len()),
+            });
+        }
+
+        let id = parsear_numero(&registro, 0, "id")
+            .map_err(|mensaje| InventarioError::Parseo { linea: registro.linea, columna: 1, mensaje })?;
+        let contacto = partes.get(2).cloned().filter(|c| !c.is_empty());
+        proveedores.push(Proveedor { id, nombre: partes[1].clone(), contacto });
+    }
+
+    Ok(proveedores)
+}
+
+/// Guardar las órdenes de compra en un archivo CSV. Cada línea de una orden
+/// repite los datos de la orden; una orden sin líneas ocupa una fila con el
+/// producto vacío.
+pub fn guardar_ordenes(ruta: &str, ordenes: &[OrdenCompra]) -> Result<(), InventarioError> {
+    let mut archivo = File::create(ruta)?;
+
+    archivo.write_all(escribir_registro(&ENCABEZADO_ORDENES).as_bytes())?;
+    archivo.write_all(b"\r\n")?;
+
+    for orden in ordenes {
+        let id = orden.id.to_string();
+        let proveedor_id = orden.proveedor_id.to_string();
+        let fecha = orden.fecha.to_string();
+        let cabecera = [
+            id.as_str(),
+            proveedor_id.as_str(),
+            fecha.as_str(),
+            orden.estado.clave(),
+            orden.nota.as_deref().unwrap_or(""),
+        ];
+
+        if orden.lineas.is_empty() {
+            let linea = escribir_registro(&[&cabecera[..], &["", "", "", "", ""]].concat());
+            archivo.write_all(linea.as_bytes())?;
+            archivo.write_all(b"\r\n")?;
+        }
+
+        for linea_orden in &orden.lineas {
+            let costo_unitario = linea_orden.costo_unitario.map(|c| c.monto()).unwrap_or_default();
+            let linea = escribir_registro(&[&cabecera[..], &[
+                linea_orden.producto_id.to_string().as_str(),
+                linea_orden.cantidad.to_string().as_str(),
+                linea_orden.recibida.to_string().as_str(),
+                costo_unitario.as_str(),
+                linea_orden.costo_unitario.as_ref().map(|c| c.moneda.codigo()).unwrap_or(""),
+            ]].concat());
+            archivo.write_all(linea.as_bytes())?;
+            archivo.write_all(b"\r\n")?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Cargar las órdenes de compra desde un archivo CSV
+pub fn cargar_ordenes(ruta: &str) -> Result<Vec<OrdenCompra>, InventarioError> {
+    let contenido = fs::read_to_string(ruta)?;
+    let mut ordenes: Vec<OrdenCompra> = Vec::new();
+
+    for registro in leer_registros(&contenido)?.into_iter().skip(1) {
+        let partes = &registro.campos;
+        let error = |columna: usize, mensaje: String| InventarioError::Parseo {
+            linea: registro.linea,
+            columna,
+            mensaje,
+        };
+
+        if partes.len() < ENCABEZADO_ORDENES.len() {
+            return Err(error(
+                partes.len() + 1,
+                format!("se esperaban {} campos y hay {}", ENCABEZADO_ORDENES.len(), partes.len()),
+            ));
+        }
+
+        let numero = |indice: usize, nombre: &str| parsear_numero(&registro, indice, nombre)
+            .map_err(|mensaje| error(indice + 1, mensaje));
+
+        let id = numero(0, "orden_id")?;
+        // Las filas de una misma orden van seguidas
+        if ordenes.last().map(|o| o.id) != Some(id) {
+            let fecha = partes[2].trim().parse()
+                .map_err(|_| error(3, format!("fecha inválida: \"{}\"", partes[2])))?;
+            let estado = EstadoOrden::desde_clave(partes[3].trim())
+                .ok_or_else(|| error(4, format!("estado desconocido: \"{}\"", partes[3])))?;
+            ordenes.push(OrdenCompra {
+                id,
+                proveedor_id: numero(1, "proveedor_id")?,
+                fecha,
+                estado,
+                lineas: Vec::new(),
+                nota: Some(partes[4].clone()).filter(|n| !n.is_empty()),
+            });
+        }
+
+        if partes[5].trim().is_empty() {
+            continue;
+        }
+
+        let costo_unitario = match partes[8].trim() {
+            "" => None,
+            campo => {
+                let moneda = match partes[9].trim() {
+                    "" => Moneda::POR_DEFECTO,
+                    codigo => Moneda::parsear(codigo).map_err(|motivo| error(10, motivo))?,
+                };
+                Some(Dinero::parsear(campo, moneda).map_err(|motivo| error(9, format!("costo_unitario: {}", motivo)))?)
+            }
+        };
+        let linea = LineaOrden {
+            producto_id: numero(5, "producto_id")?,
+            cantidad: numero(6, "cantidad")?,
+            recibida: numero(7, "recibida")?,
+            costo_unitario,
+        };
+        if let Some(orden) = ordenes.last_mut() {
+            orden.lineas.push(linea);
+        }
+    }
+
+    Ok(ordenes)
+}
+
+/// Exportar un reporte de valuación a CSV: una línea por producto, una
+/// por tag y al final el total. Los montos van sin símbolo y el margen es
+/// un porcentaje.
+pub fn exportar_reporte(ruta: &str, reporte: &ReporteValuacion) -> Result<(), InventarioError> {
+    let mut archivo = File::create(ruta)?;
+
+    archivo.write_all(escribir_registro(&ENCABEZADO_REPORTE).as_bytes())?;
+    archivo.write_all(b"\r\n")?;
+
+    let monto = |dinero: Option<Dinero>| dinero.map(|d| d.monto()).unwrap_or_default();
+    let margen = |margen: Option<f64>| margen.map(|m| format!("{:.2}", m)).unwrap_or_default();
+    let moneda = reporte.moneda.codigo();
+
+    for producto in &reporte.productos {
+        let linea = escribir_registro(&[
+            "producto",
+            producto.id.to_string().as_str(),
+            producto.nombre.as_str(),
+            producto.stock.to_string().as_str(),
+            producto.precio.monto().as_str(),
+            monto(producto.costo_unitario).as_str(),
+            monto(producto.valor_costo).as_str(),
+            producto.valor_venta.monto().as_str(),
+            margen(producto.margen()).as_str(),
+            moneda,
+        ]);
+        archivo.write_all(linea.as_bytes())?;
+        archivo.write_all(b"\r\n")?;
+    }
+
+    let grupos = reporte.tags.iter()
+        .map(|(tag, totales)| ("tag", tag.as_str(), totales))
+        .chain([("total", "", &reporte.total)]);
+    for (tipo, nombre, totales) in grupos {
+        let Totales { unidades, valor_costo, valor_venta, .. } = totales;
+        let linea = escribir_registro(&[
+            tipo,
+            "",
+            nombre,
+            unidades.to_string().as_str(),
+            "",
+            "",
+            valor_costo.monto().as_str(),
+            valor_venta.monto().as_str(),
+            margen(totales.margen()).as_str(),
+            moneda,
+        ]);
+        archivo.write_all(linea.as_bytes())?;
+        archivo.write_all(b"\r\n")?;
+    }
+
+    Ok(())
+}
+
+/// Guardar el recibo de una venta como archivo de texto
+pub fn exportar_recibo(ruta: &str, venta: &Venta, formato: &FormatoMoneda) -> Result<(), InventarioError> {
+    fs::write(ruta, venta.recibo(formato))?;
+    Ok(())
+}
+
+/// Cargar los nombres de las ubicaciones desde un archivo CSV
+pub fn cargar_ubicaciones(ruta: &str) -> Result<Vec<String>, InventarioError> {
+    let contenido = fs::read_to_string(ruta)?;
+
+    Ok(leer_registros(&contenido)?
+        .into_iter()
+        .skip(1)
+        .filter_map(|registro| registro.campos.into_iter().next())
+        .filter(|nombre| !nombre.is_empty())
+        .collect())
+}
+
+/// Guardar el inventario completo en un archivo JSON
+pub fn guardar_en_json(ruta: &str, datos: &DatosInventario) -> Result<(), InventarioError> {
+    let archivo = File::create(ruta)?;
+
+    serde_json::to_writer_pretty(archivo, datos)
+        .map_err(|e| InventarioError::Io(e.into()))
+}
+
+/// Cargar el inventario completo desde un archivo JSON
+pub fn cargar_desde_json(ruta: &str) -> Result<DatosInventario, InventarioError> {
+    let contenido = fs::read_to_string(ruta)?;
+    let inventario: DatosInventario = serde_json::from_str(&contenido)
+        .map_err(|e| {
+            // serde_json ya agrega la posición al mensaje
+            let mut mensaje = e.to_string();
+            if let Some(pos) = mensaje.rfind(" at line ") {
+                mensaje.truncate(pos);
+            }
+            InventarioError::Parseo { linea: e.line(), columna: e.column(), mensaje }
+        })?;
+
+    let mut ids = HashSet::new();
+    for producto in &inventario.productos {
+        if !ids.insert(producto.id) {
+            return Err(InventarioError::Duplicado(producto.id));
+        }
+    }
+
+    Ok(inventario)
+}
+
+/// Cómo tratar las líneas con errores al cargar un archivo
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModoCarga {
+    /// Abortar la carga en el primer error
+    Estricto,
+    /// Rechazar o corregir las líneas con errores y seguir cargando
+    Tolerante,
+}
+
+/// Qué se hizo con una línea problemática
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccionIncidencia {
+    /// La línea se descartó
+    Rechazada,
+    /// La línea se cargó con algún campo corregido
+    Corregida,
+}
+
+/// Una línea del archivo que no se pudo cargar tal cual
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IncidenciaCarga {
+    pub linea: usize,
+    pub columna: usize,
+    pub motivo: String,
+    pub accion: AccionIncidencia,
+}
+
+/// Resumen de una carga: productos leídos y líneas rechazadas o corregidas
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReporteCarga {
+    pub productos_cargados: usize,
+    pub incidencias: Vec<IncidenciaCarga>,
+}
+
+impl ReporteCarga {
+    // En modo estricto la primera incidencia aborta la carga
+    fn registrar(&mut self, modo: ModoCarga, incidencia: IncidenciaCarga) -> Result<(), InventarioError> {
+        if modo == ModoCarga::Estricto {
+            return Err(InventarioError::Parseo {
+                linea: incidencia.linea,
+                columna: incidencia.columna,
+                mensaje: incidencia.motivo,
+            });
+        }
+
+        self.incidencias.push(incidencia);
+        Ok(())
+    }
+}
+
+/// Cargar productos desde un archivo CSV
+pub fn cargar_desde_archivo(ruta: &str, modo: ModoCarga) -> Result<(Vec<Producto>, ReporteCarga), InventarioError> {
+    let contenido = fs::read_to_string(ruta)?;
+    let mut productos = Vec::new();
+    let mut reporte = ReporteCarga::default();
+    let mut ids = HashSet::new();
+    // Claves de los códigos ya leídos, para detectar repetidos
+    let mut skus = HashSet::new();
+    let mut codigos_barras = HashSet::new();
+
+    let mut columnas = Columnas::sin_encabezado();
+
+    'registros: for (i, registro) in leer_registros(&contenido)?.into_iter().enumerate() {
+        let partes = &registro.campos;
+        let rechazar = |columna: usize, motivo: String| IncidenciaCarga {
+            linea: registro.linea,
+            columna,
+            motivo,
+            accion: AccionIncidencia::Rechazada,
+        };
+
+        // Los archivos con encabezado se leen por nombre de columna
+        // (los archivos antiguos no lo tienen)
+        if i == 0 && partes.first().map(String::as_str) == Some(ENCABEZADO[0]) {
+            columnas = Columnas::desde_encabezado(&registro)?;
+            continue;
+        }
+
+        let minimo = columnas.minimo();
+        if partes.len() < minimo {
+            let motivo = format!("se esperaban {} campos y hay {}", minimo, partes.len());
+            reporte.registrar(modo, rechazar(partes.len() + 1, motivo))?;
+            continue;
+        }
+
+        // Sin un ID válido y único la línea no se puede cargar
+        let id = match parsear_numero(&registro, columnas.id, "id") {
+            Ok(id) => id,
+            Err(motivo) => {
+                reporte.registrar(modo, rechazar(columnas.id + 1, motivo))?;
+                continue;
+            }
+        };
+        if ids.contains(&id) {
+            if modo == ModoCarga::Estricto {
+                return Err(InventarioError::Duplicado(id));
+            }
+            reporte.registrar(modo, rechazar(columnas.id + 1, format!("ID {} duplicado", id)))?;
+            continue;
+        }
+
+        // Sin columna de moneda (archivos antiguos) los precios son en dólares
+        let mut moneda = Moneda::POR_DEFECTO;
+        if let Some(indice) = columnas.moneda {
+            let campo = partes.get(indice).map(|c| c.trim()).unwrap_or_default();
+            if !campo.is_empty() {
+                match Moneda::parsear(campo) {
+                    Ok(leida) => moneda = leida,
+                    Err(motivo) => reporte.registrar(modo, IncidenciaCarga {
+                        linea: registro.linea,
+                        columna: indice + 1,
+                        motivo: format!("{}; se cargó en {}", motivo, moneda),
+                        accion: AccionIncidencia::Corregida,
+                    })?,
+                }
+            }
+        }
+
+        // Un precio o stock inválido se carga como 0, avisando en el reporte.
+        // Los archivos antiguos tienen precios enteros, que son unidades.
+        let precio = match Dinero::parsear(&partes[columnas.precio], moneda) {
+            Ok(precio) => precio,
+            Err(motivo) => {
+                reporte.registrar(modo, IncidenciaCarga {
+                    linea: registro.linea,
+                    columna: columnas.precio + 1,
+                    motivo: format!("precio: {}; se cargó como 0", motivo),
+                    accion: AccionIncidencia::Corregida,
+                })?;
+                Dinero::new(0, moneda)
+            }
+        };
+        let stock = match parsear_numero(&registro, columnas.stock, "stock") {
+            Ok(stock) => stock,
+            Err(motivo) => {
+                reporte.registrar(modo, IncidenciaCarga {
+                    linea: registro.linea,
+                    columna: columnas.stock + 1,
+                    motivo: format!("{}; se cargó como 0", motivo),
+                    accion: AccionIncidencia::Corregida,
+                })?;
+                0
+            }
+        };
+
+        // En archivos antiguos las tags venían sin comillas y ocupan
+        // varios campos a partir de la posición 4
+        let mut tags = HashSet::new();
+        let campos_tags = match columnas.tags {
+            Some(indice) if columnas.tags_hasta_el_final => &partes[indice..],
+            Some(indice) => &partes[indice..(indice + 1).min(partes.len())],
+            None => &[],
+        };
+        for campo in campos_tags {
+            match leer_tags(campo) {
+                Ok(leidas) => tags.extend(leidas),
+                // La posición dentro del campo de tags no sirve de mucho,
+                // así que se informa la del campo en el archivo
+                Err(motivo) => {
+                    let columna = columnas.tags.unwrap_or_default() + 1;
+                    reporte.registrar(modo, rechazar(columna, format!("tags: {}", motivo)))?;
+                    continue 'registros;
+                }
+            }
+        }
+
+        // Un punto de reorden inválido se descarta y el producto usa el heredado
+        let mut punto_reorden = None;
+        if let Some(indice) = columnas.punto_reorden {
+            let campo = partes.get(indice).map(|c| c.trim()).unwrap_or_default();
+            if !campo.is_empty() {
+                match parsear_numero(&registro, indice, "punto_reorden") {
+                    Ok(valor) => punto_reorden = Some(valor),
+                    Err(motivo) => reporte.registrar(modo, IncidenciaCarga {
+                        linea: registro.linea,
+                        columna: indice + 1,
+                        motivo: format!("{}; se cargó sin punto de reorden", motivo),
+                        accion: AccionIncidencia::Corregida,
+                    })?,
+                }
+            }
+        }
+
+        let mut existencias = BTreeMap::new();
+        if let Some(indice) = columnas.existencias {
+            let campo = partes.get(indice).map(String::as_str).unwrap_or_default();
+            match leer_existencias(campo) {
+                Ok(leidas) => existencias = leidas,
+                Err(motivo) => {
+                    reporte.registrar(modo, rechazar(indice + 1, format!("existencias: {}", motivo)))?;
+                    continue 'registros;
+                }
+            }
+        }
+
+        // El stock total no puede ser menor que lo repartido en ubicaciones
+        let mut stock = stock;
+        let repartido: u32 = existencias.values().sum();
+        if repartido > stock {
+            reporte.registrar(modo, IncidenciaCarga {
+                linea: registro.linea,
+                columna: columnas.stock + 1,
+                motivo: format!("stock {} menor que las existencias por ubicación; se cargó como {}", stock, repartido),
+                accion: AccionIncidencia::Corregida,
+            })?;
+            stock = repartido;
+        }
+
+        // Un costo inválido se descarta, como el punto de reorden
+        let mut costo = None;
+        if let Some(indice) = columnas.costo {
+            let campo = partes.get(indice).map(|c| c.trim()).unwrap_or_default();
+            if !campo.is_empty() {
+                match Dinero::parsear(campo, moneda) {
+                    Ok(valor) => costo = Some(valor),
+                    Err(motivo) => reporte.registrar(modo, IncidenciaCarga {
+                        linea: registro.linea,
+                        columna: indice + 1,
+                        motivo: format!("costo: {}; se cargó sin costo", motivo),
+                        accion: AccionIncidencia::Corregida,
+                    })?,
+                }
+            }
+        }
+
+        // Un proveedor inválido se descarta, como el costo
+        let mut proveedor_id = None;
+        if let Some(indice) = columnas.proveedor_id {
+            let campo = partes.get(indice).map(|c| c.trim()).unwrap_or_default();
+            if !campo.is_empty() {
+                match parsear_numero(&registro, indice, "proveedor_id") {
+                    Ok(valor) => proveedor_id = Some(valor),
+                    Err(motivo) => reporte.registrar(modo, IncidenciaCarga {
+                        linea: registro.linea,
+                        columna: indice + 1,
+                        motivo: format!("{}; se cargó sin proveedor", motivo),
+                        accion: AccionIncidencia::Corregida,
+                    })?,
+                }
+            }
+        }
+
+        // Una variante mal escrita se carga como producto independiente
+        let mut variante = None;
+        if let Some(indice) = columnas.variante_de {
+            let campo = partes.get(indice).map(|c| c.trim()).unwrap_or_default();
+            if !campo.is_empty() {
+                let leida = parsear_numero(&registro, indice, "variante_de").and_then(|padre_id| {
+                    let campo = columnas.atributos.and_then(|i| partes.get(i)).map(String::as_str).unwrap_or_default();
+                    let atributos = leer_atributos(campo).map_err(|motivo| format!("atributos: {}", motivo))?;
+                    let precio_propio = columnas.precio_propio.and_then(|i| partes.get(i))
+                        .is_some_and(|c| matches!(c.trim().to_lowercase().as_str(), "si" | "sí" | "1" | "true"));
+                    Ok(Variante { padre_id, atributos, precio_propio })
+                });
+                match leida {
+                    Ok(leida) => variante = Some(leida),
+                    Err(motivo) => reporte.registrar(modo, IncidenciaCarga {
+                        linea: registro.linea,
+                        columna: indice + 1,
+                        motivo: format!("{}; se cargó como producto sin variantes", motivo),
+                        accion: AccionIncidencia::Corregida,
+                    })?,
+                }
+            }
+        }
+
+        // Un código inválido o repetido se descarta, avisando en el reporte
+        let mut leer_codigo = |indice: Option<usize>,
+                               nombre: &str,
+                               validar: fn(&str) -> Result<(), String>,
+                               clave: fn(&str) -> String,
+                               vistos: &mut HashSet<String>| {
+            let campo = indice.and_then(|i| partes.get(i)).map(|c| c.trim()).unwrap_or_default();
+            if campo.is_empty() {
+                return Ok(None);
+            }
+
+            let motivo = match validar(campo) {
+                Err(motivo) => motivo,
+                Ok(()) if vistos.insert(clave(campo)) => return Ok(Some(campo.to_string())),
+                Ok(()) => format!("{} \"{}\" repetido", nombre, campo),
+            };
+            reporte.registrar(modo, IncidenciaCarga {
+                linea: registro.linea,
+                columna: indice.unwrap_or_default() + 1,
+                motivo: format!("{}; se cargó sin {}", motivo, nombre),
+                accion: AccionIncidencia::Corregida,
+            })?;
+            Ok::<_, InventarioError>(None)
+        };
+        let sku = leer_codigo(columnas.sku, "SKU", validar_sku, clave_sku, &mut skus)?;
+        let codigo_barras = leer_codigo(
+            columnas.codigo_barras, "código de barras", validar_codigo_barras, clave_codigo_barras, &mut codigos_barras,
+        )?;
+
+        let producto = Producto {
+            id,
+            nombre: partes[columnas.nombre].clone(),
+            precio,
+            stock,
+            tags,
+            punto_reorden,
+            existencias,
+            sku,
+            codigo_barras,
+            costo,
+            proveedor_id,
+            variante,
+        };
+
+        ids.insert(id);
+        productos.push(producto);
+    }
+
+    reporte.productos_cargados = productos.len();
+    Ok((productos, reporte))
+}
+
+/// Posición de cada columna en un CSV de productos
+struct Columnas {
+    id: usize,
+    nombre: usize,
+    precio: usize,
+    stock: usize,
+    tags: Option<usize>,
+    punto_reorden: Option<usize>,
+    existencias: Option<usize>,
+    sku: Option<usize>,
+    codigo_barras: Option<usize>,
+    moneda: Option<usize>,
+    costo: Option<usize>,
+    proveedor_id: Option<usize>,
+    variante_de: Option<usize>,
+    atributos: Option<usize>,
+    precio_propio: Option<usize>,
+    /// En archivos sin encabezado las tags ocupan todos los campos desde `tags`
+    tags_hasta_el_final: bool,
+}
+
+impl Columnas {
+    /// Formato antiguo: `id,nombre,precio,stock,tag1,tag2,...`
+    fn sin_encabezado() -> Self {
+        Self {
+            id: 0,
+            nombre: 1,
+            precio: 2,
+            stock: 3,
+            tags: Some(4),
+            punto_reorden: None,
+            existencias: None,
+            sku: None,
+            codigo_barras: None,
+            moneda: None,
+            costo: None,
+            proveedor_id: None,
+            variante_de: None,
+            atributos: None,
+            precio_propio: None,
+            tags_hasta_el_final: true,
+        }
+    }
+
+    /// Ubicar las columnas por nombre; las desconocidas se ignoran
+    fn desde_encabezado(registro: &Registro) -> Result<Self, InventarioError> {
+        let posicion = |nombre: &str| registro.campos.iter().position(|c| c.trim() == nombre);
+        let obligatoria = |nombre: &str| posicion(nombre).ok_or_else(|| InventarioError::Parseo {
+            linea: registro.linea,
+            columna: registro.campos.len() + 1,
+            mensaje: format!("falta la columna \"{}\" en el encabezado", nombre),
+        });
+
+        Ok(Self {
+            id: obligatoria("id")?,
+            nombre: obligatoria("nombre")?,
+            precio: obligatoria("precio")?,
+            stock: obligatoria("stock")?,
+            tags: posicion("tags"),
+            punto_reorden: posicion("punto_reorden"),
+            existencias: posicion("existencias"),
+            sku: posicion("sku"),
+            codigo_barras: posicion("codigo_barras"),
+            moneda: posicion("moneda"),
+            costo: posicion("costo"),
+            proveedor_id: posicion("proveedor_id"),
+            variante_de: posicion("variante_de"),
+            atributos: posicion("atributos"),
+            precio_propio: posicion("precio_propio"),
+            tags_hasta_el_final: false,
+        })
+    }
+
+    /// Cantidad de campos que debe tener cada línea
+    fn minimo(&self) -> usize {
+        let mut minimo = self.id.max(self.nombre).max(self.precio).max(self.stock);
+        if self.tags_hasta_el_final {
+            minimo = minimo.max(self.tags.unwrap_or_default());
+        }
+        minimo + 1
+    }
+}
+
+/// Interpretar como número el campo `indice` de un registro
+fn parsear_numero(registro: &Registro, indice: usize, nombre: &str) -> Result<u32, String> {
+    let campo = &registro.campos[indice];
+    campo.trim().parse()
+        .map_err(|_| format!("{} inválido: \"{}\"", nombre, campo))
+}
+
+/// Escapar un campo, poniéndolo entre comillas solo si hace falta
+fn escribir_campo(campo: &str) -> String {
+    if campo.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", campo.replace('"', "\"\""))
+    } else {
+        campo.to_string()
+    }
+}
+
+/// Unir campos en un registro CSV (sin el salto de línea final)
+fn escribir_registro(campos: &[&str]) -> String {
+    campos.iter()
+        .map(|campo| escribir_campo(campo))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Codificar las tags como registro CSV; los campos vacíos van entre comillas
+/// para distinguir una tag vacía de la ausencia de tags
+fn escribir_tags(tags: &[&str]) -> String {
+    tags.iter()
+        .map(|tag| if tag.is_empty() { "\"\"".to_string() } else { escribir_campo(tag) })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Decodificar el campo de tags escrito por `escribir_tags`
+fn leer_tags(campo: &str) -> Result<Vec<String>, String> {
+    if campo.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    match leer_registros(campo) {
+        Ok(registros) => Ok(registros.into_iter().flat_map(|registro| registro.campos).collect()),
+        Err(InventarioError::Parseo { mensaje, .. }) => Err(mensaje),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// Decodificar el campo de existencias (`ubicación=cantidad`, como las tags)
+fn leer_existencias(campo: &str) -> Result<BTreeMap<String, u32>, String> {
+    let mut existencias = BTreeMap::new();
+
+    for entrada in leer_tags(campo)? {
+        let (ubicacion, cantidad) = entrada.rsplit_once('=')
+            .ok_or_else(|| format!("falta la cantidad en \"{}\"", entrada))?;
+        let cantidad: u32 = cantidad.trim().parse()
+            .map_err(|_| format!("cantidad inválida en \"{}\"", entrada))?;
+        if cantidad > 0 {
+            existencias.insert(ubicacion.to_string(), cantidad);
+        }
+    }
+
+    Ok(existencias)
+}
+
+/// Decodificar los atributos de una variante (`atributo=valor`)
+fn leer_atributos(campo: &str) -> Result<BTreeMap<String, String>, String> {
+    leer_tags(campo)?
+        .into_iter()
+        .map(|entrada| {
+            entrada.split_once('=')
+                .map(|(atributo, valor)| (atributo.to_string(), valor.to_string()))
+                .ok_or_else(|| format!("falta el valor en \"{}\"", entrada))
+        })
+        .collect()
+}
+
+/// Un registro CSV junto con la línea del archivo en la que empieza
+struct Registro {
+    linea: usize,
+    campos: Vec<String>,
+}
+
+/// Separar el contenido en registros y campos según RFC 4180.
+/// Acepta finales de línea `\r\n` y `\n`, e ignora las líneas vacías.
+fn leer_registros(contenido: &str) -> Result<Vec<Registro>, InventarioError> {
+    let mut registros = Vec::new();
+    let mut registro: Vec<String> = Vec::new();
+    let mut campo = String::new();
+    let mut entre_comillas = false;
+    let mut campo_iniciado = false;
+    let mut linea = 1;
+    let mut linea_inicio = 1;
+    let mut chars = contenido.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c == '\n' {
+            linea += 1;
+        }
+
+        if entre_comillas {
+            if c == '"' {
+                if chars.peek() == Some(&'"') {
+                    campo.push('"');
+                    chars.next();
+                } else {
+                    entre_comillas = false;
+                }
+            } else {
+                campo.push(c);
+            }
+            continue;
+        }
+
+        match c {
+            '"' => {
+                entre_comillas = true;
+                campo_iniciado = true;
+            }
+            ',' => {
+                registro.push(std::mem::take(&mut campo));
+                campo_iniciado = true;
+            }
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' => {
+                if campo_iniciado || !campo.is_empty() {
+                    registro.push(std::mem::take(&mut campo));
+                    registros.push(Registro {
+                        linea: linea_inicio,
+                        campos: std::mem::take(&mut registro),
+                    });
+                }
+                campo_iniciado = false;
+                linea_inicio = linea;
+            }
+            _ => {
+                campo.push(c);
+                campo_iniciado = true;
+            }
+        }
+    }
+
+    if entre_comillas {
+        return Err(InventarioError::Parseo {
+            linea: linea_inicio,
+            columna: registro.len() + 1,
+            mensaje: "comillas sin cerrar".to_string(),
+        });
+    }
+
+    if campo_iniciado || !campo.is_empty() {
+        registro.push(campo);
+        registros.push(Registro {
+            linea: linea_inicio,
+            campos: registro,
+        });
+    }
+
+    Ok(registros)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn producto(id: u32, nombre: &str, precio: u64, stock: u32, tags: &[&str]) -> Producto {
+        Producto {
+            id,
+            nombre: nombre.to_string(),
+            precio: Dinero::desde_unidades(precio, Moneda::POR_DEFECTO),
+            stock,
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    fn ida_y_vuelta(nombre_archivo: &str, productos: &[Producto]) -> Vec<Producto> {
+        let ruta = std::env::temp_dir().join(nombre_archivo);
+        let ruta = ruta.to_str().unwrap();
+
+        let refs: Vec<&Producto> = productos.iter().collect();
+        guardar_en_archivo(ruta, &refs).unwrap();
+        let (cargados, reporte) = cargar_desde_archivo(ruta, ModoCarga::Estricto).unwrap();
+        fs::remove_file(ruta).unwrap();
+        assert!(reporte.incidencias.is_empty());
+
+        cargados
+    }
+
+    #[test]
+    fn productos_sobreviven_guardar_y_cargar() {
+        let productos = vec![
+            producto(1, "Cable, USB-C", 15, 40, &["electrónica", "cables"]),
+            Producto {
+                existencias: [("Bodega, norte".to_string(), 2), ("Tienda=1".to_string(), 1)].into(),
+                sku: Some("MON-27".to_string()),
+                codigo_barras: Some("036000291452".to_string()),
+                costo: Some(Dinero::new(21_075, Moneda::POR_DEFECTO)),
+                ..producto(2, "Monitor 27\" \"Pro\"", 350, 3, &["periféricos"])
+            },
+            producto(3, "Nombre\ncon salto\r\nde línea", 0, 0, &[]),
+            producto(4, "", u32::MAX.into(), u32::MAX, &["tag, con coma", "\"citada\"", ""]),
+            Producto {
+                precio: Dinero::new(1_999, Moneda::parsear("EUR").unwrap()),
+                ..producto(5, " espacios ", 0, 2, &[" a ", "b\nc"])
+            },
+            Producto {
+                precio: Dinero::new(1_500, Moneda::parsear("JPY").unwrap()),
+                ..producto(6, "Sin decimales", 0, 1, &[])
+            },
+            Producto {
+                variante: Some(Variante {
+                    padre_id: 1,
+                    // El valor puede llevar `=`; el nombre no (ver `agregar_variante`)
+                    atributos: [
+                        ("color".to_string(), "rojo, oscuro".to_string()),
+                        ("talla".to_string(), "M=38".to_string()),
+                        ("uso".to_string(), "a=b=c".to_string()),
+                    ].into(),
+                    precio_propio: true,
+                }),
+                ..producto(7, "Cable, USB-C", 18, 5, &["electrónica", "cables"])
+            },
+        ];
+
+        let cargados = ida_y_vuelta("rustiventario_ida_y_vuelta.csv", &productos);
+
+        assert_eq!(cargados, productos);
+    }
+
+    #[test]
+    fn escribe_encabezado_y_comillas() {
+        let ruta = std::env::temp_dir().join("rustiventario_encabezado.csv");
+        let ruta = ruta.to_str().unwrap();
+        let p = producto(7, "Cable, USB-C", 15, 40, &["b", "a"]);
+
+        guardar_en_archivo(ruta, &[&p]).unwrap();
+        let contenido = fs::read_to_string(ruta).unwrap();
+        fs::remove_file(ruta).unwrap();
+
+        assert_eq!(contenido, "id,nombre,precio,stock,tags,punto_reorden,existencias,sku,codigo_barras,moneda,costo,proveedor_id,variante_de,atributos,precio_propio\r\n7,\"Cable, USB-C\",15.00,40,\"a,b\",,,,,USD,,,,,\r\n");
+    }
+
+    #[test]
+    fn carga_formato_antiguo_sin_encabezado() {
+        let ruta = std::env::temp_dir().join("rustiventario_antiguo.csv");
+        let ruta = ruta.to_str().unwrap();
+        fs::write(ruta, "1,Laptop Gamer,1200,10,electrónica,computadoras\n2,Mouse,50,2,\n").unwrap();
+
+        let (mut cargados, _) = cargar_desde_archivo(ruta, ModoCarga::Estricto).unwrap();
+        fs::remove_file(ruta).unwrap();
+        cargados.sort_by_key(|p| p.id);
+
+        assert_eq!(cargados, vec![
+            producto(1, "Laptop Gamer", 1200, 10, &["electrónica", "computadoras"]),
+            producto(2, "Mouse", 50, 2, &[]),
+        ]);
+    }
+
+    #[test]
+    fn informa_linea_y_columna_de_campos_invalidos() {
+        let ruta = std::env::temp_dir().join("rustiventario_invalido.csv");
+        let ruta = ruta.to_str().unwrap();
+        fs::write(ruta, "id,nombre,precio,stock,tags\r\n1,\"Varias\nlíneas\",10,1,\r\n2,Mouse,abc,2,\r\n").unwrap();
+
+        let resultado = cargar_desde_archivo(ruta, ModoCarga::Estricto);
+        fs::remove_file(ruta).unwrap();
+
+        match resultado {
+            Err(InventarioError::Parseo { linea, columna, .. }) => assert_eq!((linea, columna), (4, 3)),
+            otro => panic!("se esperaba un error de parseo, se obtuvo {:?}", otro),
+        }
+    }
+
+    #[test]
+    fn modo_tolerante_reporta_lineas_rechazadas_y_corregidas() {
+        let ruta = std::env::temp_dir().join("rustiventario_tolerante.csv");
+        let ruta = ruta.to_str().unwrap();
+        fs::write(ruta, "id,nombre,precio,stock,tags\n1,Laptop,abc,10,\n2,Corta\nx,Mouse,50,2,\n1,Repetido,5,5,\n3,Monitor,350,7,a\n").unwrap();
+
+        let (cargados, reporte) = cargar_desde_archivo(ruta, ModoCarga::Tolerante).unwrap();
+        fs::remove_file(ruta).unwrap();
+
+        assert_eq!(cargados, vec![
+            producto(1, "Laptop", 0, 10, &[]),
+            producto(3, "Monitor", 350, 7, &["a"]),
+        ]);
+        assert_eq!(reporte.productos_cargados, 2);
+
+        let resumen: Vec<(usize, usize, AccionIncidencia)> = reporte.incidencias
+            .iter()
+            .map(|i| (i.linea, i.columna, i.accion))
+            .collect();
+        assert_eq!(resumen, vec![
+            (2, 3, AccionIncidencia::Corregida),
+            (3, 3, AccionIncidencia::Rechazada),
+            (4, 1, AccionIncidencia::Rechazada),
+            (5, 1, AccionIncidencia::Rechazada),
+        ]);
+    }
+
+    #[test]
+    fn codigos_invalidos_o_repetidos_se_descartan() {
+        let ruta = std::env::temp_dir().join("rustiventario_codigos.csv");
+        let ruta = ruta.to_str().unwrap();
+        fs::write(ruta, "id,nombre,precio,stock,sku,codigo_barras\n\
+            1,Teclado,10,1,tec-1,4006381333931\n\
+            2,Mouse,10,1,TEC-1,0036000291452\n\
+            3,Monitor,10,1,MON 3,036000291452\n\
+            4,Cable,10,1,,4006381333932\n").unwrap();
+
+        let (cargados, reporte) = cargar_desde_archivo(ruta, ModoCarga::Tolerante).unwrap();
+        fs::remove_file(ruta).unwrap();
+
+        let codigos: Vec<(Option<&str>, Option<&str>)> = cargados.iter()
+            .map(|p| (p.sku.as_deref(), p.codigo_barras.as_deref()))
+            .collect();
+        assert_eq!(codigos, [
+            (Some("tec-1"), Some("4006381333931")),
+            (None, Some("0036000291452")),
+            (None, None),
+            (None, None),
+        ]);
+        let resumen: Vec<(usize, usize)> = reporte.incidencias.iter().map(|i| (i.linea, i.columna)).collect();
+        assert_eq!(resumen, [(3, 5), (4, 5), (4, 6), (5, 6)]);
+    }
+
+    #[test]
+    fn json_conserva_productos_movimientos_y_siguiente_id() {
+        let ruta = std::env::temp_dir().join("rustiventario_ida_y_vuelta.json");
+        let ruta = ruta.to_str().unwrap();
+        let productos = vec![
+            producto(1, "Cable, USB-C", 15, 40, &["electrónica", "cables"]),
+            producto(4, "Monitor 27\"", 350, 3, &[]),
+        ];
+
+        let datos = DatosInventario {
+            siguiente_id: 9,
+            productos,
+            movimientos: vec![
+                Movimiento { producto_id: 1, fecha: 1_700_000_000, delta: -2, motivo: MotivoMovimiento::Venta, nota: None, ubicacion: None, costo_unitario: None },
+            ],
+            configuracion: ConfiguracionStock::default(),
+            ubicaciones: Vec::new(),
+            siguiente_proveedor_id: 3,
+            siguiente_orden_id: 5,
+            ..Default::default()
+        };
+
+        guardar_en_json(ruta, &datos).unwrap();
+        let cargados = cargar_desde_json(ruta).unwrap();
+        fs::remove_file(ruta).unwrap();
+
+        assert_eq!(cargados, datos);
+    }
+
+    #[test]
+    fn csv_guarda_movimientos_en_archivo_aparte() {
+        let ruta = std::env::temp_dir().join("rustiventario_con_historial.csv");
+        let ruta = ruta.to_str().unwrap();
+        let datos = DatosInventario {
+            siguiente_id: 2,
+            productos: vec![Producto { proveedor_id: Some(1), ..producto(1, "Laptop", 1200, 7, &["a"]) }],
+            movimientos: vec![
+                Movimiento {
+                    producto_id: 1,
+                    fecha: 1_700_000_000,
+                    delta: 10,
+                    motivo: MotivoMovimiento::Compra,
+                    nota: None,
+                    ubicacion: Some("Bodega".to_string()),
+                    costo_unitario: Some(Dinero::new(95_000, Moneda::parsear("EUR").unwrap())),
+                },
+                Movimiento { producto_id: 1, fecha: 1_700_000_100, delta: -3, motivo: MotivoMovimiento::Venta, nota: Some("Pedido 12, mostrador".to_string()), ubicacion: None, costo_unitario: None },
+            ],
+            configuracion: ConfiguracionStock::default(),
+            ubicaciones: vec!["Bodega".to_string(), "Tienda, centro".to_string()],
+            proveedores: vec![
+                Proveedor { id: 1, nombre: "Distribuidora, Sur".to_string(), contacto: Some("ventas@sur.com".to_string()) },
+                Proveedor { id: 2, nombre: "Mayorista".to_string(), contacto: None },
+            ],
+            ordenes: vec![
+                OrdenCompra {
+                    id: 1,
+                    proveedor_id: 1,
+                    fecha: 1_700_000_200,
+                    estado: EstadoOrden::RecibidaParcial,
+                    lineas: vec![
+                        LineaOrden { producto_id: 1, cantidad: 10, recibida: 4, costo_unitario: Some(Dinero::new(95_000, Moneda::POR_DEFECTO)) },
+                        LineaOrden::new(2, 3, None),
+                    ],
+                    nota: Some("Urgente".to_string()),
+                },
+                OrdenCompra { id: 2, proveedor_id: 2, fecha: 1_700_000_300, estado: EstadoOrden::Borrador, lineas: Vec::new(), nota: None },
+            ],
+            siguiente_proveedor_id: 3,
+            siguiente_orden_id: 3,
+        };
+
+        guardar_datos(ruta, &datos).unwrap();
+        let (cargados, _) = cargar_datos(ruta, ModoCarga::Estricto).unwrap();
+        fs::remove_file(ruta).unwrap();
+        fs::remove_file(ruta_movimientos(ruta)).unwrap();
+        fs::remove_file(ruta_umbrales(ruta)).unwrap();
+        fs::remove_file(ruta_ubicaciones(ruta)).unwrap();
+        fs::remove_file(ruta_proveedores(ruta)).unwrap();
+        fs::remove_file(ruta_ordenes(ruta)).unwrap();
+
+        assert_eq!(cargados, datos);
+    }
+}
@@ -0,0 +1,82 @@
+// Pruebas de la API pública, usada como la usaría otro programa
+
+use std::fs;
+use std::path::Path;
+
+use rustiventario::{
+    CsvStorage, Dinero, EventoInventario, InventarioError, InventarioManager, ModoCarga, Moneda, MotivoMovimiento,
+    SqliteStorage,
+};
+
+fn usd(texto: &str) -> Dinero {
+    Dinero::parsear(texto, Moneda::POR_DEFECTO).unwrap()
+}
+
+fn ruta_temporal(nombre: &str) -> String {
+    std::env::temp_dir().join(nombre).to_string_lossy().to_string()
+}
+
+// Borrar un CSV de prueba junto con sus archivos acompañantes
+fn borrar_csv(ruta: &str) {
+    let _ = fs::remove_file(ruta);
+    for sufijo in ["movimientos", "umbrales", "ubicaciones", "proveedores", "ordenes"] {
+        let _ = fs::remove_file(Path::new(ruta).with_extension(format!("{}.csv", sufijo)));
+    }
+}
+
+#[test]
+fn guardar_y_cargar_conserva_el_inventario() {
+    let ruta = ruta_temporal("rustiventario_api.csv");
+    let mut inventario = InventarioManager::new();
+    inventario.agregar_ubicacion("Tienda").unwrap();
+    let id = inventario.agregar_producto("Café, molido".to_string(), usd("10.50"), 4, vec!["bebidas".to_string()], Some(2)).unwrap();
+    inventario.transferir(id, None, Some("Tienda"), 3, None).unwrap();
+    inventario.guardar_inventario(&ruta).unwrap();
+
+    let mut copia = InventarioManager::new();
+    let reporte = copia.cargar_inventario(&ruta, ModoCarga::Estricto).unwrap();
+    borrar_csv(&ruta);
+
+    assert!(reporte.incidencias.is_empty());
+    assert_eq!(copia.datos(), inventario.datos());
+    assert_eq!(copia.buscar_por_nombre("cafe")[0].stock_en(Some("Tienda")), 3);
+}
+
+// Hacer cambios, soltar el gestor y comprobar que al reabrir siguen ahí
+fn comprobar_reapertura(abrir: impl Fn() -> Result<InventarioManager, InventarioError>) {
+    let mut inventario = abrir().unwrap();
+    let id = inventario.agregar_producto("Lámpara".to_string(), usd("25"), 6, Vec::new(), None).unwrap();
+    inventario.registrar_movimiento(id, -2, MotivoMovimiento::Venta, Some("mostrador".to_string())).unwrap();
+    drop(inventario);
+
+    let reabierto = abrir().unwrap();
+    assert_eq!(reabierto.obtener_producto(id).unwrap().stock, 4);
+    assert_eq!(reabierto.movimientos_de(id).len(), 2);
+}
+
+#[test]
+fn los_almacenamientos_guardan_cada_cambio() {
+    let ruta_sqlite = ruta_temporal("rustiventario_api.db");
+    let ruta_csv = ruta_temporal("rustiventario_api_storage.csv");
+    let _ = fs::remove_file(&ruta_sqlite);
+    borrar_csv(&ruta_csv);
+
+    comprobar_reapertura(|| InventarioManager::con_almacenamiento(Box::new(SqliteStorage::abrir(&ruta_sqlite)?)));
+    comprobar_reapertura(|| InventarioManager::con_almacenamiento(Box::new(CsvStorage::new(&ruta_csv))));
+
+    fs::remove_file(&ruta_sqlite).unwrap();
+    borrar_csv(&ruta_csv);
+}
+
+#[test]
+fn los_suscriptores_se_enteran_del_bajo_stock() {
+    let mut inventario = InventarioManager::new();
+    let eventos = inventario.suscribir();
+    let id = inventario.agregar_producto("Tinta".to_string(), usd("3"), 5, Vec::new(), Some(2)).unwrap();
+    inventario.registrar_venta(&[(id, 3)], None, None).unwrap();
+
+    let bajo_stock: Vec<_> = eventos.try_iter()
+        .filter(|e| matches!(e, EventoInventario::BajoStock { .. }))
+        .collect();
+    assert_eq!(bajo_stock, [EventoInventario::BajoStock { id, stock: 2 }]);
+}
@@ -1,5 +0,0 @@
-// aqui se maneja lo que usaremos para el inventario 
-
-pub mod manager;
-
-pub use manager::InventarioManager;
@@ -1,3 +0,0 @@
-pub mod producto;
-
-pub use producto::Producto;